@@ -1,69 +1,241 @@
+#![allow(clippy::redundant_closure)]
+
 use criterion::{criterion_group, criterion_main, Criterion};
 use rust_async_bench::run;
+use std::time::Duration;
 
 fn criterion_benchmark(c: &mut Criterion) {
-    run::run_manual(false, |r| {
+    let config = run::BenchConfig::DEFAULT;
+
+    run::run_manual(config, false, run::Scenario::DEFAULT, |r| {
         c.bench_function("manual", |b| b.iter(|| r()));
     });
 
-    run::run_nonbox(false, |r| {
+    run::run_callback(config, false, run::Scenario::DEFAULT, |r| {
+        c.bench_function("callback", |b| b.iter(|| r()));
+    });
+
+    run::run_nonbox(config, false, run::Scenario::DEFAULT, |r| {
         c.bench_function("nonbox", |b| b.iter(|| r()));
     });
 
-    run::run_callerbox(false, |r| {
+    run::run_nonbox_timeout(config, false, run::Scenario::DEFAULT, |r| {
+        c.bench_function("nonbox+timeout", |b| b.iter(|| r()));
+    });
+
+    run::run_nonbox_semaphore(config, false, run::Scenario::DEFAULT, |r| {
+        c.bench_function("nonbox+sem", |b| b.iter(|| r()));
+    });
+
+    run::run_nonbox_pingpong(config, false, run::Scenario::DEFAULT, |r| {
+        c.bench_function("nonbox+pingpong", |b| b.iter(|| r()));
+    });
+
+    run::run_nonbox_growable(config, false, run::Scenario::DEFAULT, |r| {
+        c.bench_function("nonbox+grow", |b| b.iter(|| r()));
+    });
+
+    run::run_nonbox_handwritten(config, false, run::Scenario::DEFAULT, |r| {
+        c.bench_function("nonbox+handwritten", |b| b.iter(|| r()));
+    });
+
+    run::run_callerbox(config, false, run::Scenario::DEFAULT, |r| {
         c.bench_function("callerbox", |b| b.iter(|| r()));
     });
 
-    run::run_large_nonbox(false, |r| {
+    run::run_large_nonbox(config, false, run::Scenario::DEFAULT, |r| {
         c.bench_function("large+nonbox", |b| b.iter(|| r()));
     });
 
-    run::run_box(false, |r| {
+    run::run_box(config, false, run::Scenario::DEFAULT, |r| {
         c.bench_function("box", |b| b.iter(|| r()));
     });
 
-    run::run_box_callerbox(false, |r| {
+    run::run_box_growable(config, false, run::Scenario::DEFAULT, |r| {
+        c.bench_function("box+grow", |b| b.iter(|| r()));
+    });
+
+    run::run_box_callerbox(config, false, run::Scenario::DEFAULT, |r| {
         c.bench_function("box+callerbox", |b| b.iter(|| r()));
     });
 
-    run::run_large_box(false, |r| {
+    run::run_large_box(config, false, run::Scenario::DEFAULT, |r| {
         c.bench_function("large+box", |b| b.iter(|| r()));
     });
 
-    run::run_box_rc(false, run::BoxRcMode::RcWaker, |r| {
-        c.bench_function("box+rc", |b| b.iter(|| r()));
+    run::run_box_rc(
+        config,
+        false,
+        run::Scenario::DEFAULT,
+        run::BoxRcMode::RcWaker,
+        |r| {
+            c.bench_function("box+rc", |b| b.iter(|| r()));
+        },
+    );
+
+    run::run_box_rc(
+        config,
+        false,
+        run::Scenario::DEFAULT,
+        run::BoxRcMode::CheckedRcWaker,
+        |r| {
+            c.bench_function("box+chkrc", |b| b.iter(|| r()));
+        },
+    );
+
+    run::run_box_rc(
+        config,
+        false,
+        run::Scenario::DEFAULT,
+        run::BoxRcMode::ArcWaker,
+        |r| {
+            c.bench_function("box+arc", |b| b.iter(|| r()));
+        },
+    );
+
+    run::run_box_arc_mt(config, false, run::Scenario::DEFAULT, |r| {
+        c.bench_function("box+arc+mt", |b| b.iter(|| r()));
     });
 
-    run::run_box_rc(false, run::BoxRcMode::CheckedRcWaker, |r| {
-        c.bench_function("box+chkrc", |b| b.iter(|| r()));
+    run::run_manual(config, false, run::Scenario::PARTIAL, |r| {
+        c.bench_function("manual+partial", |b| b.iter(|| r()));
     });
 
-    run::run_box_rc(false, run::BoxRcMode::ArcWaker, |r| {
-        c.bench_function("box+arc", |b| b.iter(|| r()));
+    run::run_nonbox(config, false, run::Scenario::PARTIAL, |r| {
+        c.bench_function("nonbox+partial", |b| b.iter(|| r()));
     });
 
-    run::run_manual(true, |r| {
-        c.bench_function("manual+syscalls", |b| b.iter(|| r()));
+    run::run_box(config, false, run::Scenario::PARTIAL, |r| {
+        c.bench_function("box+partial", |b| b.iter(|| r()));
     });
 
-    run::run_nonbox(true, |r| {
-        c.bench_function("nonbox+syscalls", |b| b.iter(|| r()));
+    let http = run::BenchConfig {
+        protocol: run::Protocol::Http,
+        ..config
+    };
+
+    run::run_manual(http, false, run::Scenario::HTTP, |r| {
+        c.bench_function("manual+http", |b| b.iter(|| r()));
     });
 
-    run::run_box(true, |r| {
-        c.bench_function("box+syscalls", |b| b.iter(|| r()));
+    run::run_nonbox(http, false, run::Scenario::HTTP, |r| {
+        c.bench_function("nonbox+http", |b| b.iter(|| r()));
+    });
+
+    run::run_box(http, false, run::Scenario::HTTP, |r| {
+        c.bench_function("box+http", |b| b.iter(|| r()));
+    });
+
+    run::run_manual_epoll(config, |r| {
+        c.bench_function("manual+epoll", |b| b.iter(|| r()));
+    });
+
+    run::run_nonbox_epoll(config, |r| {
+        c.bench_function("nonbox+epoll", |b| b.iter(|| r()));
     });
 
-    run::run_box_rc(true, run::BoxRcMode::RcWaker, |r| {
-        c.bench_function("box+rc+syscalls", |b| b.iter(|| r()));
+    run::run_blocking(config, |r| {
+        c.bench_function("blocking", |b| b.iter(|| r()));
     });
 
-    run::run_box_rc(true, run::BoxRcMode::CheckedRcWaker, |r| {
-        c.bench_function("box+chkrc+syscalls", |b| b.iter(|| r()));
+    run::run_blocking_pool(config, |r| {
+        c.bench_function("blocking+pool", |b| b.iter(|| r()));
+    });
+
+    run::run_manual_tcp(config, |r| {
+        c.bench_function("manual+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_nonbox_tcp(config, |r| {
+        c.bench_function("nonbox+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_callerbox_tcp(config, |r| {
+        c.bench_function("callerbox+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_large_nonbox_tcp(config, |r| {
+        c.bench_function("large+nonbox+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_box_tcp(config, |r| {
+        c.bench_function("box+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_box_callerbox_tcp(config, |r| {
+        c.bench_function("box+callerbox+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_large_box_tcp(config, |r| {
+        c.bench_function("large+box+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_box_rc_tcp(config, run::BoxRcMode::RcWaker, |r| {
+        c.bench_function("box+rc+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_box_rc_tcp(config, run::BoxRcMode::CheckedRcWaker, |r| {
+        c.bench_function("box+chkrc+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_box_rc_tcp(config, run::BoxRcMode::ArcWaker, |r| {
+        c.bench_function("box+arc+tcp", |b| b.iter(|| r()));
+    });
+
+    run::run_manual(config, true, run::Scenario::DEFAULT, |r| {
+        c.bench_function("manual+syscalls", |b| b.iter(|| r()));
+    });
+
+    run::run_nonbox(config, true, run::Scenario::DEFAULT, |r| {
+        c.bench_function("nonbox+syscalls", |b| b.iter(|| r()));
+    });
+
+    run::run_box(config, true, run::Scenario::DEFAULT, |r| {
+        c.bench_function("box+syscalls", |b| b.iter(|| r()));
     });
 
-    run::run_box_rc(true, run::BoxRcMode::ArcWaker, |r| {
-        c.bench_function("box+arc+syscalls", |b| b.iter(|| r()));
+    run::run_box_rc(
+        config,
+        true,
+        run::Scenario::DEFAULT,
+        run::BoxRcMode::RcWaker,
+        |r| {
+            c.bench_function("box+rc+syscalls", |b| b.iter(|| r()));
+        },
+    );
+
+    run::run_box_rc(
+        config,
+        true,
+        run::Scenario::DEFAULT,
+        run::BoxRcMode::CheckedRcWaker,
+        |r| {
+            c.bench_function("box+chkrc+syscalls", |b| b.iter(|| r()));
+        },
+    );
+
+    run::run_box_rc(
+        config,
+        true,
+        run::Scenario::DEFAULT,
+        run::BoxRcMode::ArcWaker,
+        |r| {
+            c.bench_function("box+arc+syscalls", |b| b.iter(|| r()));
+        },
+    );
+
+    // about 10us of application work per request
+    let work = run::BenchConfig {
+        work: run::calibrate_work(Duration::from_micros(10)),
+        ..config
+    };
+
+    run::run_manual(work, true, run::Scenario::DEFAULT, |r| {
+        c.bench_function("manual+work+syscalls", |b| b.iter(|| r()));
+    });
+
+    run::run_nonbox(work, true, run::Scenario::DEFAULT, |r| {
+        c.bench_function("nonbox+work+syscalls", |b| b.iter(|| r()));
     });
 }
 
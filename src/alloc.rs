@@ -0,0 +1,219 @@
+// heap allocation accounting. when the alloc-stats feature is enabled, a
+// counting allocator is installed as the global allocator, and allocations
+// made by the current thread can be counted. otherwise, nothing is counted
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct AllocMetrics {
+    pub alloc: u32,
+    pub dealloc: u32,
+    pub bytes: u64,
+}
+
+#[cfg(feature = "alloc-stats")]
+mod counting {
+    use super::AllocMetrics;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    #[derive(Clone, Copy, Default)]
+    struct Counts {
+        enabled: bool,
+        metrics: AllocMetrics,
+    }
+
+    // const-initialized and without a destructor, so accessing it never
+    // allocates
+    thread_local! {
+        static COUNTS: Cell<Counts> = const {
+            Cell::new(Counts {
+                enabled: false,
+                metrics: AllocMetrics {
+                    alloc: 0,
+                    dealloc: 0,
+                    bytes: 0,
+                },
+            })
+        };
+    }
+
+    fn record(alloc: bool, size: usize) {
+        // the thread local may already be gone during thread exit
+        let _ = COUNTS.try_with(|c| {
+            let mut counts = c.get();
+
+            if !counts.enabled {
+                return;
+            }
+
+            if alloc {
+                counts.metrics.alloc += 1;
+                counts.metrics.bytes += size as u64;
+            } else {
+                counts.metrics.dealloc += 1;
+            }
+
+            c.set(counts);
+        });
+    }
+
+    pub struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            record(true, layout.size());
+
+            System.alloc(layout)
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            record(true, layout.size());
+
+            System.alloc_zeroed(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            record(false, layout.size());
+
+            System.dealloc(ptr, layout)
+        }
+
+        // counted as a new allocation plus freeing the old one
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            record(false, layout.size());
+            record(true, new_size);
+
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    // restores the enclosing count when dropped, including on panic. the
+    // allocations counted by the inner call were also made within the
+    // enclosing one, so they are added to it
+    struct Restore(Counts);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let outer = self.0;
+
+            let _ = COUNTS.try_with(|c| {
+                let inner = c.replace(outer).metrics;
+
+                if outer.enabled {
+                    let mut counts = outer;
+
+                    counts.metrics.alloc += inner.alloc;
+                    counts.metrics.dealloc += inner.dealloc;
+                    counts.metrics.bytes += inner.bytes;
+
+                    c.set(counts);
+                }
+            });
+        }
+    }
+
+    pub fn count<F: FnOnce()>(f: F) -> AllocMetrics {
+        let outer = COUNTS.with(|c| {
+            c.replace(Counts {
+                enabled: true,
+                metrics: AllocMetrics::default(),
+            })
+        });
+
+        let restore = Restore(outer);
+
+        f();
+
+        let metrics = COUNTS.with(|c| c.get().metrics);
+
+        drop(restore);
+
+        metrics
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+pub use counting::CountingAllocator;
+
+// runs f and returns the allocations it made on the current thread.
+// allocations made by other threads are not counted. calls may be nested, in
+// which case the outer count includes the inner one
+#[cfg(feature = "alloc-stats")]
+pub fn count<F: FnOnce()>(f: F) -> AllocMetrics {
+    counting::count(f)
+}
+
+#[cfg(not(feature = "alloc-stats"))]
+pub fn count<F: FnOnce()>(f: F) -> AllocMetrics {
+    f();
+
+    AllocMetrics::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count() {
+        let m = count(|| {
+            let v: Vec<u32> = Vec::with_capacity(4);
+            drop(v);
+        });
+
+        if cfg!(feature = "alloc-stats") {
+            assert_eq!(
+                m,
+                AllocMetrics {
+                    alloc: 1,
+                    dealloc: 1,
+                    bytes: 16,
+                }
+            );
+        } else {
+            assert_eq!(m, AllocMetrics::default());
+        }
+
+        // an empty closure allocates nothing
+        assert_eq!(count(|| {}), AllocMetrics::default());
+    }
+
+    #[test]
+    fn test_count_nested() {
+        let mut inner = AllocMetrics::default();
+
+        let outer = count(|| {
+            let v: Vec<u32> = Vec::with_capacity(4);
+
+            inner = count(|| {
+                let v: Vec<u32> = Vec::with_capacity(2);
+                drop(v);
+            });
+
+            drop(v);
+        });
+
+        if cfg!(feature = "alloc-stats") {
+            assert_eq!(
+                inner,
+                AllocMetrics {
+                    alloc: 1,
+                    dealloc: 1,
+                    bytes: 8,
+                }
+            );
+
+            // the outer count includes the inner one
+            assert_eq!(
+                outer,
+                AllocMetrics {
+                    alloc: 2,
+                    dealloc: 2,
+                    bytes: 24,
+                }
+            );
+        } else {
+            assert_eq!(inner, AllocMetrics::default());
+            assert_eq!(outer, AllocMetrics::default());
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// storage for executor tasks. items are kept in chunks that are never moved
+// or freed while the storage exists, so pointers to items stay valid as
+// chunks are added. this lets an executor grow past its initial capacity
+// while it holds pointers into task slots, such as to the EmbedWaker of a
+// task being polled
+
+use std::mem;
+use std::ops::{Index, IndexMut};
+
+pub struct Chunks<T> {
+    chunks: Vec<Box<[T]>>,
+    // a key's chunk is key >> shift, and its index within the chunk is
+    // key & mask. for fixed storage, the shift is large enough that all keys
+    // map to the first chunk, which may be of any size
+    shift: u32,
+    mask: usize,
+    capacity: usize,
+    growable: bool,
+}
+
+impl<T> Chunks<T> {
+    // a single chunk of capacity items, that never grows
+    pub fn fixed<F>(capacity: usize, f: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        Self {
+            chunks: vec![(0..capacity).map(f).collect()],
+            shift: usize::BITS - 1,
+            mask: usize::MAX >> 1,
+            capacity,
+            growable: false,
+        }
+    }
+
+    // chunks of chunk_size items, rounded up to a power of two, starting
+    // with one
+    pub fn growable<F>(chunk_size: usize, f: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        let chunk_size = chunk_size.max(1).next_power_of_two();
+
+        let mut chunks = Self {
+            chunks: Vec::new(),
+            shift: chunk_size.trailing_zeros(),
+            mask: chunk_size - 1,
+            capacity: 0,
+            growable: true,
+        };
+
+        chunks.grow(f);
+
+        chunks
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_growable(&self) -> bool {
+        self.growable
+    }
+
+    // adds a chunk, calling f with the key of each new item
+    pub fn grow<F>(&mut self, f: F)
+    where
+        F: FnMut(usize) -> T,
+    {
+        assert!(self.growable);
+
+        let start = self.capacity;
+        let end = start + self.mask + 1;
+
+        self.chunks.push((start..end).map(f).collect());
+        self.capacity = end;
+    }
+}
+
+impl<T> Index<usize> for Chunks<T> {
+    type Output = T;
+
+    fn index(&self, key: usize) -> &T {
+        &self.chunks[key >> self.shift][key & self.mask]
+    }
+}
+
+impl<T> IndexMut<usize> for Chunks<T> {
+    fn index_mut(&mut self, key: usize) -> &mut T {
+        &mut self.chunks[key >> self.shift][key & self.mask]
+    }
+}
+
+enum Slot<T> {
+    // the key of the next vacant slot
+    Vacant(usize),
+    Occupied(T),
+}
+
+// a slab whose items never move. keys are assigned the same way as by
+// Slab: in order at first, and then reusing the most recently removed
+pub struct Arena<T> {
+    slots: Chunks<Slot<T>>,
+    len: usize,
+    next: usize,
+}
+
+impl<T> Arena<T> {
+    pub fn fixed(capacity: usize) -> Self {
+        Self {
+            slots: Chunks::fixed(capacity, |key| Slot::Vacant(key + 1)),
+            len: 0,
+            next: 0,
+        }
+    }
+
+    pub fn growable(chunk_size: usize) -> Self {
+        Self {
+            slots: Chunks::growable(chunk_size, |key| Slot::Vacant(key + 1)),
+            len: 0,
+            next: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // the key the next insert will use, or none if the arena is full and
+    // can't grow
+    pub fn vacant_key(&self) -> Option<usize> {
+        if self.next == self.slots.capacity() && !self.slots.is_growable() {
+            return None;
+        }
+
+        Some(self.next)
+    }
+
+    pub fn insert(&mut self, value: T) -> Result<usize, T> {
+        if self.next == self.slots.capacity() {
+            if !self.slots.is_growable() {
+                return Err(value);
+            }
+
+            // the vacant slots of the new chunk are linked in order, and
+            // the last one points past the end, as with the first chunk
+            self.slots.grow(|key| Slot::Vacant(key + 1));
+        }
+
+        let key = self.next;
+
+        self.next = match &self.slots[key] {
+            Slot::Vacant(next) => *next,
+            Slot::Occupied(_) => unreachable!(),
+        };
+
+        self.slots[key] = Slot::Occupied(value);
+        self.len += 1;
+
+        Ok(key)
+    }
+
+    pub fn get(&self, key: usize) -> Option<&T> {
+        if key >= self.slots.capacity() {
+            return None;
+        }
+
+        match &self.slots[key] {
+            Slot::Occupied(value) => Some(value),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        if key >= self.slots.capacity() {
+            return None;
+        }
+
+        match &mut self.slots[key] {
+            Slot::Occupied(value) => Some(value),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    pub fn remove(&mut self, key: usize) -> T {
+        assert!(self.get(key).is_some(), "invalid key");
+
+        let value = match mem::replace(&mut self.slots[key], Slot::Vacant(self.next)) {
+            Slot::Occupied(value) => value,
+            Slot::Vacant(_) => unreachable!(),
+        };
+
+        self.next = key;
+        self.len -= 1;
+
+        value
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.slots
+            .chunks
+            .iter_mut()
+            .flat_map(|chunk| chunk.iter_mut())
+            .enumerate()
+            .filter_map(|(key, slot)| match slot {
+                Slot::Occupied(value) => Some((key, value)),
+                Slot::Vacant(_) => None,
+            })
+    }
+}
+
+impl<T> Index<usize> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, key: usize) -> &T {
+        self.get(key).expect("invalid key")
+    }
+}
+
+impl<T> IndexMut<usize> for Arena<T> {
+    fn index_mut(&mut self, key: usize) -> &mut T {
+        self.get_mut(key).expect("invalid key")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks() {
+        let c = Chunks::fixed(3, |key| key * 10);
+        assert_eq!(c.capacity(), 3);
+        assert_eq!(c[2], 20);
+
+        // rounded up to 4
+        let mut c = Chunks::growable(3, |key| key * 10);
+        assert_eq!(c.capacity(), 4);
+
+        let p = &c[1] as *const usize;
+
+        c.grow(|key| key * 10);
+        c.grow(|key| key * 10);
+        assert_eq!(c.capacity(), 12);
+        assert_eq!(c[9], 90);
+
+        // the first chunk didn't move
+        assert_eq!(p, &c[1] as *const usize);
+    }
+
+    #[test]
+    fn test_arena() {
+        let mut a = Arena::fixed(2);
+        assert_eq!(a.vacant_key(), Some(0));
+        assert_eq!(a.insert("a"), Ok(0));
+        assert_eq!(a.insert("b"), Ok(1));
+        assert_eq!(a.vacant_key(), None);
+        assert_eq!(a.insert("c"), Err("c"));
+
+        // the most recently removed key is reused
+        assert_eq!(a.remove(0), "a");
+        assert_eq!(a.get(0), None);
+        assert_eq!(a.insert("c"), Ok(0));
+        assert_eq!(a[0], "c");
+
+        let mut a = Arena::growable(2);
+
+        for i in 0..5 {
+            assert_eq!(a.vacant_key(), Some(i));
+            assert_eq!(a.insert(i), Ok(i));
+        }
+
+        // a third chunk was added
+        assert_eq!(a.slots.capacity(), 6);
+
+        a.remove(3);
+        a.remove(1);
+        assert_eq!(a.insert(10), Ok(1));
+        assert_eq!(a.insert(11), Ok(3));
+        assert_eq!(a.insert(12), Ok(5));
+
+        a[5] += 1;
+
+        assert_eq!(
+            a.iter_mut().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+            [(0, 0), (1, 10), (2, 2), (3, 11), (4, 4), (5, 13)]
+        );
+
+        for key in 0..6 {
+            a.remove(key);
+        }
+
+        assert!(a.is_empty());
+    }
+}
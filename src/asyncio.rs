@@ -0,0 +1,366 @@
+// poll-based read/write traits for the async I/O objects, and generic
+// futures built on top of them. none of the futures allocate. buffers are
+// provided by the caller
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pub trait AsyncRead {
+    fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, io::Error>>;
+}
+
+pub trait AsyncWrite {
+    fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, io::Error>>;
+}
+
+impl<R: AsyncRead + ?Sized> AsyncRead for &mut R {
+    fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, io::Error>> {
+        (**self).poll_read(cx, buf)
+    }
+}
+
+impl<W: AsyncWrite + ?Sized> AsyncWrite for &mut W {
+    fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        (**self).poll_write(cx, buf)
+    }
+}
+
+// reads once into buf. resolves to the number of bytes read
+pub fn read<'a, R>(r: &'a mut R, buf: &'a mut [u8]) -> ReadOnce<'a, R>
+where
+    R: AsyncRead + ?Sized,
+{
+    ReadOnce { r, buf }
+}
+
+// reads into buf until it contains delim, the reader reaches EOF, or buf is
+// full. resolves to the number of bytes read
+pub fn read_until<'a, R>(r: &'a mut R, delim: u8, buf: &'a mut [u8]) -> ReadUntil<'a, R>
+where
+    R: AsyncRead + ?Sized,
+{
+    ReadUntil {
+        r,
+        delim,
+        buf,
+        len: 0,
+    }
+}
+
+pub fn write_all<'a, W>(w: &'a mut W, buf: &'a [u8]) -> WriteAll<'a, W>
+where
+    W: AsyncWrite + ?Sized,
+{
+    WriteAll { w, buf }
+}
+
+// copies from r to w until r reaches EOF, using buf as the intermediate
+// buffer. resolves to the number of bytes copied
+pub fn copy<'a, R, W>(r: &'a mut R, w: &'a mut W, buf: &'a mut [u8]) -> Copy<'a, R, W>
+where
+    R: AsyncRead + ?Sized,
+    W: AsyncWrite + ?Sized,
+{
+    Copy {
+        r,
+        w,
+        buf,
+        pos: 0,
+        cap: 0,
+        eof: false,
+        total: 0,
+    }
+}
+
+pub struct ReadOnce<'a, R: ?Sized> {
+    r: &'a mut R,
+    buf: &'a mut [u8],
+}
+
+impl<R> Future for ReadOnce<'_, R>
+where
+    R: AsyncRead + ?Sized,
+{
+    type Output = Result<usize, io::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let f = &mut *self;
+
+        f.r.poll_read(cx, f.buf)
+    }
+}
+
+pub struct ReadUntil<'a, R: ?Sized> {
+    r: &'a mut R,
+    delim: u8,
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<R> Future for ReadUntil<'_, R>
+where
+    R: AsyncRead + ?Sized,
+{
+    type Output = Result<usize, io::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let f = &mut *self;
+
+        while f.len < f.buf.len() {
+            let size = match f.r.poll_read(cx, &mut f.buf[f.len..]) {
+                Poll::Ready(Ok(size)) => size,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if size == 0 {
+                break;
+            }
+
+            let start = f.len;
+            f.len += size;
+
+            // only the new bytes need to be checked
+            if f.buf[start..f.len].contains(&f.delim) {
+                break;
+            }
+        }
+
+        Poll::Ready(Ok(f.len))
+    }
+}
+
+pub struct WriteAll<'a, W: ?Sized> {
+    w: &'a mut W,
+    buf: &'a [u8],
+}
+
+impl<W> Future for WriteAll<'_, W>
+where
+    W: AsyncWrite + ?Sized,
+{
+    type Output = Result<(), io::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let f = &mut *self;
+
+        while !f.buf.is_empty() {
+            let size = match f.w.poll_write(cx, f.buf) {
+                Poll::Ready(Ok(size)) => size,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if size == 0 {
+                return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero)));
+            }
+
+            f.buf = &f.buf[size..];
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+pub struct Copy<'a, R: ?Sized, W: ?Sized> {
+    r: &'a mut R,
+    w: &'a mut W,
+    buf: &'a mut [u8],
+    pos: usize,
+    cap: usize,
+    eof: bool,
+    total: u64,
+}
+
+impl<R, W> Future for Copy<'_, R, W>
+where
+    R: AsyncRead + ?Sized,
+    W: AsyncWrite + ?Sized,
+{
+    type Output = Result<u64, io::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let f = &mut *self;
+
+        loop {
+            if f.pos == f.cap && !f.eof {
+                let size = match f.r.poll_read(cx, f.buf) {
+                    Poll::Ready(Ok(size)) => size,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                };
+
+                if size == 0 {
+                    f.eof = true;
+                } else {
+                    f.pos = 0;
+                    f.cap = size;
+                }
+            }
+
+            while f.pos < f.cap {
+                let size = match f.w.poll_write(cx, &f.buf[f.pos..f.cap]) {
+                    Poll::Ready(Ok(size)) => size,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                };
+
+                if size == 0 {
+                    return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero)));
+                }
+
+                f.pos += size;
+                f.total += size as u64;
+            }
+
+            if f.eof {
+                return Poll::Ready(Ok(f.total));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::Waker;
+
+    // returns Pending before every chunk, waking itself immediately
+    struct ChunkReader {
+        data: &'static [u8],
+        chunk: usize,
+        pending: bool,
+    }
+
+    impl AsyncRead for ChunkReader {
+        fn poll_read(
+            &mut self,
+            cx: &mut Context,
+            buf: &mut [u8],
+        ) -> Poll<Result<usize, io::Error>> {
+            self.pending = !self.pending;
+
+            if self.pending {
+                cx.waker().wake_by_ref();
+
+                return Poll::Pending;
+            }
+
+            let size = self.chunk.min(self.data.len()).min(buf.len());
+
+            buf[..size].copy_from_slice(&self.data[..size]);
+            self.data = &self.data[size..];
+
+            Poll::Ready(Ok(size))
+        }
+    }
+
+    struct ChunkWriter {
+        data: Vec<u8>,
+        chunk: usize,
+        pending: bool,
+    }
+
+    impl AsyncWrite for ChunkWriter {
+        fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+            self.pending = !self.pending;
+
+            if self.pending {
+                cx.waker().wake_by_ref();
+
+                return Poll::Pending;
+            }
+
+            let size = self.chunk.min(buf.len());
+
+            self.data.extend_from_slice(&buf[..size]);
+
+            Poll::Ready(Ok(size))
+        }
+    }
+
+    fn reader(data: &'static [u8], chunk: usize) -> ChunkReader {
+        ChunkReader {
+            data,
+            chunk,
+            pending: false,
+        }
+    }
+
+    fn writer(chunk: usize) -> ChunkWriter {
+        ChunkWriter {
+            data: Vec::new(),
+            chunk,
+            pending: false,
+        }
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = std::pin::pin!(fut);
+        let mut cx = Context::from_waker(Waker::noop());
+
+        loop {
+            if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    #[test]
+    fn test_read() {
+        let mut r = reader(b"hello world\n", 5);
+        let mut buf = [0; 32];
+
+        let size = block_on(read(&mut r, &mut buf)).unwrap();
+        assert_eq!(&buf[..size], b"hello");
+
+        let size = block_on(read(&mut r, &mut buf)).unwrap();
+        assert_eq!(&buf[..size], b" worl");
+    }
+
+    #[test]
+    fn test_read_until() {
+        let mut r = reader(b"hello\nworld\n", 4);
+        let mut buf = [0; 32];
+
+        // stops at the chunk containing the delimiter
+        let size = block_on(read_until(&mut r, b'\n', &mut buf)).unwrap();
+        assert_eq!(&buf[..size], b"hello\nwo");
+
+        // eof
+        let mut r = reader(b"hello", 4);
+        let size = block_on(read_until(&mut r, b'\n', &mut buf)).unwrap();
+        assert_eq!(&buf[..size], b"hello");
+
+        // full
+        let mut r = reader(b"hello world\n", 4);
+        let size = block_on(read_until(&mut r, b'\n', &mut buf[..6])).unwrap();
+        assert_eq!(&buf[..size], b"hello ");
+    }
+
+    #[test]
+    fn test_write_all() {
+        let mut w = writer(5);
+
+        block_on(write_all(&mut w, b"hello world\n")).unwrap();
+        assert_eq!(w.data, b"hello world\n");
+
+        let mut w = writer(0);
+
+        let e = block_on(write_all(&mut w, b"hello")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn test_copy() {
+        let mut r = reader(b"hello world\n", 5);
+        let mut w = writer(3);
+        let mut buf = [0; 4];
+
+        let size = block_on(copy(&mut r, &mut w, &mut buf)).unwrap();
+        assert_eq!(size, 12);
+        assert_eq!(w.data, b"hello world\n");
+    }
+}
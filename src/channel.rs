@@ -0,0 +1,654 @@
+// async channels for single-threaded executors. messages and waiters are
+// kept in slabs allocated up front, and the channel state is behind a
+// RefCell rather than atomics, so sending and receiving doesn't allocate or
+// synchronize when used with EmbedWaker or Rc-based wakers. the channels are
+// owned by the caller, and the sending and receiving halves borrow them
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RecvError;
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sender went away")
+    }
+}
+
+impl Error for RecvError {}
+
+impl From<RecvError> for io::Error {
+    fn from(e: RecvError) -> Self {
+        io::Error::new(io::ErrorKind::BrokenPipe, e)
+    }
+}
+
+// the receiver went away. the message is handed back
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SendError(..)")
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "receiver went away")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+impl<T> From<SendError<T>> for io::Error {
+    fn from(e: SendError<T>) -> Self {
+        // the message may not be Send or 'static, so it isn't kept
+        io::Error::new(io::ErrorKind::BrokenPipe, e.to_string())
+    }
+}
+
+pub mod mpsc {
+    use super::SendError;
+    use crate::list;
+    use slab::Slab;
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    struct ChannelData<T> {
+        items: Slab<list::Node<T>>,
+        queue: list::List,
+        senders: usize,
+        receiver: bool,
+        recv_waker: Option<Waker>,
+        // senders waiting for room, in the order they started waiting
+        send_waiters: Slab<list::Node<Option<Waker>>>,
+        send_queue: list::List,
+    }
+
+    impl<T> ChannelData<T> {
+        // wakes the longest waiting sender, if there is room for it
+        fn wake_first_sender(&self) {
+            if self.items.len() == self.items.capacity() {
+                return;
+            }
+
+            if let Some(key) = self.send_queue.head {
+                if let Some(waker) = &self.send_waiters[key].value {
+                    waker.wake_by_ref();
+                }
+            }
+        }
+
+        fn wake_receiver(&self) {
+            if let Some(waker) = &self.recv_waker {
+                waker.wake_by_ref();
+            }
+        }
+    }
+
+    // a bounded channel. it can be split into a sender and receiver any
+    // number of times, but only once all halves of the previous split have
+    // been dropped
+    pub struct Channel<T> {
+        data: RefCell<ChannelData<T>>,
+    }
+
+    impl<T> Channel<T> {
+        // capacity is the most messages that may be queued, and senders_max
+        // is the most sends that may be waiting for room at once
+        pub fn new(capacity: usize, senders_max: usize) -> Self {
+            let data = ChannelData {
+                items: Slab::with_capacity(capacity),
+                queue: list::List::default(),
+                senders: 0,
+                receiver: false,
+                recv_waker: None,
+                send_waiters: Slab::with_capacity(senders_max),
+                send_queue: list::List::default(),
+            };
+
+            Self {
+                data: RefCell::new(data),
+            }
+        }
+
+        pub fn split(&self) -> (Sender<'_, T>, Receiver<'_, T>) {
+            let data = &mut *self.data.borrow_mut();
+
+            assert!(
+                data.senders == 0 && !data.receiver,
+                "channel is still in use"
+            );
+
+            data.senders = 1;
+            data.receiver = true;
+
+            (Sender { channel: self }, Receiver { channel: self })
+        }
+    }
+
+    pub struct Sender<'a, T> {
+        channel: &'a Channel<T>,
+    }
+
+    impl<'a, T> Sender<'a, T> {
+        // waits for room, unless the receiver goes away first
+        pub fn send(&self, value: T) -> Send<'a, T> {
+            Send {
+                channel: self.channel,
+                value: Some(value),
+                key: None,
+            }
+        }
+    }
+
+    impl<T> Clone for Sender<'_, T> {
+        fn clone(&self) -> Self {
+            self.channel.data.borrow_mut().senders += 1;
+
+            Self {
+                channel: self.channel,
+            }
+        }
+    }
+
+    impl<T> Drop for Sender<'_, T> {
+        fn drop(&mut self) {
+            let data = &mut *self.channel.data.borrow_mut();
+
+            data.senders -= 1;
+
+            if data.senders == 0 {
+                data.wake_receiver();
+            }
+        }
+    }
+
+    pub struct Send<'a, T> {
+        channel: &'a Channel<T>,
+        value: Option<T>,
+        // set while waiting
+        key: Option<usize>,
+    }
+
+    // the value is never pinned, only moved into the channel
+    impl<T> Unpin for Send<'_, T> {}
+
+    impl<T> Future for Send<'_, T> {
+        type Output = Result<(), SendError<T>>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            let channel = self.channel;
+            let data = &mut *channel.data.borrow_mut();
+
+            if !data.receiver {
+                if let Some(key) = self.key.take() {
+                    data.send_queue.remove(&mut data.send_waiters, key);
+                    data.send_waiters.remove(key);
+                }
+
+                return Poll::Ready(Err(SendError(self.value.take().unwrap())));
+            }
+
+            let has_room = data.items.len() < data.items.capacity();
+
+            let first = match self.key {
+                Some(key) => data.send_queue.head == Some(key),
+                None => data.send_queue.is_empty(),
+            };
+
+            if has_room && first {
+                if let Some(key) = self.key.take() {
+                    data.send_queue.remove(&mut data.send_waiters, key);
+                    data.send_waiters.remove(key);
+                }
+
+                let key = data
+                    .items
+                    .insert(list::Node::new(self.value.take().unwrap()));
+                data.queue.push_back(&mut data.items, key);
+
+                data.wake_receiver();
+
+                // there may be room for the next sender too
+                data.wake_first_sender();
+
+                return Poll::Ready(Ok(()));
+            }
+
+            let key = match self.key {
+                Some(key) => key,
+                None => {
+                    assert!(
+                        data.send_waiters.len() < data.send_waiters.capacity(),
+                        "too many waiting senders"
+                    );
+
+                    let key = data.send_waiters.insert(list::Node::new(None));
+                    data.send_queue.push_back(&mut data.send_waiters, key);
+
+                    self.key = Some(key);
+
+                    key
+                }
+            };
+
+            let waker = &mut data.send_waiters[key].value;
+
+            match waker {
+                Some(current) if current.will_wake(cx.waker()) => {}
+                _ => *waker = Some(cx.waker().clone()),
+            }
+
+            Poll::Pending
+        }
+    }
+
+    impl<T> Drop for Send<'_, T> {
+        fn drop(&mut self) {
+            if let Some(key) = self.key {
+                let data = &mut *self.channel.data.borrow_mut();
+
+                data.send_queue.remove(&mut data.send_waiters, key);
+                data.send_waiters.remove(key);
+
+                // if this sender was woken for room, pass the wake on
+                data.wake_first_sender();
+            }
+        }
+    }
+
+    pub struct Receiver<'a, T> {
+        channel: &'a Channel<T>,
+    }
+
+    impl<'a, T> Receiver<'a, T> {
+        // resolves to none once all senders have gone away and all queued
+        // messages have been received
+        pub fn recv(&mut self) -> Recv<'_, 'a, T> {
+            Recv { receiver: self }
+        }
+    }
+
+    impl<T> Drop for Receiver<'_, T> {
+        fn drop(&mut self) {
+            let data = &mut *self.channel.data.borrow_mut();
+
+            data.receiver = false;
+            data.recv_waker = None;
+
+            // undelivered messages are dropped in place, keeping the slab's
+            // storage for the next split
+            while let Some(key) = data.queue.pop_front(&mut data.items) {
+                data.items.remove(key);
+            }
+
+            // any waiting senders will find the receiver gone
+            let mut next = data.send_queue.head;
+
+            while let Some(key) = next {
+                let node = &data.send_waiters[key];
+
+                if let Some(waker) = &node.value {
+                    waker.wake_by_ref();
+                }
+
+                next = node.next;
+            }
+        }
+    }
+
+    pub struct Recv<'r, 'a, T> {
+        receiver: &'r mut Receiver<'a, T>,
+    }
+
+    impl<T> Future for Recv<'_, '_, T> {
+        type Output = Option<T>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            let data = &mut *self.receiver.channel.data.borrow_mut();
+
+            if let Some(key) = data.queue.pop_front(&mut data.items) {
+                let value = data.items.remove(key).value;
+
+                data.wake_first_sender();
+
+                return Poll::Ready(Some(value));
+            }
+
+            if data.senders == 0 {
+                return Poll::Ready(None);
+            }
+
+            match &data.recv_waker {
+                Some(current) if current.will_wake(cx.waker()) => {}
+                _ => data.recv_waker = Some(cx.waker().clone()),
+            }
+
+            Poll::Pending
+        }
+    }
+}
+
+pub mod oneshot {
+    use super::{RecvError, SendError};
+    use slab::Slab;
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    struct Slot<T> {
+        value: Option<T>,
+        waker: Option<Waker>,
+        sender: bool,
+        receiver: bool,
+    }
+
+    // storage for oneshot channels. each channel takes a slot, which is
+    // freed once both of its halves have been dropped
+    pub struct Pool<T> {
+        slots: RefCell<Slab<Slot<T>>>,
+    }
+
+    impl<T> Pool<T> {
+        // capacity is the most channels that may exist at once
+        pub fn new(capacity: usize) -> Self {
+            Self {
+                slots: RefCell::new(Slab::with_capacity(capacity)),
+            }
+        }
+
+        pub fn channel(&self) -> (Sender<'_, T>, Receiver<'_, T>) {
+            let slots = &mut *self.slots.borrow_mut();
+
+            assert!(slots.len() < slots.capacity(), "too many channels");
+
+            let key = slots.insert(Slot {
+                value: None,
+                waker: None,
+                sender: true,
+                receiver: true,
+            });
+
+            (Sender { pool: self, key }, Receiver { pool: self, key })
+        }
+    }
+
+    pub struct Sender<'a, T> {
+        pool: &'a Pool<T>,
+        key: usize,
+    }
+
+    impl<T> Sender<'_, T> {
+        // the receiver is woken when self is dropped, below
+        pub fn send(self, value: T) -> Result<(), SendError<T>> {
+            let slots = &mut *self.pool.slots.borrow_mut();
+
+            let slot = &mut slots[self.key];
+
+            if !slot.receiver {
+                return Err(SendError(value));
+            }
+
+            slot.value = Some(value);
+
+            Ok(())
+        }
+    }
+
+    impl<T> Drop for Sender<'_, T> {
+        fn drop(&mut self) {
+            let slots = &mut *self.pool.slots.borrow_mut();
+
+            let slot = &mut slots[self.key];
+
+            slot.sender = false;
+
+            if !slot.receiver {
+                slots.remove(self.key);
+            } else if let Some(waker) = &slot.waker {
+                waker.wake_by_ref();
+            }
+        }
+    }
+
+    // resolves to the sent value, or an error if the sender went away
+    // without sending
+    pub struct Receiver<'a, T> {
+        pool: &'a Pool<T>,
+        key: usize,
+    }
+
+    impl<T> Future for Receiver<'_, T> {
+        type Output = Result<T, RecvError>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            let slots = &mut *self.pool.slots.borrow_mut();
+
+            let slot = &mut slots[self.key];
+
+            if let Some(value) = slot.value.take() {
+                return Poll::Ready(Ok(value));
+            }
+
+            if !slot.sender {
+                return Poll::Ready(Err(RecvError));
+            }
+
+            match &slot.waker {
+                Some(current) if current.will_wake(cx.waker()) => {}
+                _ => slot.waker = Some(cx.waker().clone()),
+            }
+
+            Poll::Pending
+        }
+    }
+
+    impl<T> Drop for Receiver<'_, T> {
+        fn drop(&mut self) {
+            let slots = &mut *self.pool.slots.borrow_mut();
+
+            let slot = &mut slots[self.key];
+
+            slot.receiver = false;
+            slot.waker = None;
+
+            if !slot.sender {
+                slots.remove(self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{BoxExecutor, BoxRcExecutor, BoxSpawner};
+    use crate::waker::RcWakerFactory;
+    use std::cell::{Cell, RefCell};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::task::{Context, Poll, Waker};
+
+    fn poll<F: Future + Unpin>(f: &mut F) -> Poll<F::Output> {
+        Pin::new(f).poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn test_mpsc() {
+        let ch = mpsc::Channel::new(2, 1);
+
+        {
+            let (tx, mut rx) = ch.split();
+            let tx2 = tx.clone();
+
+            assert!(poll(&mut tx.send(1)).is_ready());
+            assert!(poll(&mut tx2.send(2)).is_ready());
+
+            // full
+            let mut send = tx.send(3);
+            assert!(poll(&mut send).is_pending());
+
+            assert_eq!(poll(&mut rx.recv()), Poll::Ready(Some(1)));
+            assert!(matches!(poll(&mut send), Poll::Ready(Ok(()))));
+
+            drop(tx);
+            drop(tx2);
+
+            // queued messages are still received after the senders go away
+            assert_eq!(poll(&mut rx.recv()), Poll::Ready(Some(2)));
+            assert_eq!(poll(&mut rx.recv()), Poll::Ready(Some(3)));
+            assert_eq!(poll(&mut rx.recv()), Poll::Ready(None));
+        }
+
+        // the channel can be reused
+        let (tx, mut rx) = ch.split();
+
+        assert!(poll(&mut rx.recv()).is_pending());
+        assert!(poll(&mut tx.send(4)).is_ready());
+        assert_eq!(poll(&mut rx.recv()), Poll::Ready(Some(4)));
+
+        // the message is handed back if the receiver went away
+        drop(rx);
+        assert!(matches!(
+            poll(&mut tx.send(5)),
+            Poll::Ready(Err(SendError(5)))
+        ));
+    }
+
+    #[test]
+    fn test_oneshot() {
+        let pool = oneshot::Pool::new(1);
+
+        let (tx, mut rx) = pool.channel();
+        assert!(poll(&mut rx).is_pending());
+        tx.send(1).unwrap();
+        assert_eq!(poll(&mut rx), Poll::Ready(Ok(1)));
+        drop(rx);
+
+        // the slot was freed
+        let (tx, mut rx) = pool.channel();
+        drop(tx);
+        assert_eq!(poll(&mut rx), Poll::Ready(Err(RecvError)));
+        drop(rx);
+
+        let (tx, rx) = pool.channel();
+        drop(rx);
+        assert!(matches!(tx.send(2), Err(SendError(2))));
+    }
+
+    // a worker task doubles numbers sent to it by other tasks
+    type Request<'a> = (u32, oneshot::Sender<'a, u32>);
+
+    async fn ping_pong<'a: 'c, 'c, S>(
+        requests: &'c mpsc::Channel<Request<'a>>,
+        replies: &'a oneshot::Pool<u32>,
+        spawn: S,
+        done: &'c Cell<u32>,
+    ) where
+        S: Fn(Pin<Box<dyn Future<Output = ()> + 'c>>),
+    {
+        let (tx, mut rx) = requests.split();
+
+        spawn(Box::pin(async move {
+            while let Some((n, reply)) = rx.recv().await {
+                reply.send(n * 2).unwrap();
+            }
+        }));
+
+        for n in 0..4 {
+            let tx = tx.clone();
+
+            spawn(Box::pin(async move {
+                let (reply_tx, reply_rx) = replies.channel();
+
+                tx.send((n, reply_tx)).await.unwrap();
+
+                assert_eq!(reply_rx.await, Ok(n * 2));
+
+                done.set(done.get() + 1);
+            }));
+        }
+    }
+
+    #[test]
+    fn test_executors() {
+        // requests hold reply senders, so the pool must outlive the channel
+        let replies = oneshot::Pool::new(4);
+        let requests = mpsc::Channel::new(1, 4);
+        let done = Cell::new(0);
+
+        {
+            let spawner = BoxSpawner::new();
+            let executor = BoxExecutor::new(6);
+
+            executor.set_spawner(&spawner);
+
+            let spawn = |f| {
+                spawner.spawn_boxed(f).unwrap();
+            };
+
+            spawner
+                .spawn(ping_pong(&requests, &replies, spawn, &done))
+                .unwrap();
+
+            executor.run(|| Ok(()));
+        }
+
+        assert_eq!(done.get(), 4);
+
+        // futures spawned on BoxRcExecutor must be 'static, but the channel
+        // halves borrow the channel. so a single task owns the channels and
+        // polls the worker and the other tasks itself, and the count is
+        // shared through an Rc, as run_box_rc does with its reactor. the
+        // tasks are still woken through the executor's Rc wakers
+        let done = Rc::new(Cell::new(0));
+
+        let executor = BoxRcExecutor::new(1, RcWakerFactory::default());
+
+        executor
+            .spawn({
+                let done = done.clone();
+
+                async move {
+                    let replies = oneshot::Pool::new(4);
+                    let requests = mpsc::Channel::new(1, 4);
+                    let tasks = RefCell::new(Vec::new());
+
+                    let spawn = |f| tasks.borrow_mut().push(Some(f));
+
+                    ping_pong(&requests, &replies, spawn, &done).await;
+
+                    let mut tasks = tasks.into_inner();
+
+                    std::future::poll_fn(|cx| {
+                        for slot in tasks.iter_mut() {
+                            if let Some(f) = slot {
+                                if f.as_mut().poll(cx).is_ready() {
+                                    *slot = None;
+                                }
+                            }
+                        }
+
+                        if tasks.iter().all(Option::is_none) {
+                            Poll::Ready(())
+                        } else {
+                            Poll::Pending
+                        }
+                    })
+                    .await
+                }
+            })
+            .unwrap();
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(done.get(), 4);
+    }
+}
@@ -0,0 +1,1055 @@
+use crate::asyncio::{AsyncRead, AsyncWrite};
+use crate::fakeio::{Stats, StatsType, READABLE, WRITABLE};
+use crate::future::RegisterError;
+use crate::registration::{EventRegistration, Reactor, ReactorRef, RegistrationHandle};
+use slab::Slab;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::io::{Read, Write};
+use std::mem;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::pin::Pin;
+use std::process;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const REQUEST: &[u8] = b"hello world\n";
+
+// the longest a blocking poll may go without seeing any events. the client
+// always has more to send until the run is done, so this only happens if
+// the client is gone, such as after it failed
+const BLOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn cvt(ret: libc::c_int) -> Result<libc::c_int, io::Error> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+fn cvt_size(ret: libc::ssize_t) -> Result<usize, io::Error> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+fn socket(nonblocking: bool) -> Result<OwnedFd, io::Error> {
+    let mut flags = libc::SOCK_STREAM | libc::SOCK_CLOEXEC;
+
+    if nonblocking {
+        flags |= libc::SOCK_NONBLOCK;
+    }
+
+    let fd = cvt(unsafe { libc::socket(libc::AF_UNIX, flags, 0) })?;
+
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+#[derive(Clone, Copy)]
+struct UnixAddr {
+    addr: libc::sockaddr_un,
+    len: libc::socklen_t,
+}
+
+impl UnixAddr {
+    // an address in the abstract namespace, unique to this process. abstract
+    // sockets don't touch the filesystem and go away when closed
+    fn unique() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let name = format!(
+            "rust-async-bench-{}-{}",
+            process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+        // the first byte of the path stays nul, marking the address abstract
+        for (dest, b) in addr.sun_path[1..].iter_mut().zip(name.as_bytes()) {
+            *dest = *b as libc::c_char;
+        }
+
+        let len = mem::size_of::<libc::sa_family_t>() + 1 + name.len();
+
+        Self {
+            addr,
+            len: len as libc::socklen_t,
+        }
+    }
+
+    fn as_ptr(&self) -> *const libc::sockaddr {
+        &self.addr as *const libc::sockaddr_un as *const libc::sockaddr
+    }
+}
+
+pub struct FdStream<T>
+where
+    T: Stats,
+{
+    fd: OwnedFd,
+    stats: T,
+}
+
+impl<T> io::Read for FdStream<T>
+where
+    T: Stats,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        self.stats.inc(StatsType::Read);
+
+        cvt_size(unsafe {
+            libc::recv(
+                self.fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        })
+    }
+}
+
+impl<T> io::Write for FdStream<T>
+where
+    T: Stats,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        self.stats.inc(StatsType::Write);
+
+        cvt_size(unsafe {
+            libc::send(
+                self.fd.as_raw_fd(),
+                buf.as_ptr() as *const libc::c_void,
+                buf.len(),
+                libc::MSG_NOSIGNAL,
+            )
+        })
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+impl<T> FdStream<T>
+where
+    T: Stats,
+{
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), io::Error> {
+        let mut value = nonblocking as libc::c_int;
+
+        cvt(unsafe { libc::ioctl(self.fd.as_raw_fd(), libc::FIONBIO, &mut value) })?;
+
+        Ok(())
+    }
+}
+
+impl<T> AsRawFd for FdStream<T>
+where
+    T: Stats,
+{
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ListenerAddr {
+    Unix(UnixAddr),
+    Tcp(SocketAddr),
+}
+
+// a nonblocking listener. connections are made by a UnixClient, TcpClient,
+// or BlockingClient
+pub struct FdListener<T> {
+    fd: OwnedFd,
+    addr: ListenerAddr,
+    stats: T,
+}
+
+impl<T> FdListener<T>
+where
+    T: Stats + Clone,
+{
+    pub fn bind_unix(stats: T) -> Result<Self, io::Error> {
+        let addr = UnixAddr::unique();
+
+        let fd = socket(true)?;
+
+        cvt(unsafe { libc::bind(fd.as_raw_fd(), addr.as_ptr(), addr.len) })?;
+        cvt(unsafe { libc::listen(fd.as_raw_fd(), libc::SOMAXCONN) })?;
+
+        Ok(Self {
+            fd,
+            addr: ListenerAddr::Unix(addr),
+            stats,
+        })
+    }
+
+    pub fn bind_tcp(stats: T) -> Result<Self, io::Error> {
+        let l = TcpListener::bind(("127.0.0.1", 0))?;
+        l.set_nonblocking(true)?;
+
+        let addr = l.local_addr()?;
+
+        // std uses a smaller backlog, which the client could fill
+        cvt(unsafe { libc::listen(l.as_raw_fd(), libc::SOMAXCONN) })?;
+
+        Ok(Self {
+            fd: l.into(),
+            addr: ListenerAddr::Tcp(addr),
+            stats,
+        })
+    }
+
+    // the clone shares the underlying socket
+    pub fn try_clone(&self) -> Result<Self, io::Error> {
+        Ok(Self {
+            fd: self.fd.try_clone()?,
+            addr: self.addr,
+            stats: self.stats.clone(),
+        })
+    }
+
+    pub fn accept(&self) -> Result<FdStream<T>, io::Error> {
+        self.stats.inc(StatsType::Accept);
+
+        let fd = cvt(unsafe {
+            libc::accept4(
+                self.fd.as_raw_fd(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            )
+        })?;
+
+        Ok(FdStream {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            stats: self.stats.clone(),
+        })
+    }
+}
+
+impl<T> AsRawFd for FdListener<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+pub struct Epoll<T> {
+    fd: OwnedFd,
+    stats: T,
+    events: RefCell<Vec<libc::epoll_event>>,
+}
+
+impl<T> Epoll<T>
+where
+    T: Stats,
+{
+    pub fn new(capacity: usize, stats: T) -> Self {
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        assert!(fd >= 0);
+
+        Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            stats,
+            events: RefCell::new(vec![libc::epoll_event { events: 0, u64: 0 }; capacity]),
+        }
+    }
+
+    pub fn register<E: AsRawFd>(&self, handle: &E, key: usize, interest: u8) {
+        self.stats.inc(StatsType::Register);
+
+        // edge-triggered, so the caller must track readiness until it sees
+        // WouldBlock, same as with the fake poller
+        let mut flags = libc::EPOLLET;
+
+        if interest & READABLE != 0 {
+            flags |= libc::EPOLLIN;
+        }
+
+        if interest & WRITABLE != 0 {
+            flags |= libc::EPOLLOUT;
+        }
+
+        let mut event = libc::epoll_event {
+            events: flags as u32,
+            u64: key as u64,
+        };
+
+        let ret = unsafe {
+            libc::epoll_ctl(
+                self.fd.as_raw_fd(),
+                libc::EPOLL_CTL_ADD,
+                handle.as_raw_fd(),
+                &mut event,
+            )
+        };
+        assert_eq!(ret, 0);
+    }
+
+    pub fn unregister<E: AsRawFd>(&self, handle: &E) {
+        self.stats.inc(StatsType::Unregister);
+
+        let ret = unsafe {
+            libc::epoll_ctl(
+                self.fd.as_raw_fd(),
+                libc::EPOLL_CTL_DEL,
+                handle.as_raw_fd(),
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(ret, 0);
+    }
+
+    // only blocks if block is set, otherwise returns immediately. a blocking
+    // poll fails with TimedOut if nothing happens for BLOCK_TIMEOUT
+    pub fn poll(&self, events: &mut Slab<(usize, u8)>, block: bool) -> Result<(), io::Error> {
+        self.stats.inc(StatsType::Poll);
+
+        events.clear();
+
+        let buf = &mut *self.events.borrow_mut();
+
+        let timeout = if block {
+            BLOCK_TIMEOUT.as_millis() as libc::c_int
+        } else {
+            0
+        };
+
+        let ret = unsafe {
+            libc::epoll_wait(
+                self.fd.as_raw_fd(),
+                buf.as_mut_ptr(),
+                buf.len() as libc::c_int,
+                timeout,
+            )
+        };
+
+        let count = match cvt(ret) {
+            Ok(0) if block => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no events while blocking, the client may have failed",
+                ))
+            }
+            Ok(count) => count as usize,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => 0,
+            Err(e) => return Err(e),
+        };
+
+        for e in &buf[..count] {
+            let flags = e.events as libc::c_int;
+
+            let mut state = 0;
+
+            // hangups and errors are reported as readiness, so that the next
+            // I/O call sees them
+            if flags & (libc::EPOLLIN | libc::EPOLLHUP | libc::EPOLLERR) != 0 {
+                state |= READABLE;
+            }
+
+            if flags & (libc::EPOLLOUT | libc::EPOLLHUP | libc::EPOLLERR) != 0 {
+                state |= WRITABLE;
+            }
+
+            events.insert((e.u64 as usize, state));
+        }
+
+        Ok(())
+    }
+}
+
+struct ClientConn {
+    fd: OwnedFd,
+    sent: usize,
+    received: usize,
+    requests_left: usize,
+}
+
+impl ClientConn {
+    fn new(fd: OwnedFd, requests: usize) -> Self {
+        Self {
+            fd,
+            sent: 0,
+            received: 0,
+            requests_left: requests,
+        }
+    }
+
+    // the readiness the connection is waiting for
+    fn interest(&self) -> libc::c_short {
+        if self.sent < REQUEST.len() {
+            libc::POLLOUT
+        } else {
+            libc::POLLIN
+        }
+    }
+
+    // returns true once the responses to all of the requests have been
+    // received. each request is sent after the previous response arrives
+    fn process(&mut self) -> Result<bool, io::Error> {
+        loop {
+            while self.sent < REQUEST.len() {
+                let ret = unsafe {
+                    libc::send(
+                        self.fd.as_raw_fd(),
+                        REQUEST[self.sent..].as_ptr() as *const libc::c_void,
+                        REQUEST.len() - self.sent,
+                        libc::MSG_NOSIGNAL,
+                    )
+                };
+
+                match cvt_size(ret) {
+                    Ok(size) => self.sent += size,
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let mut buf = [0; 128];
+
+            while self.received < REQUEST.len() {
+                let ret = unsafe {
+                    libc::recv(
+                        self.fd.as_raw_fd(),
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                        0,
+                    )
+                };
+
+                match cvt_size(ret) {
+                    Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                    Ok(size) => self.received += size,
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            self.requests_left -= 1;
+
+            if self.requests_left == 0 {
+                return Ok(true);
+            }
+
+            self.sent = 0;
+            self.received = 0;
+        }
+    }
+}
+
+// the peer side of a benchmark run. each transaction connects to an
+// FdListener, and then sends a number of request lines, reading the
+// response to each before sending the next. the driver's syscalls are not
+// counted
+pub trait Driver {
+    fn start(&self, count: usize, requests: usize);
+
+    // called before each poll. returns whether the poll may block
+    fn step(&self) -> bool;
+
+    // waits for the transactions of the run to complete
+    fn finish(&self);
+}
+
+struct UnixClientData {
+    conns: Slab<ClientConn>,
+    connect_left: usize,
+    requests: usize,
+}
+
+// drives connections from the same thread as the server, so the server must
+// never block
+pub struct UnixClient {
+    addr: UnixAddr,
+    data: RefCell<UnixClientData>,
+}
+
+impl UnixClient {
+    pub fn new<T>(listener: &FdListener<T>, conns_max: usize) -> Self {
+        let addr = match listener.addr {
+            ListenerAddr::Unix(addr) => addr,
+            ListenerAddr::Tcp(_) => panic!("not a unix listener"),
+        };
+
+        Self {
+            addr,
+            data: RefCell::new(UnixClientData {
+                conns: Slab::with_capacity(conns_max),
+                connect_left: 0,
+                requests: 1,
+            }),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        let data = &*self.data.borrow();
+
+        data.connect_left == 0 && data.conns.is_empty()
+    }
+}
+
+impl Driver for UnixClient {
+    fn start(&self, count: usize, requests: usize) {
+        let data = &mut *self.data.borrow_mut();
+
+        data.connect_left = count;
+        data.requests = requests;
+    }
+
+    fn step(&self) -> bool {
+        let data = &mut *self.data.borrow_mut();
+
+        data.conns.retain(|_, c| !c.process().unwrap());
+
+        // new connections send their requests on the next step, so that the
+        // server sees them become readable
+        while data.connect_left > 0 && data.conns.len() < data.conns.capacity() {
+            let fd = socket(true).unwrap();
+
+            let ret = unsafe { libc::connect(fd.as_raw_fd(), self.addr.as_ptr(), self.addr.len) };
+
+            match cvt(ret) {
+                Ok(_) => {}
+                // the listen backlog is full. try again next time
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => panic!("client connect failed: {}", e),
+            }
+
+            data.connect_left -= 1;
+
+            data.conns.insert(ClientConn::new(fd, data.requests));
+        }
+
+        false
+    }
+
+    fn finish(&self) {
+        // collect the last responses
+        while !self.is_done() {
+            self.step();
+        }
+    }
+}
+
+// makes connections for the blocking variants, using blocking sockets on
+// the same thread as the server's acceptor. each connection is made just
+// before the server accepts it, so the server never blocks on accept. the
+// client's syscalls are not counted
+pub struct BlockingClient {
+    addr: UnixAddr,
+    conns: Vec<UnixStream>,
+}
+
+impl BlockingClient {
+    pub fn new<T>(listener: &FdListener<T>, conns_max: usize) -> Self {
+        let addr = match listener.addr {
+            ListenerAddr::Unix(addr) => addr,
+            ListenerAddr::Tcp(_) => panic!("not a unix listener"),
+        };
+
+        Self {
+            addr,
+            conns: Vec::with_capacity(conns_max),
+        }
+    }
+
+    pub fn connect(&mut self) -> Result<(), io::Error> {
+        let fd = socket(false)?;
+
+        cvt(unsafe { libc::connect(fd.as_raw_fd(), self.addr.as_ptr(), self.addr.len) })?;
+
+        self.conns.push(UnixStream::from(fd));
+
+        Ok(())
+    }
+
+    // sends a request on each connection and then reads the responses, the
+    // given number of times, and then closes the connections
+    pub fn transact(&mut self, requests: usize) -> Result<(), io::Error> {
+        let mut buf = [0; 128];
+
+        for _ in 0..requests {
+            for stream in self.conns.iter_mut() {
+                stream.write_all(REQUEST)?;
+            }
+
+            for stream in self.conns.iter_mut() {
+                let mut received = 0;
+
+                while received < REQUEST.len() {
+                    let size = stream.read(&mut buf)?;
+
+                    if size == 0 {
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+                    }
+
+                    received += size;
+                }
+            }
+        }
+
+        self.conns.clear();
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyMetrics {
+    count: u32,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl LatencyMetrics {
+    fn new() -> Self {
+        Self {
+            count: 0,
+            total: Duration::ZERO,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+        }
+    }
+
+    fn record(&mut self, d: Duration) {
+        self.count += 1;
+        self.total += d;
+        self.min = self.min.min(d);
+        self.max = self.max.max(d);
+    }
+
+    fn merge(&mut self, other: &LatencyMetrics) {
+        self.count += other.count;
+        self.total += other.total;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.count > 0 {
+            self.total / self.count
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    pub fn min(&self) -> Duration {
+        if self.count > 0 {
+            self.min
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+}
+
+impl fmt::Display for LatencyMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "transactions={} min={:?} mean={:?} max={:?}",
+            self.count,
+            self.min(),
+            self.mean(),
+            self.max()
+        )
+    }
+}
+
+// performs count transactions against addr, with up to conns_max of them
+// in flight at a time. the latency of each is recorded, from connecting
+// until the last response is received
+fn transact(
+    addr: SocketAddr,
+    conns_max: usize,
+    count: usize,
+    requests: usize,
+) -> Result<LatencyMetrics, io::Error> {
+    let mut conns: Slab<(ClientConn, Instant)> = Slab::with_capacity(conns_max);
+    let mut fds = Vec::with_capacity(conns_max);
+    let mut connect_left = count;
+    let mut latency = LatencyMetrics::new();
+
+    loop {
+        while connect_left > 0 && conns.len() < conns_max {
+            let start = Instant::now();
+
+            let stream = TcpStream::connect(addr)?;
+            stream.set_nodelay(true)?;
+            stream.set_nonblocking(true)?;
+
+            connect_left -= 1;
+
+            conns.insert((ClientConn::new(stream.into(), requests), start));
+        }
+
+        let mut error = None;
+
+        conns.retain(|_, (c, start)| match c.process() {
+            Ok(true) => {
+                latency.record(start.elapsed());
+
+                false
+            }
+            Ok(false) => true,
+            Err(e) => {
+                error.get_or_insert(e);
+
+                false
+            }
+        });
+
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        if conns.is_empty() {
+            if connect_left == 0 {
+                break;
+            }
+
+            continue;
+        }
+
+        // wait until any of the connections can make progress
+        fds.clear();
+        fds.extend(conns.iter().map(|(_, (c, _))| libc::pollfd {
+            fd: c.fd.as_raw_fd(),
+            events: c.interest(),
+            revents: 0,
+        }));
+
+        let ret = unsafe {
+            libc::poll(
+                fds.as_mut_ptr(),
+                fds.len() as libc::nfds_t,
+                BLOCK_TIMEOUT.as_millis() as libc::c_int,
+            )
+        };
+
+        match cvt(ret) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::TimedOut)),
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(latency)
+}
+
+// drives connections from a separate thread, using nonblocking sockets. up
+// to conns_max transactions of a run are in flight at a time, and the
+// latency of each is recorded. if the client fails, the error is reported by
+// finish, and since the server stops seeing events, its blocking polls time
+// out rather than hang
+pub struct TcpClient {
+    commands: Option<mpsc::Sender<(usize, usize)>>,
+    results: mpsc::Receiver<Result<LatencyMetrics, io::Error>>,
+    latency: Cell<LatencyMetrics>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl TcpClient {
+    pub fn new<T>(listener: &FdListener<T>, conns_max: usize) -> Self {
+        let addr = match listener.addr {
+            ListenerAddr::Tcp(addr) => addr,
+            ListenerAddr::Unix(_) => panic!("not a tcp listener"),
+        };
+
+        let (commands, commands_receiver) = mpsc::channel();
+        let (results_sender, results) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            for (count, requests) in commands_receiver {
+                let result = transact(addr, conns_max, count, requests);
+
+                if results_sender.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            commands: Some(commands),
+            results,
+            latency: Cell::new(LatencyMetrics::new()),
+            thread: Some(thread),
+        }
+    }
+
+    // latency of all transactions performed so far
+    pub fn latency(&self) -> LatencyMetrics {
+        self.latency.get()
+    }
+}
+
+impl Driver for TcpClient {
+    fn start(&self, count: usize, requests: usize) {
+        self.commands
+            .as_ref()
+            .unwrap()
+            .send((count, requests))
+            .unwrap();
+    }
+
+    fn step(&self) -> bool {
+        true
+    }
+
+    fn finish(&self) {
+        let run_latency = match self.results.recv() {
+            Ok(Ok(latency)) => latency,
+            Ok(Err(e)) => panic!("client failed: {}", e),
+            Err(_) => panic!("client thread failed"),
+        };
+
+        let mut latency = self.latency.get();
+        latency.merge(&run_latency);
+        self.latency.set(latency);
+    }
+}
+
+impl Drop for TcpClient {
+    fn drop(&mut self) {
+        // closing the channel stops the thread
+        self.commands = None;
+
+        if let Some(thread) = self.thread.take() {
+            // don't double panic if the thread failed
+            let _ = thread.join();
+        }
+    }
+}
+
+pub trait EpollReactorRef<T>: ReactorRef<Reactor = EpollReactor<T>>
+where
+    T: Stats,
+{
+    fn register<E: AsRawFd>(
+        &self,
+        handle: &E,
+        interest: u8,
+    ) -> Result<RegistrationHandle<Self>, RegisterError> {
+        let reg = RegistrationHandle::new(self.clone())?;
+
+        self.get().poll.register(handle, reg.key(), interest);
+
+        Ok(reg)
+    }
+}
+
+impl<T, R> EpollReactorRef<T> for R
+where
+    T: Stats,
+    R: ReactorRef<Reactor = EpollReactor<T>>,
+{
+}
+
+struct EpollReactorData {
+    registrations: Slab<EventRegistration>,
+    events: Slab<(usize, u8)>,
+}
+
+pub struct EpollReactor<T> {
+    data: RefCell<EpollReactorData>,
+    poll: Epoll<T>,
+}
+
+impl<T> EpollReactor<T>
+where
+    T: Stats,
+{
+    pub fn new(registrations_max: usize, stats: T) -> Self {
+        let data = EpollReactorData {
+            registrations: Slab::with_capacity(registrations_max),
+            events: Slab::with_capacity(registrations_max),
+        };
+
+        Self {
+            data: RefCell::new(data),
+            poll: Epoll::new(registrations_max, stats),
+        }
+    }
+
+    pub fn poll(&self, block: bool) -> Result<(), io::Error> {
+        let data = &mut *self.data.borrow_mut();
+
+        self.poll.poll(&mut data.events, block)?;
+
+        for (_, &(key, readiness)) in data.events.iter() {
+            if let Some(event_reg) = data.registrations.get_mut(key) {
+                event_reg.set_readiness(readiness, Waker::wake);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn unregister<E: AsRawFd>(&self, handle: &E) {
+        self.poll.unregister(handle);
+    }
+}
+
+impl<T> Reactor for EpollReactor<T> {
+    fn with_registrations<F, V>(&self, f: F) -> V
+    where
+        F: FnOnce(&mut Slab<EventRegistration>) -> V,
+    {
+        f(&mut self.data.borrow_mut().registrations)
+    }
+}
+
+pub struct AsyncFdStream<T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    inner: FdStream<T>,
+    handle: RegistrationHandle<R>,
+}
+
+impl<T, R> AsyncFdStream<T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    pub fn new(s: FdStream<T>, reactor: R) -> Result<Self, RegisterError> {
+        let handle = reactor.register(&s, READABLE | WRITABLE)?;
+
+        handle.set_ready(READABLE | WRITABLE, true);
+
+        Ok(Self { inner: s, handle })
+    }
+}
+
+impl<T, R> Drop for AsyncFdStream<T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    fn drop(&mut self) {
+        self.handle.reactor().get().unregister(&self.inner);
+    }
+}
+
+impl<T, R> AsyncRead for AsyncFdStream<T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, io::Error>> {
+        let inner = &mut self.inner;
+
+        self.handle.poll_io(READABLE, cx, || inner.read(buf))
+    }
+}
+
+impl<T, R> AsyncWrite for AsyncFdStream<T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let inner = &mut self.inner;
+
+        self.handle.poll_io(WRITABLE, cx, || inner.write(buf))
+    }
+}
+
+pub struct AsyncFdListener<T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    inner: FdListener<T>,
+    handle: RegistrationHandle<R>,
+}
+
+impl<T, R> AsyncFdListener<T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    pub fn new(reactor: R, l: FdListener<T>) -> Self {
+        let handle = reactor.register(&l, READABLE).unwrap();
+
+        handle.set_ready(READABLE, true);
+
+        Self { inner: l, handle }
+    }
+
+    pub fn accept<'a>(&'a self) -> AcceptFuture<'a, T, R> {
+        AcceptFuture { l: self }
+    }
+}
+
+impl<T, R> Drop for AsyncFdListener<T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    fn drop(&mut self) {
+        self.handle.reactor().get().unregister(&self.inner);
+    }
+}
+
+pub struct AcceptFuture<'a, T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    l: &'a AsyncFdListener<T, R>,
+}
+
+impl<T, R> Future for AcceptFuture<'_, T, R>
+where
+    T: Stats + Clone,
+    R: EpollReactorRef<T>,
+{
+    type Output = Result<AsyncFdStream<T, R>, io::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let f = &mut *self;
+
+        let reactor = f.l.handle.reactor();
+
+        f.l.handle.poll_io(READABLE, cx, || {
+            let stream = f.l.inner.accept()?;
+
+            Ok(AsyncFdStream::new(stream, reactor.clone())?)
+        })
+    }
+}
+
+impl<T, R> Drop for AcceptFuture<'_, T, R>
+where
+    T: Stats,
+    R: EpollReactorRef<T>,
+{
+    fn drop(&mut self) {
+        self.l.handle.unbind_waker(READABLE);
+    }
+}
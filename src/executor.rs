@@ -1,28 +1,121 @@
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
 use std::future::Future;
+use std::io;
 use std::pin::Pin;
 
 pub type BoxFuture = Pin<Box<dyn Future<Output = ()>>>;
 
+pub enum JoinError {
+    Cancelled,
+    Panicked(Box<dyn Any + Send>),
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "Cancelled"),
+            Self::Panicked(_) => write!(f, "Panicked(..)"),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "task was cancelled"),
+            Self::Panicked(_) => write!(f, "task panicked"),
+        }
+    }
+}
+
+impl Error for JoinError {}
+
+impl From<JoinError> for io::Error {
+    fn from(e: JoinError) -> Self {
+        // the panic payload isn't Sync, so only the description is kept
+        io::Error::other(e.to_string())
+    }
+}
+
+// the reason a task couldn't be spawned. the rejected argument or future is
+// handed back, so the caller can decide what to do with it
+pub enum SpawnError<T> {
+    // the executor's task storage is full. this is backpressure, and
+    // spawning may succeed once some tasks finish
+    AtCapacity(T),
+    // the spawner isn't attached to an executor, such as after the executor
+    // was dropped. spawning will never succeed
+    ExecutorGone(T),
+}
+
+impl<T> SpawnError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::AtCapacity(v) | Self::ExecutorGone(v) => v,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SpawnError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AtCapacity(_) => write!(f, "AtCapacity(..)"),
+            Self::ExecutorGone(_) => write!(f, "ExecutorGone(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SpawnError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AtCapacity(_) => write!(f, "executor is at capacity"),
+            Self::ExecutorGone(_) => write!(f, "executor is gone"),
+        }
+    }
+}
+
+impl<T> Error for SpawnError<T> {}
+
+impl<T> From<SpawnError<T>> for io::Error {
+    fn from(e: SpawnError<T>) -> Self {
+        // the rejected value may not be Send, so only the description is
+        // kept
+        io::Error::other(e.to_string())
+    }
+}
+
 mod arg {
+    use super::SpawnError;
+    use crate::arena::{Arena, Chunks};
     use crate::list;
     use crate::waker::{EmbedWake, EmbedWaker};
-    use slab::Slab;
     use std::cell::RefCell;
     use std::future::Future;
     use std::io;
     use std::mem::MaybeUninit;
     use std::pin::Pin;
-    use std::task::{Context, Poll};
+    use std::ptr;
+    use std::task::Context;
 
     struct Task<'a, W> {
         waker: EmbedWaker<'a, W>,
         awake: bool,
+        live: bool,
+        generation: u64,
     }
 
     struct TasksData<'a, F, W> {
-        nodes: Slab<list::Node<Task<'a, W>>>,
+        nodes: Arena<list::Node<Task<'a, W>>>,
         next: list::List,
-        futs: Vec<MaybeUninit<F>>,
+        // indexed by task id, and grown along with nodes
+        futs: Chunks<MaybeUninit<F>>,
+        // live tasks. a task that is gone may still hold its slot, until
+        // all clones of its waker are dropped
+        running: usize,
+        current: Option<usize>,
+        generation: u64,
     }
 
     struct Tasks<'a, F> {
@@ -33,14 +126,28 @@ mod arg {
     where
         F: Future<Output = ()> + 'a,
     {
-        fn new(tasks_max: usize) -> Self {
-            let mut data = TasksData {
-                nodes: Slab::with_capacity(tasks_max),
-                next: list::List::default(),
-                futs: Vec::with_capacity(tasks_max),
+        // if growable, capacity is the size of each chunk of task storage
+        fn new(capacity: usize, growable: bool) -> Self {
+            let (nodes, futs) = if growable {
+                (
+                    Arena::growable(capacity),
+                    Chunks::growable(capacity, |_| MaybeUninit::uninit()),
+                )
+            } else {
+                (
+                    Arena::fixed(capacity),
+                    Chunks::fixed(capacity, |_| MaybeUninit::uninit()),
+                )
             };
 
-            unsafe { data.futs.set_len(tasks_max) };
+            let data = TasksData {
+                nodes,
+                next: list::List::default(),
+                futs,
+                running: 0,
+                current: None,
+                generation: 0,
+            };
 
             Self {
                 data: RefCell::new(data),
@@ -48,33 +155,47 @@ mod arg {
         }
 
         fn is_empty(&self) -> bool {
-            self.data.borrow().nodes.is_empty()
+            self.data.borrow().running == 0
         }
 
-        fn add<S>(&'a self, get_fut_fn: S) -> Result<(), ()>
+        // if there is room, init_fn is called with arg to write the future
+        // into place. otherwise, arg is returned
+        fn add<T, S>(&'a self, arg: T, init_fn: S) -> Result<(usize, u64), T>
         where
-            S: FnOnce(&mut MaybeUninit<F>),
+            S: FnOnce(T, &mut MaybeUninit<F>),
         {
             let data = &mut *self.data.borrow_mut();
 
-            if data.nodes.len() == data.nodes.capacity() {
-                return Err(());
-            }
-
-            let entry = data.nodes.vacant_entry();
-            let key = entry.key();
+            let key = match data.nodes.vacant_key() {
+                Some(key) => key,
+                None => return Err(arg),
+            };
 
             let waker = EmbedWaker::new(self, key);
 
-            let task = Task { waker, awake: true };
+            let generation = data.generation;
+            data.generation += 1;
 
-            entry.insert(list::Node::new(task));
+            let task = Task {
+                waker,
+                awake: true,
+                live: true,
+                generation,
+            };
+
+            assert_eq!(data.nodes.insert(list::Node::new(task)).ok(), Some(key));
+
+            // both use the same chunk size, so they grow at the same time
+            if key >= data.futs.capacity() {
+                data.futs.grow(|_| MaybeUninit::uninit());
+            }
 
             data.next.push_back(&mut data.nodes, key);
+            data.running += 1;
 
-            get_fut_fn(&mut data.futs[key]);
+            init_fn(arg, &mut data.futs[key]);
 
-            Ok(())
+            Ok((key, generation))
         }
 
         fn wake(&self, task_id: usize) {
@@ -82,7 +203,7 @@ mod arg {
 
             let task = &mut data.nodes[task_id].value;
 
-            if !task.awake {
+            if task.live && !task.awake {
                 task.awake = true;
 
                 data.next.remove(&mut data.nodes, task_id);
@@ -90,6 +211,64 @@ mod arg {
             }
         }
 
+        fn abort(&self, task_id: usize, generation: u64) {
+            let fut_ptr = {
+                let data = &mut *self.data.borrow_mut();
+
+                let task = match data.nodes.get_mut(task_id) {
+                    Some(node) if node.value.generation == generation => &mut node.value,
+                    _ => return,
+                };
+
+                if !task.live {
+                    return;
+                }
+
+                task.live = false;
+
+                // if the task is aborting itself, then process_next will
+                // drop it once the poll returns
+                if data.current == Some(task_id) {
+                    return;
+                }
+
+                data.next.remove(&mut data.nodes, task_id);
+
+                unsafe { data.futs[task_id].assume_init_mut() as *mut F }
+            };
+
+            self.drop_task(task_id, fut_ptr);
+        }
+
+        fn drop_task(&self, task_id: usize, fut_ptr: *mut F) {
+            // SAFETY: the task is no longer live, so nothing else will poll
+            // or drop the future. we drop it without holding the borrow,
+            // since dropping may wake other tasks, and wakes to this task
+            // are ignored since it's not live
+            unsafe { ptr::drop_in_place(fut_ptr) };
+
+            let tasks = &mut *self.data.borrow_mut();
+
+            tasks.running -= 1;
+            tasks.next.remove(&mut tasks.nodes, task_id);
+
+            let task = &tasks.nodes[task_id].value;
+
+            // if the waker is still held elsewhere, then the slot stays
+            // reserved until the last clone is dropped
+            if task.waker.ref_count() > 1 {
+                task.waker.orphan();
+            } else {
+                tasks.nodes.remove(task_id);
+            }
+        }
+
+        fn free(&self, task_id: usize) {
+            let tasks = &mut *self.data.borrow_mut();
+
+            tasks.nodes.remove(task_id);
+        }
+
         fn process_next(&self) {
             loop {
                 let (nkey, task_ptr, fut_ptr) = {
@@ -108,20 +287,22 @@ mod arg {
 
                     let fut = unsafe { tasks.futs[nkey].assume_init_mut() };
 
+                    tasks.current = Some(nkey);
+
                     (nkey, task as *mut Task<Self>, fut as *mut F)
                 };
 
                 // SAFETY: task won't move/drop while this pointer is in use.
-                // we don't allow inserting into the slab beyond its capacity,
-                // therefore its items never move. and the only place we remove
-                // the pointed-to item is at the end of this function, after we
-                // are no longer using the pointer
+                // the arena only grows by adding chunks, and existing chunks
+                // never move, therefore its items never move. and the only
+                // place we remove the pointed-to item is at the end of this
+                // function, after we are no longer using the pointer
                 let task = unsafe { task_ptr.as_mut().unwrap() };
 
                 // SAFETY: fut never moves, and won't drop while this pointer
-                // is in use. we don't add or remove items to/from the vec
-                // after its initialization. drop is done in-place, after we
-                // are no longer using the pointer
+                // is in use. futs only grows by adding chunks, and existing
+                // chunks never move. drop is done in-place, after we are no
+                // longer using the pointer
                 let mut fut = unsafe { Pin::new_unchecked(fut_ptr.as_mut().unwrap()) };
 
                 let done = {
@@ -133,23 +314,25 @@ mod arg {
 
                     let mut cx = Context::from_waker(w.as_std(&mut waker_mem));
 
-                    match fut.as_mut().poll(&mut cx) {
-                        Poll::Ready(_) => true,
-                        Poll::Pending => false,
-                    }
+                    fut.as_mut().poll(&mut cx).is_ready()
                 };
 
-                if done {
+                let live = {
                     let tasks = &mut *self.data.borrow_mut();
 
-                    unsafe { tasks.futs[nkey].assume_init_drop() };
+                    tasks.current = None;
 
                     let task = &mut tasks.nodes[nkey].value;
 
-                    assert_eq!(task.waker.ref_count(), 1);
+                    if done {
+                        task.live = false;
+                    }
 
-                    tasks.next.remove(&mut tasks.nodes, nkey);
-                    tasks.nodes.remove(nkey);
+                    task.live
+                };
+
+                if !live {
+                    self.drop_task(nkey, fut_ptr);
                 }
             }
         }
@@ -162,11 +345,45 @@ mod arg {
         fn wake(&self, task_id: usize) {
             Tasks::wake(self, task_id);
         }
+
+        fn free(&self, task_id: usize) {
+            Tasks::free(self, task_id);
+        }
+    }
+
+    trait Abort {
+        fn abort(&self, task_id: usize, generation: u64);
+    }
+
+    impl<F> Abort for Tasks<'_, F>
+    where
+        F: Future<Output = ()>,
+    {
+        fn abort(&self, task_id: usize, generation: u64) {
+            Tasks::abort(self, task_id, generation);
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    pub struct AbortHandle<'a> {
+        tasks: &'a dyn Abort,
+        task_id: usize,
+        generation: u64,
+    }
+
+    impl AbortHandle<'_> {
+        // drops the task's future in place, if the task is still running
+        pub fn abort(&self) {
+            self.tasks.abort(self.task_id, self.generation);
+        }
     }
 
+    type SpawnFn<A> = unsafe fn(*const (), A) -> Result<(usize, u64), SpawnError<A>>;
+
     struct SpawnerData<A> {
         ctx: *const (),
-        spawn_fn: unsafe fn(*const (), A) -> Result<(), ()>,
+        spawn_fn: SpawnFn<A>,
+        abort_fn: unsafe fn(*const (), usize, u64),
     }
 
     pub struct ArgSpawner<A> {
@@ -180,10 +397,24 @@ mod arg {
             }
         }
 
-        pub fn spawn(&self, arg: A) -> Result<(), ()> {
-            match &*self.data.borrow() {
-                Some(data) => unsafe { (data.spawn_fn)(data.ctx, arg) },
-                None => Err(()),
+        pub fn spawn(&self, arg: A) -> Result<AbortHandle<'_>, SpawnError<A>> {
+            let (task_id, generation) = match &*self.data.borrow() {
+                Some(data) => unsafe { (data.spawn_fn)(data.ctx, arg)? },
+                None => return Err(SpawnError::ExecutorGone(arg)),
+            };
+
+            Ok(AbortHandle {
+                tasks: self,
+                task_id,
+                generation,
+            })
+        }
+    }
+
+    impl<A> Abort for ArgSpawner<A> {
+        fn abort(&self, task_id: usize, generation: u64) {
+            if let Some(data) = &*self.data.borrow() {
+                unsafe { (data.abort_fn)(data.ctx, task_id, generation) };
             }
         }
     }
@@ -201,14 +432,33 @@ mod arg {
     {
         pub fn new(tasks_max: usize, spawn_fn: S) -> Self {
             Self {
-                tasks: Tasks::new(tasks_max),
+                tasks: Tasks::new(tasks_max, false),
                 spawn_fn,
                 spawner: RefCell::new(None),
             }
         }
 
-        pub fn spawn(&'ex self, arg: A) -> Result<(), ()> {
-            self.tasks.add(|dest| (self.spawn_fn)(arg, dest))
+        // task storage is added in chunks as needed, so spawning never fails
+        // for lack of capacity
+        pub fn growable(chunk_size: usize, spawn_fn: S) -> Self {
+            Self {
+                tasks: Tasks::new(chunk_size, true),
+                spawn_fn,
+                spawner: RefCell::new(None),
+            }
+        }
+
+        pub fn spawn(&'ex self, arg: A) -> Result<AbortHandle<'ex>, SpawnError<A>> {
+            let (task_id, generation) = self
+                .tasks
+                .add(arg, |arg, dest| (self.spawn_fn)(arg, dest))
+                .map_err(SpawnError::AtCapacity)?;
+
+            Ok(AbortHandle {
+                tasks: &self.tasks,
+                task_id,
+                generation,
+            })
         }
 
         pub fn set_spawner(&self, spawner: &'sp ArgSpawner<A>) {
@@ -220,13 +470,22 @@ mod arg {
             *spawner.data.borrow_mut() = Some(SpawnerData {
                 ctx: self as *const Self as *const (),
                 spawn_fn: Self::spawn_by_arg_fn,
+                abort_fn: Self::abort_fn,
             });
         }
 
-        unsafe fn spawn_by_arg_fn(ctx: *const (), arg: A) -> Result<(), ()> {
+        unsafe fn spawn_by_arg_fn(ctx: *const (), arg: A) -> Result<(usize, u64), SpawnError<A>> {
+            let executor = { (ctx as *const Self).as_ref().unwrap() };
+
+            executor
+                .spawn(arg)
+                .map(|handle| (handle.task_id, handle.generation))
+        }
+
+        unsafe fn abort_fn(ctx: *const (), task_id: usize, generation: u64) {
             let executor = { (ctx as *const Self).as_ref().unwrap() };
 
-            executor.spawn(arg)
+            executor.tasks.abort(task_id, generation);
         }
 
         pub fn run<P>(&self, park: P)
@@ -255,25 +514,96 @@ mod arg {
 }
 
 mod bx {
+    use super::{JoinError, SpawnError};
+    use crate::arena::Arena;
     use crate::list;
     use crate::waker::{EmbedWake, EmbedWaker};
-    use slab::Slab;
+    use std::any::Any;
     use std::cell::RefCell;
     use std::future::Future;
     use std::io;
-    use std::mem::MaybeUninit;
+    use std::mem::{self, MaybeUninit};
+    use std::panic::{self, AssertUnwindSafe};
     use std::pin::Pin;
-    use std::task::{Context, Poll};
+    use std::task::{Context, Poll, Waker};
+
+    type LocalBoxFuture<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+    // wraps a spawned future so that its output can be kept in the same
+    // allocation as the future, until a JoinHandle picks it up
+    enum Stage<F: Future> {
+        Running(F),
+        Finished(Option<F::Output>),
+    }
+
+    impl<F: Future> Stage<F> {
+        // SAFETY: ptr must point to a Stage<F> that has finished, and the
+        // output must not have been taken already
+        unsafe fn take_output(ptr: *mut ()) -> F::Output {
+            match &mut *(ptr as *mut Self) {
+                Stage::Finished(output) => output.take().unwrap(),
+                Stage::Running(_) => unreachable!(),
+            }
+        }
+
+        // SAFETY: fut must have been created from a Stage<F> that was never
+        // polled, so moving the inner future out of the box is fine
+        unsafe fn into_inner(fut: LocalBoxFuture<'_>) -> F {
+            let ptr = Box::into_raw(Pin::into_inner_unchecked(fut)) as *mut Self;
+
+            match *Box::from_raw(ptr) {
+                Stage::Running(fut) => fut,
+                Stage::Finished(_) => unreachable!(),
+            }
+        }
+    }
+
+    impl<F: Future> Future for Stage<F> {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            let output = {
+                // SAFETY: the inner future is never moved out. it is only
+                // dropped in place, by the call to set() below
+                let fut = match unsafe { self.as_mut().get_unchecked_mut() } {
+                    Stage::Running(fut) => unsafe { Pin::new_unchecked(fut) },
+                    Stage::Finished(_) => return Poll::Ready(()),
+                };
+
+                match fut.poll(cx) {
+                    Poll::Ready(output) => output,
+                    Poll::Pending => return Poll::Pending,
+                }
+            };
+
+            self.set(Stage::Finished(Some(output)));
+
+            Poll::Ready(())
+        }
+    }
+
+    enum TaskState {
+        Running,
+        Finished,
+        Panicked(Box<dyn Any + Send>),
+        Cancelled,
+    }
 
     struct Task<'a, W> {
-        fut: Option<Pin<Box<dyn Future<Output = ()> + 'a>>>,
+        fut: Option<LocalBoxFuture<'a>>,
         waker: EmbedWaker<'a, W>,
         awake: bool,
+        state: TaskState,
+        join_waker: Option<Waker>,
+        detached: bool,
+        abort: bool,
     }
 
     struct TasksData<'a, W> {
-        nodes: Slab<list::Node<Task<'a, W>>>,
+        nodes: Arena<list::Node<Task<'a, W>>>,
         next: list::List,
+        running: usize,
+        current: Option<usize>,
     }
 
     struct Tasks<'a> {
@@ -281,10 +611,12 @@ mod bx {
     }
 
     impl<'a> Tasks<'a> {
-        fn new(tasks_max: usize) -> Self {
+        fn new(nodes: Arena<list::Node<Task<'a, Self>>>) -> Self {
             let data = TasksData {
-                nodes: Slab::with_capacity(tasks_max),
+                nodes,
                 next: list::List::default(),
+                running: 0,
+                current: None,
             };
 
             Self {
@@ -293,18 +625,16 @@ mod bx {
         }
 
         fn is_empty(&self) -> bool {
-            self.data.borrow().nodes.is_empty()
+            self.data.borrow().running == 0
         }
 
-        fn add(&'a self, f: Pin<Box<dyn Future<Output = ()> + 'a>>) -> Result<(), ()> {
+        fn add<'f: 'a>(&'a self, f: LocalBoxFuture<'f>) -> Result<usize, LocalBoxFuture<'f>> {
             let data = &mut *self.data.borrow_mut();
 
-            if data.nodes.len() == data.nodes.capacity() {
-                return Err(());
-            }
-
-            let entry = data.nodes.vacant_entry();
-            let key = entry.key();
+            let key = match data.nodes.vacant_key() {
+                Some(key) => key,
+                None => return Err(f),
+            };
 
             let waker = EmbedWaker::new(self, key);
 
@@ -312,13 +642,18 @@ mod bx {
                 fut: Some(f),
                 waker,
                 awake: true,
+                state: TaskState::Running,
+                join_waker: None,
+                detached: false,
+                abort: false,
             };
 
-            entry.insert(list::Node::new(task));
+            assert_eq!(data.nodes.insert(list::Node::new(task)).ok(), Some(key));
 
             data.next.push_back(&mut data.nodes, key);
+            data.running += 1;
 
-            Ok(())
+            Ok(key)
         }
 
         fn wake(&self, task_id: usize) {
@@ -326,7 +661,7 @@ mod bx {
 
             let task = &mut data.nodes[task_id].value;
 
-            if !task.awake {
+            if matches!(task.state, TaskState::Running) && !task.awake {
                 task.awake = true;
 
                 data.next.remove(&mut data.nodes, task_id);
@@ -334,6 +669,47 @@ mod bx {
             }
         }
 
+        fn abort(&self, task_id: usize) {
+            let fut = {
+                let data = &mut *self.data.borrow_mut();
+
+                let task = &mut data.nodes[task_id].value;
+
+                if !matches!(task.state, TaskState::Running) {
+                    return;
+                }
+
+                // if the task is aborting itself, then process_next will
+                // drop it once the poll returns
+                if data.current == Some(task_id) {
+                    task.abort = true;
+
+                    return;
+                }
+
+                task.state = TaskState::Cancelled;
+
+                data.running -= 1;
+                data.next.remove(&mut data.nodes, task_id);
+
+                data.nodes[task_id].value.fut.take()
+            };
+
+            // drop outside of the borrow, since dropping may wake other
+            // tasks. wakes to this task are ignored since it's not running
+            drop(fut);
+
+            let join_waker = {
+                let data = &mut *self.data.borrow_mut();
+
+                data.nodes[task_id].value.join_waker.take()
+            };
+
+            if let Some(waker) = join_waker {
+                waker.wake();
+            }
+        }
+
         fn process_next(&self) {
             loop {
                 let (nkey, task_ptr) = {
@@ -350,19 +726,20 @@ mod bx {
 
                     task.awake = false;
 
+                    tasks.current = Some(nkey);
+
                     (nkey, task as *mut Task<Self>)
                 };
 
                 // SAFETY: task won't move/drop while this pointer is in use.
-                // we don't allow inserting into the slab beyond its capacity,
-                // therefore its items never move. and the only place we remove
-                // the pointed-to item is at the end of this function, after we
-                // are no longer using the pointer
+                // the arena only grows by adding chunks, and existing chunks
+                // never move, therefore its items never move. and the only
+                // place we remove the pointed-to item is at the end of this
+                // function, after we are no longer using the pointer
                 let task = unsafe { task_ptr.as_mut().unwrap() };
 
-                let done = {
-                    let fut: &mut Pin<Box<dyn Future<Output = ()> + 'a>> =
-                        task.fut.as_mut().unwrap();
+                let result = {
+                    let fut: &mut LocalBoxFuture<'a> = task.fut.as_mut().unwrap();
 
                     // SAFETY: as established above, the task won't move,
                     //   thus neither will the waker field
@@ -372,78 +749,382 @@ mod bx {
 
                     let mut cx = Context::from_waker(w.as_std(&mut waker_mem));
 
-                    match fut.as_mut().poll(&mut cx) {
-                        Poll::Ready(_) => true,
-                        Poll::Pending => false,
+                    panic::catch_unwind(AssertUnwindSafe(|| fut.as_mut().poll(&mut cx)))
+                };
+
+                let abort = {
+                    let tasks = &mut *self.data.borrow_mut();
+
+                    tasks.current = None;
+
+                    tasks.nodes[nkey].value.abort
+                };
+
+                let state = match result {
+                    Ok(Poll::Ready(())) => TaskState::Finished,
+                    Ok(Poll::Pending) if abort => {
+                        task.fut = None;
+
+                        TaskState::Cancelled
+                    }
+                    Ok(Poll::Pending) => continue,
+                    Err(e) => {
+                        // a panicked future can't be resumed, so drop it now
+                        task.fut = None;
+
+                        TaskState::Panicked(e)
                     }
                 };
 
-                if done {
-                    task.fut = None;
+                // a completed task is kept around, with its output, until
+                // its JoinHandle collects it or is dropped
+                let fut = if task.detached { task.fut.take() } else { None };
 
-                    assert_eq!(task.waker.ref_count(), 1);
+                drop(fut);
 
+                let join_waker = {
                     let tasks = &mut *self.data.borrow_mut();
 
+                    tasks.running -= 1;
+
+                    // the task may have woken itself during its final poll
                     tasks.next.remove(&mut tasks.nodes, nkey);
-                    tasks.nodes.remove(nkey);
+
+                    let task = &mut tasks.nodes[nkey].value;
+
+                    task.state = state;
+
+                    let join_waker = task.join_waker.take();
+
+                    if task.detached {
+                        Self::remove(tasks, nkey);
+                    }
+
+                    join_waker
+                };
+
+                if let Some(waker) = join_waker {
+                    waker.wake();
+                }
+            }
+        }
+
+        fn poll_join(&self, task_id: usize, waker: &Waker) -> Poll<Result<*mut (), JoinError>> {
+            let data = &mut *self.data.borrow_mut();
+
+            let task = &mut data.nodes[task_id].value;
+
+            match &task.state {
+                TaskState::Running => {
+                    if let Some(current_waker) = &task.join_waker {
+                        if current_waker.will_wake(waker) {
+                            // keep the current waker
+                            return Poll::Pending;
+                        }
+                    }
+
+                    task.join_waker = Some(waker.clone());
+
+                    Poll::Pending
+                }
+                TaskState::Finished => {
+                    let fut = task.fut.as_mut().unwrap();
+
+                    // SAFETY: we only hand out a pointer to the future, which
+                    // the JoinHandle uses to take the output. nothing is moved
+                    let fut = unsafe { fut.as_mut().get_unchecked_mut() };
+
+                    Poll::Ready(Ok(fut as *mut dyn Future<Output = ()> as *mut ()))
+                }
+                TaskState::Panicked(_) => {
+                    match mem::replace(&mut task.state, TaskState::Cancelled) {
+                        TaskState::Panicked(e) => Poll::Ready(Err(JoinError::Panicked(e))),
+                        _ => unreachable!(),
+                    }
+                }
+                TaskState::Cancelled => Poll::Ready(Err(JoinError::Cancelled)),
+            }
+        }
+
+        fn release(&self, task_id: usize) {
+            let fut = {
+                let data = &mut *self.data.borrow_mut();
+
+                let task = &mut data.nodes[task_id].value;
+
+                match task.state {
+                    TaskState::Running => {
+                        task.detached = true;
+                        task.join_waker = None;
+
+                        None
+                    }
+                    _ => Self::remove(data, task_id),
                 }
+            };
+
+            // drop outside of the borrow, in case the output refers back to
+            // the executor
+            drop(fut);
+        }
+
+        // removes a task that is done and whose JoinHandle is gone. if its
+        // waker is still held elsewhere, then the slot stays reserved until
+        // the last clone is dropped, and wakes to it are ignored since it's
+        // not running
+        fn remove(data: &mut TasksData<'a, Self>, task_id: usize) -> Option<LocalBoxFuture<'a>> {
+            let task = &mut data.nodes[task_id].value;
+
+            if task.waker.ref_count() > 1 {
+                task.waker.orphan();
+
+                task.fut.take()
+            } else {
+                data.nodes.remove(task_id).value.fut
             }
         }
+
+        fn free(&self, task_id: usize) {
+            let fut = {
+                let data = &mut *self.data.borrow_mut();
+
+                data.nodes.remove(task_id).value.fut
+            };
+
+            drop(fut);
+        }
     }
 
     impl EmbedWake for Tasks<'_> {
         fn wake(&self, task_id: usize) {
             Tasks::wake(self, task_id);
         }
-    }
 
-    struct SpawnerData<'a> {
-        ctx: *const (),
-        spawn_fn: unsafe fn(*const (), Pin<Box<dyn Future<Output = ()> + 'a>>) -> Result<(), ()>,
+        fn free(&self, task_id: usize) {
+            Tasks::free(self, task_id);
+        }
     }
 
-    pub struct BoxSpawner<'a> {
-        data: RefCell<Option<SpawnerData<'a>>>,
-    }
+    impl Drop for Tasks<'_> {
+        fn drop(&mut self) {
+            // futures may hold JoinHandles that refer back to the executor,
+            // so drop them one at a time without holding the borrow
+            loop {
+                let fut = {
+                    let data = &mut *self.data.borrow_mut();
 
-    impl<'a> BoxSpawner<'a> {
-        pub fn new() -> Self {
-            Self {
-                data: RefCell::new(None),
-            }
-        }
+                    let fut = data
+                        .nodes
+                        .iter_mut()
+                        .find_map(|(_, node)| node.value.fut.take());
 
-        pub fn spawn<F>(&self, f: F) -> Result<(), ()>
-        where
-            F: Future<Output = ()> + 'a,
-        {
-            self.spawn_boxed(Box::pin(f))
-        }
+                    fut
+                };
 
-        pub fn spawn_boxed(&self, f: Pin<Box<dyn Future<Output = ()> + 'a>>) -> Result<(), ()> {
-            match &*self.data.borrow() {
-                Some(data) => unsafe { (data.spawn_fn)(data.ctx, f) },
-                None => Err(()),
+                match fut {
+                    Some(fut) => drop(fut),
+                    None => break,
+                }
             }
         }
     }
 
-    pub struct BoxExecutor<'sp: 'ex, 'ex> {
-        tasks: Tasks<'ex>,
-        spawner: RefCell<Option<&'sp BoxSpawner<'sp>>>,
+    trait Join {
+        fn poll_join(&self, task_id: usize, waker: &Waker) -> Poll<Result<*mut (), JoinError>>;
+
+        fn abort(&self, task_id: usize);
+
+        fn release(&self, task_id: usize);
     }
 
-    impl<'sp: 'ex, 'ex> BoxExecutor<'sp, 'ex> {
-        pub fn new(tasks_max: usize) -> Self {
-            Self {
-                tasks: Tasks::new(tasks_max),
-                spawner: RefCell::new(None),
-            }
+    impl Join for Tasks<'_> {
+        fn poll_join(&self, task_id: usize, waker: &Waker) -> Poll<Result<*mut (), JoinError>> {
+            Tasks::poll_join(self, task_id, waker)
         }
 
-        pub fn spawn(&'ex self, f: Pin<Box<dyn Future<Output = ()> + 'sp>>) -> Result<(), ()> {
-            self.tasks.add(f)
+        fn abort(&self, task_id: usize) {
+            Tasks::abort(self, task_id)
+        }
+
+        fn release(&self, task_id: usize) {
+            Tasks::release(self, task_id)
+        }
+    }
+
+    pub struct JoinHandle<'a, T> {
+        joiner: &'a dyn Join,
+        task_id: Option<usize>,
+        take_output: unsafe fn(*mut ()) -> T,
+    }
+
+    impl<'a, T> JoinHandle<'a, T> {
+        fn new(joiner: &'a dyn Join, task_id: usize, take_output: unsafe fn(*mut ()) -> T) -> Self {
+            Self {
+                joiner,
+                task_id: Some(task_id),
+                take_output,
+            }
+        }
+
+        // drops the task's future in place, if the task is still running.
+        // awaiting the handle afterwards returns JoinError::Cancelled
+        pub fn abort(&self) {
+            if let Some(task_id) = self.task_id {
+                self.joiner.abort(task_id);
+            }
+        }
+
+        // give up the handle without releasing the task
+        fn into_task_id(mut self) -> usize {
+            self.task_id.take().unwrap()
+        }
+    }
+
+    impl<T> Future for JoinHandle<'_, T> {
+        type Output = Result<T, JoinError>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            let task_id = self.task_id.expect("join handle polled after completion");
+
+            let ret = match self.joiner.poll_join(task_id, cx.waker()) {
+                // SAFETY: the pointer refers to the finished future that was
+                // spawned along with this handle, and it stays valid until we
+                // release the task
+                Poll::Ready(Ok(ptr)) => Ok(unsafe { (self.take_output)(ptr) }),
+                Poll::Ready(Err(e)) => Err(e),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            self.joiner.release(task_id);
+            self.task_id = None;
+
+            Poll::Ready(ret)
+        }
+    }
+
+    impl<T> Drop for JoinHandle<'_, T> {
+        fn drop(&mut self) {
+            if let Some(task_id) = self.task_id {
+                self.joiner.release(task_id);
+            }
+        }
+    }
+
+    unsafe fn take_unit(_ptr: *mut ()) {}
+
+    type PollJoinFn = unsafe fn(*const (), usize, &Waker) -> Poll<Result<*mut (), JoinError>>;
+
+    type SpawnFn<'a> =
+        unsafe fn(*const (), LocalBoxFuture<'a>) -> Result<usize, SpawnError<LocalBoxFuture<'a>>>;
+
+    struct SpawnerData<'a> {
+        ctx: *const (),
+        spawn_fn: SpawnFn<'a>,
+        poll_join_fn: PollJoinFn,
+        abort_fn: unsafe fn(*const (), usize),
+        release_fn: unsafe fn(*const (), usize),
+    }
+
+    pub struct BoxSpawner<'a> {
+        data: RefCell<Option<SpawnerData<'a>>>,
+    }
+
+    impl<'a> BoxSpawner<'a> {
+        pub fn new() -> Self {
+            Self {
+                data: RefCell::new(None),
+            }
+        }
+
+        pub fn spawn<F>(&self, f: F) -> Result<JoinHandle<'_, F::Output>, SpawnError<F>>
+        where
+            F: Future + 'a,
+        {
+            let task_id = self.spawn_raw(Box::pin(Stage::Running(f))).map_err(|e| {
+                // SAFETY: the future was boxed above and never spawned
+                match e {
+                    SpawnError::AtCapacity(fut) => {
+                        SpawnError::AtCapacity(unsafe { Stage::into_inner(fut) })
+                    }
+                    SpawnError::ExecutorGone(fut) => {
+                        SpawnError::ExecutorGone(unsafe { Stage::into_inner(fut) })
+                    }
+                }
+            })?;
+
+            Ok(JoinHandle::new(self, task_id, Stage::<F>::take_output))
+        }
+
+        pub fn spawn_boxed(
+            &self,
+            f: LocalBoxFuture<'a>,
+        ) -> Result<JoinHandle<'_, ()>, SpawnError<LocalBoxFuture<'a>>> {
+            let task_id = self.spawn_raw(f)?;
+
+            Ok(JoinHandle::new(self, task_id, take_unit))
+        }
+
+        fn spawn_raw(
+            &self,
+            f: LocalBoxFuture<'a>,
+        ) -> Result<usize, SpawnError<LocalBoxFuture<'a>>> {
+            match &*self.data.borrow() {
+                Some(data) => unsafe { (data.spawn_fn)(data.ctx, f) },
+                None => Err(SpawnError::ExecutorGone(f)),
+            }
+        }
+    }
+
+    impl Join for BoxSpawner<'_> {
+        fn poll_join(&self, task_id: usize, waker: &Waker) -> Poll<Result<*mut (), JoinError>> {
+            match &*self.data.borrow() {
+                Some(data) => unsafe { (data.poll_join_fn)(data.ctx, task_id, waker) },
+                None => Poll::Ready(Err(JoinError::Cancelled)),
+            }
+        }
+
+        fn abort(&self, task_id: usize) {
+            if let Some(data) = &*self.data.borrow() {
+                unsafe { (data.abort_fn)(data.ctx, task_id) }
+            }
+        }
+
+        fn release(&self, task_id: usize) {
+            if let Some(data) = &*self.data.borrow() {
+                unsafe { (data.release_fn)(data.ctx, task_id) }
+            }
+        }
+    }
+
+    pub struct BoxExecutor<'sp: 'ex, 'ex> {
+        tasks: Tasks<'ex>,
+        spawner: RefCell<Option<&'sp BoxSpawner<'sp>>>,
+    }
+
+    impl<'sp: 'ex, 'ex> BoxExecutor<'sp, 'ex> {
+        pub fn new(tasks_max: usize) -> Self {
+            Self {
+                tasks: Tasks::new(Arena::fixed(tasks_max)),
+                spawner: RefCell::new(None),
+            }
+        }
+
+        // task storage is added in chunks as needed, so spawning never fails
+        // for lack of capacity
+        pub fn growable(chunk_size: usize) -> Self {
+            Self {
+                tasks: Tasks::new(Arena::growable(chunk_size)),
+                spawner: RefCell::new(None),
+            }
+        }
+
+        pub fn spawn(
+            &'ex self,
+            f: LocalBoxFuture<'sp>,
+        ) -> Result<JoinHandle<'ex, ()>, SpawnError<LocalBoxFuture<'sp>>> {
+            let task_id = self.tasks.add(f).map_err(SpawnError::AtCapacity)?;
+
+            Ok(JoinHandle::new(&self.tasks, task_id, take_unit))
         }
 
         pub fn set_spawner(&self, spawner: &'sp BoxSpawner<'sp>) {
@@ -455,16 +1136,41 @@ mod bx {
             *spawner.data.borrow_mut() = Some(SpawnerData {
                 ctx: self as *const Self as *const (),
                 spawn_fn: Self::spawn_fn,
+                poll_join_fn: Self::poll_join_fn,
+                abort_fn: Self::abort_fn,
+                release_fn: Self::release_fn,
             });
         }
 
         unsafe fn spawn_fn(
             ctx: *const (),
-            f: Pin<Box<dyn Future<Output = ()> + 'sp>>,
-        ) -> Result<(), ()> {
+            f: LocalBoxFuture<'sp>,
+        ) -> Result<usize, SpawnError<LocalBoxFuture<'sp>>> {
+            let executor = { (ctx as *const Self).as_ref().unwrap() };
+
+            executor.spawn(f).map(JoinHandle::into_task_id)
+        }
+
+        unsafe fn poll_join_fn(
+            ctx: *const (),
+            task_id: usize,
+            waker: &Waker,
+        ) -> Poll<Result<*mut (), JoinError>> {
+            let executor = { (ctx as *const Self).as_ref().unwrap() };
+
+            executor.tasks.poll_join(task_id, waker)
+        }
+
+        unsafe fn abort_fn(ctx: *const (), task_id: usize) {
+            let executor = { (ctx as *const Self).as_ref().unwrap() };
+
+            executor.tasks.abort(task_id)
+        }
+
+        unsafe fn release_fn(ctx: *const (), task_id: usize) {
             let executor = { (ctx as *const Self).as_ref().unwrap() };
 
-            executor.spawn(f)
+            executor.tasks.release(task_id)
         }
 
         pub fn run<P>(&self, park: P)
@@ -493,10 +1199,10 @@ mod bx {
 }
 
 mod boxrc {
-    use super::BoxFuture;
+    use super::{BoxFuture, SpawnError};
+    use crate::arena::{Arena, Chunks};
     use crate::list;
     use crate::waker::{CheckedLocalWake, LocalWake, WakerFactory};
-    use slab::Slab;
     use std::cell::RefCell;
     use std::future::Future;
     use std::io;
@@ -558,98 +1264,126 @@ mod boxrc {
     struct Task {
         fut: Option<BoxFuture>,
         awake: bool,
+        live: bool,
+        generation: u64,
     }
 
     struct TasksData {
-        nodes: Slab<list::Node<Task>>,
+        nodes: Arena<list::Node<Task>>,
         next: list::List,
+        current: Option<usize>,
+        generation: u64,
     }
 
+    // a waker and a function returning its strong count
+    type WakerEntry = (Waker, Box<dyn Fn() -> usize>);
+
+    type NewWakerFn = Box<dyn Fn(usize) -> WakerEntry>;
+
     struct Tasks {
         data: RefCell<TasksData>,
-        wakers: Vec<Waker>,
-        waker_strong_counts: Vec<Box<dyn Fn() -> usize>>,
+        // per task id. grown along with nodes
+        wakers: RefCell<Chunks<WakerEntry>>,
+        new_waker: NewWakerFn,
     }
 
     impl Tasks {
-        fn new<W>(tasks_max: usize, waker_factory: W) -> Rc<Self>
+        // if growable, capacity is the size of each chunk of task storage
+        fn new<W>(capacity: usize, growable: bool, waker_factory: W) -> Rc<Self>
         where
-            W: WakerFactory,
+            W: WakerFactory + 'static,
         {
+            let nodes = if growable {
+                Arena::growable(capacity)
+            } else {
+                Arena::fixed(capacity)
+            };
+
             let data = TasksData {
-                nodes: Slab::with_capacity(tasks_max),
+                nodes,
                 next: list::List::default(),
+                current: None,
+                generation: 0,
             };
 
-            let tasks = {
-                let tasks = Rc::new(Self {
-                    data: RefCell::new(data),
-                    wakers: Vec::new(),
-                    waker_strong_counts: Vec::new(),
-                });
+            Rc::new_cyclic(|tasks| {
+                let tasks = tasks.clone();
+                let thread_id = thread::current().id();
 
-                let mut wakers = Vec::with_capacity(tasks_max);
-                let mut strong_counts = Vec::with_capacity(tasks_max);
-
-                for task_id in 0..wakers.capacity() {
-                    let (waker, c) = waker_factory.new_waker(TaskWaker {
-                        tasks: Rc::downgrade(&tasks),
+                let new_waker: NewWakerFn = Box::new(move |task_id| {
+                    waker_factory.new_waker(TaskWaker {
+                        tasks: tasks.clone(),
                         task_id,
-                        thread_id: thread::current().id(),
-                    });
-
-                    wakers.push(waker);
-                    strong_counts.push(c);
-                }
+                        thread_id,
+                    })
+                });
 
-                // SAFETY: we can modify the content of the Rc here because
-                // nothing is accessing it yet. we only just constructed the Rc
-                // above, and the TaskWakers take refs but they don't access
-                // the Rc content at rest
-                unsafe {
-                    let tasks = Rc::into_raw(tasks) as *mut Tasks;
-                    (*tasks).wakers = wakers;
-                    (*tasks).waker_strong_counts = strong_counts;
+                let wakers = if growable {
+                    Chunks::growable(capacity, &new_waker)
+                } else {
+                    Chunks::fixed(capacity, &new_waker)
+                };
 
-                    Rc::from_raw(tasks)
+                Self {
+                    data: RefCell::new(data),
+                    wakers: RefCell::new(wakers),
+                    new_waker,
                 }
-            };
-
-            tasks
+            })
         }
 
         fn is_empty(&self) -> bool {
             self.data.borrow().nodes.is_empty()
         }
 
-        fn add(&self, f: BoxFuture) -> Result<(), ()> {
+        // the future is only boxed if there is room for it. otherwise it is
+        // returned
+        fn add<F>(&self, f: F) -> Result<(usize, u64), F>
+        where
+            F: Future<Output = ()> + 'static,
+        {
             let data = &mut *self.data.borrow_mut();
 
-            if data.nodes.len() == data.nodes.capacity() {
-                return Err(());
-            }
+            let key = match data.nodes.vacant_key() {
+                Some(key) => key,
+                None => return Err(f),
+            };
 
-            let entry = data.nodes.vacant_entry();
-            let key = entry.key();
+            let generation = data.generation;
 
             let task = Task {
-                fut: Some(f),
+                fut: Some(Box::pin(f)),
                 awake: true,
+                live: true,
+                generation,
             };
 
-            entry.insert(list::Node::new(task));
+            assert_eq!(data.nodes.insert(list::Node::new(task)).ok(), Some(key));
+
+            data.generation += 1;
+
+            // both use the same chunk size, so they grow at the same time
+            let wakers = &mut *self.wakers.borrow_mut();
+
+            if key >= wakers.capacity() {
+                wakers.grow(&self.new_waker);
+            }
 
             data.next.push_back(&mut data.nodes, key);
 
-            Ok(())
+            Ok((key, generation))
         }
 
         fn wake(&self, task_id: usize) {
             let data = &mut *self.data.borrow_mut();
 
-            let task = &mut data.nodes[task_id].value;
+            // wakers may outlive aborted tasks, so the slot could be empty
+            let task = match data.nodes.get_mut(task_id) {
+                Some(node) => &mut node.value,
+                None => return,
+            };
 
-            if !task.awake {
+            if task.live && !task.awake {
                 task.awake = true;
 
                 data.next.remove(&mut data.nodes, task_id);
@@ -657,6 +1391,47 @@ mod boxrc {
             }
         }
 
+        fn abort(&self, task_id: usize, generation: u64) {
+            let fut = {
+                let data = &mut *self.data.borrow_mut();
+
+                let task = match data.nodes.get_mut(task_id) {
+                    Some(node) if node.value.generation == generation => &mut node.value,
+                    _ => return,
+                };
+
+                if !task.live {
+                    return;
+                }
+
+                task.live = false;
+
+                // if the task is aborting itself, then process_next will
+                // drop it once the poll returns
+                if data.current == Some(task_id) {
+                    return;
+                }
+
+                data.next.remove(&mut data.nodes, task_id);
+
+                data.nodes[task_id].value.fut.take()
+            };
+
+            // drop outside of the borrow, since dropping may wake other
+            // tasks. wakes to this task are ignored since it's not live
+            drop(fut);
+
+            self.remove(task_id);
+        }
+
+        fn remove(&self, task_id: usize) {
+            let tasks = &mut *self.data.borrow_mut();
+
+            tasks.next.remove(&mut tasks.nodes, task_id);
+            tasks.nodes.remove(task_id);
+        }
+
+        #[allow(clippy::needless_lifetimes, clippy::redundant_pattern_matching)]
         fn process_next<'a>(&'a self) {
             loop {
                 let (nkey, task_ptr) = {
@@ -673,20 +1448,33 @@ mod boxrc {
 
                     task.awake = false;
 
+                    tasks.current = Some(nkey);
+
                     (nkey, task as *mut Task)
                 };
 
                 // SAFETY: task won't move/drop while this pointer is in use.
-                // we don't allow inserting into the slab beyond its capacity,
-                // therefore its items never move. and the only place we remove
-                // the pointed-to item is at the end of this function, after we
-                // are no longer using the pointer
+                // the arena only grows by adding chunks, and existing chunks
+                // never move, therefore its items never move. and the only
+                // place we remove the pointed-to item is at the end of this
+                // function, after we are no longer using the pointer
                 let task = unsafe { task_ptr.as_mut().unwrap() };
 
+                let waker_ptr = {
+                    let wakers = self.wakers.borrow();
+
+                    &wakers[nkey].0 as *const Waker
+                };
+
+                // SAFETY: wakers are never removed, and only grow by adding
+                // chunks, so the waker won't move or drop. we don't hold the
+                // borrow while polling, since the task may spawn another
+                let waker = unsafe { &*waker_ptr };
+
                 let done = {
                     let fut: &mut BoxFuture = task.fut.as_mut().unwrap();
 
-                    let mut cx = Context::from_waker(&self.wakers[nkey]);
+                    let mut cx = Context::from_waker(waker);
 
                     match fut.as_mut().poll(&mut cx) {
                         Poll::Ready(_) => true,
@@ -694,20 +1482,52 @@ mod boxrc {
                     }
                 };
 
-                if done {
-                    task.fut = None;
+                let live = {
+                    let tasks = &mut *self.data.borrow_mut();
 
-                    assert_eq!((self.waker_strong_counts[nkey])(), 1);
+                    tasks.current = None;
 
-                    let tasks = &mut *self.data.borrow_mut();
+                    let task = &mut tasks.nodes[nkey].value;
 
-                    tasks.next.remove(&mut tasks.nodes, nkey);
-                    tasks.nodes.remove(nkey);
+                    if done {
+                        task.live = false;
+                    }
+
+                    task.live
+                };
+
+                if !live {
+                    task.fut = None;
+
+                    // a task that completed normally shouldn't leave its
+                    // waker behind. an aborted one may, and any wakes from
+                    // it are ignored or spurious
+                    if done {
+                        debug_assert_eq!((self.wakers.borrow()[nkey].1)(), 1);
+                    }
+
+                    self.remove(nkey);
                 }
             }
         }
     }
 
+    #[derive(Clone)]
+    pub struct RcAbortHandle {
+        tasks: Weak<Tasks>,
+        task_id: usize,
+        generation: u64,
+    }
+
+    impl RcAbortHandle {
+        // drops the task's future in place, if the task is still running
+        pub fn abort(&self) {
+            if let Some(tasks) = self.tasks.upgrade() {
+                tasks.abort(self.task_id, self.generation);
+            }
+        }
+    }
+
     pub struct BoxRcExecutor {
         tasks: Rc<Tasks>,
     }
@@ -715,18 +1535,35 @@ mod boxrc {
     impl BoxRcExecutor {
         pub fn new<W>(tasks_max: usize, waker_factory: W) -> Self
         where
-            W: WakerFactory,
+            W: WakerFactory + 'static,
+        {
+            Self {
+                tasks: Tasks::new(tasks_max, false, waker_factory),
+            }
+        }
+
+        // task storage and wakers are added in chunks as needed, so
+        // spawning never fails for lack of capacity
+        pub fn growable<W>(chunk_size: usize, waker_factory: W) -> Self
+        where
+            W: WakerFactory + 'static,
         {
             Self {
-                tasks: Tasks::new(tasks_max, waker_factory),
+                tasks: Tasks::new(chunk_size, true, waker_factory),
             }
         }
 
-        pub fn spawn<F>(&self, f: F) -> Result<(), ()>
+        pub fn spawn<F>(&self, f: F) -> Result<RcAbortHandle, SpawnError<F>>
         where
             F: Future<Output = ()> + 'static,
         {
-            self.tasks.add(Box::pin(f))
+            let (task_id, generation) = self.tasks.add(f).map_err(SpawnError::AtCapacity)?;
+
+            Ok(RcAbortHandle {
+                tasks: Rc::downgrade(&self.tasks),
+                task_id,
+                generation,
+            })
         }
 
         pub fn run<P>(&self, park: P)
@@ -746,6 +1583,966 @@ mod boxrc {
     }
 }
 
+// the single-threaded executors keep their tasks in a slab and link ready
+// tasks with list::List, which both need exclusive access. behind a lock,
+// every wake would take that one lock to find the task and link it, and
+// workers would serialize on it. instead, the task slots are a fixed array
+// that is never locked as a whole, a wake is an atomic state change plus a
+// push onto one worker's queue, and the free slot IDs are a separate stack
+mod mt {
+    use super::SpawnError;
+    use std::collections::VecDeque;
+    use std::future::Future;
+    use std::io;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+    use std::sync::{Arc, Condvar, Mutex, Weak};
+    use std::task::{Context, Wake, Waker};
+    use std::thread;
+
+    type SendBoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    // task states. a task is only ever in one queue, since it is queued
+    // only when moving out of IDLE or NOTIFIED
+    const EMPTY: u8 = 0;
+    const IDLE: u8 = 1;
+    const QUEUED: u8 = 2;
+    const RUNNING: u8 = 3;
+    // woken while running. the worker queues it again once the poll returns
+    const NOTIFIED: u8 = 4;
+
+    struct TaskWaker {
+        tasks: Weak<Tasks>,
+        task_id: usize,
+    }
+
+    impl Wake for TaskWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            if let Some(tasks) = self.tasks.upgrade() {
+                tasks.wake(self.task_id);
+            }
+        }
+    }
+
+    struct Task {
+        state: AtomicU8,
+        // the worker that last ran the task. it is queued there when woken
+        worker: AtomicUsize,
+        // only locked by the spawner or by the worker that dequeued the
+        // task, so it is never contended
+        fut: Mutex<Option<SendBoxFuture>>,
+    }
+
+    // the task slots are allocated up front, and each worker has its own
+    // queue of task IDs with room for every task, so running tasks doesn't
+    // allocate. waking a task only takes the lock of the queue it goes to
+    struct Tasks {
+        tasks: Vec<Task>,
+        free: Mutex<Vec<usize>>,
+        queues: Vec<Mutex<VecDeque<usize>>>,
+        next_worker: AtomicUsize,
+        // workers stop once there are no tasks left
+        count: AtomicUsize,
+        // at least the number of tasks in all of the queues
+        queued: AtomicUsize,
+        // set while a worker is calling park
+        polling: AtomicBool,
+        sleepers: AtomicUsize,
+        sleep_lock: Mutex<()>,
+        sleep_cond: Condvar,
+        wakers: Vec<Waker>,
+    }
+
+    impl Tasks {
+        fn new(tasks_max: usize, workers: usize) -> Arc<Self> {
+            let tasks = (0..tasks_max)
+                .map(|_| Task {
+                    state: AtomicU8::new(EMPTY),
+                    worker: AtomicUsize::new(0),
+                    fut: Mutex::new(None),
+                })
+                .collect();
+
+            // hand out the lowest IDs first
+            let free = (0..tasks_max).rev().collect();
+
+            let queues = (0..workers)
+                .map(|_| Mutex::new(VecDeque::with_capacity(tasks_max)))
+                .collect();
+
+            Arc::new_cyclic(|tasks_ref| {
+                let wakers = (0..tasks_max)
+                    .map(|task_id| {
+                        Waker::from(Arc::new(TaskWaker {
+                            tasks: tasks_ref.clone(),
+                            task_id,
+                        }))
+                    })
+                    .collect();
+
+                Self {
+                    tasks,
+                    free: Mutex::new(free),
+                    queues,
+                    next_worker: AtomicUsize::new(0),
+                    count: AtomicUsize::new(0),
+                    queued: AtomicUsize::new(0),
+                    polling: AtomicBool::new(false),
+                    sleepers: AtomicUsize::new(0),
+                    sleep_lock: Mutex::new(()),
+                    sleep_cond: Condvar::new(),
+                    wakers,
+                }
+            })
+        }
+
+        // as with boxrc, the future is only boxed if there is room for it
+        fn add<F>(&self, f: F) -> Result<(), F>
+        where
+            F: Future<Output = ()> + Send + 'static,
+        {
+            let task_id = match self.free.lock().unwrap().pop() {
+                Some(task_id) => task_id,
+                None => return Err(f),
+            };
+
+            let task = &self.tasks[task_id];
+
+            *task.fut.lock().unwrap() = Some(Box::pin(f));
+
+            // spread new tasks across the workers
+            let worker = self.next_worker.fetch_add(1, Ordering::Relaxed) % self.queues.len();
+
+            task.worker.store(worker, Ordering::Relaxed);
+            task.state.store(QUEUED, Ordering::Release);
+
+            self.count.fetch_add(1, Ordering::SeqCst);
+            self.push(worker, task_id);
+
+            Ok(())
+        }
+
+        fn push(&self, worker: usize, task_id: usize) {
+            // counted before pushing, so the count never drops below the
+            // number of queued tasks
+            self.queued.fetch_add(1, Ordering::SeqCst);
+
+            self.queues[worker].lock().unwrap().push_back(task_id);
+
+            if self.sleepers.load(Ordering::SeqCst) > 0 {
+                let _guard = self.sleep_lock.lock().unwrap();
+
+                self.sleep_cond.notify_one();
+            }
+        }
+
+        fn wake(&self, task_id: usize) {
+            let task = &self.tasks[task_id];
+
+            // wakers outlive tasks, so the slot could be empty or reused
+            let mut state = task.state.load(Ordering::Acquire);
+
+            loop {
+                let next = match state {
+                    IDLE => QUEUED,
+                    RUNNING => NOTIFIED,
+                    _ => return,
+                };
+
+                match task.state.compare_exchange_weak(
+                    state,
+                    next,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => break,
+                    Err(s) => state = s,
+                }
+            }
+
+            if state == IDLE {
+                self.push(task.worker.load(Ordering::Relaxed), task_id);
+            }
+        }
+
+        // takes the oldest task from the worker's own queue, or else steals
+        // the newest task from another worker's queue
+        fn take_next(&self, worker: usize) -> Option<usize> {
+            if self.queued.load(Ordering::SeqCst) == 0 {
+                return None;
+            }
+
+            let workers = self.queues.len();
+
+            (0..workers).map(|i| (worker + i) % workers).find_map(|w| {
+                let q = &mut *self.queues[w].lock().unwrap();
+
+                let task_id = if w == worker {
+                    q.pop_front()
+                } else {
+                    q.pop_back()
+                }?;
+
+                self.queued.fetch_sub(1, Ordering::SeqCst);
+
+                Some(task_id)
+            })
+        }
+
+        fn run_task(&self, worker: usize, task_id: usize) {
+            let task = &self.tasks[task_id];
+
+            // only the worker that dequeued the task moves it out of QUEUED
+            task.worker.store(worker, Ordering::Relaxed);
+            task.state.store(RUNNING, Ordering::Release);
+
+            let mut fut = task.fut.lock().unwrap();
+
+            let done = {
+                let mut cx = Context::from_waker(&self.wakers[task_id]);
+
+                fut.as_mut().unwrap().as_mut().poll(&mut cx).is_ready()
+            };
+
+            if done {
+                *fut = None;
+                drop(fut);
+
+                task.state.store(EMPTY, Ordering::Release);
+                self.free.lock().unwrap().push(task_id);
+
+                if self.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+                    let _guard = self.sleep_lock.lock().unwrap();
+
+                    self.sleep_cond.notify_all();
+                }
+
+                return;
+            }
+
+            drop(fut);
+
+            if task
+                .state
+                .compare_exchange(RUNNING, IDLE, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+            {
+                // woken during the poll
+                task.state.store(QUEUED, Ordering::Release);
+                self.push(worker, task_id);
+            }
+        }
+
+        // waits until there may be a task to run, the tasks are done, or
+        // no other worker is polling
+        fn sleep(&self) {
+            let mut guard = self.sleep_lock.lock().unwrap();
+
+            self.sleepers.fetch_add(1, Ordering::SeqCst);
+
+            while self.queued.load(Ordering::SeqCst) == 0
+                && self.count.load(Ordering::SeqCst) > 0
+                && self.polling.load(Ordering::SeqCst)
+            {
+                guard = self.sleep_cond.wait(guard).unwrap();
+            }
+
+            self.sleepers.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        fn run_worker<P>(&self, worker: usize, park: &P)
+        where
+            P: Fn() -> Result<(), io::Error>,
+        {
+            loop {
+                while let Some(task_id) = self.take_next(worker) {
+                    self.run_task(worker, task_id);
+                }
+
+                if self.count.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+
+                // one worker at a time calls park, and any others sleep
+                // until it queues tasks for them
+                if self.polling.swap(true, Ordering::SeqCst) {
+                    self.sleep();
+                } else {
+                    let ret = park();
+
+                    self.polling.store(false, Ordering::SeqCst);
+
+                    ret.unwrap();
+                }
+            }
+        }
+    }
+
+    // the tasks, wakers, and anything the tasks share are used from several
+    // threads at once. compared to the single-threaded executors, this adds
+    // the cost of the atomic task states, the Arc waker reference counts,
+    // and the locks on the run queues, which are contended when workers
+    // steal from each other or wake tasks that belong to another worker
+    pub struct MtExecutor {
+        tasks: Arc<Tasks>,
+        workers: usize,
+    }
+
+    impl MtExecutor {
+        pub fn new(tasks_max: usize, workers: usize) -> Self {
+            assert!(workers > 0);
+
+            Self {
+                tasks: Tasks::new(tasks_max, workers),
+                workers,
+            }
+        }
+
+        pub fn spawn<F>(&self, f: F) -> Result<(), SpawnError<F>>
+        where
+            F: Future<Output = ()> + Send + 'static,
+        {
+            self.tasks.add(f).map_err(SpawnError::AtCapacity)
+        }
+
+        // runs tasks on the current thread plus workers - 1 extra threads,
+        // until there are none left. park is called by a worker that runs
+        // out of tasks, but only by one worker at a time. any other idle
+        // workers sleep until tasks are queued
+        pub fn run<P>(&self, park: P)
+        where
+            P: Fn() -> Result<(), io::Error> + Sync,
+        {
+            let park = &park;
+
+            thread::scope(|s| {
+                for worker in 1..self.workers {
+                    s.spawn(move || self.tasks.run_worker(worker, park));
+                }
+
+                self.tasks.run_worker(0, park);
+            });
+        }
+    }
+}
+
 pub use arg::{ArgExecutor, ArgSpawner};
 pub use boxrc::BoxRcExecutor;
 pub use bx::{BoxExecutor, BoxSpawner};
+pub use mt::MtExecutor;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waker::ArcWakerFactory;
+    use std::cell::Cell;
+    use std::mem;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+
+    struct DropFlag<'a>(&'a Cell<bool>);
+
+    impl Drop for DropFlag<'_> {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    type AbortHandleCell<'a> = Cell<Option<arg::AbortHandle<'a>>>;
+
+    enum ArgInvoke<'a> {
+        Hang(&'a Cell<bool>),
+        Hold(&'a Cell<Option<Waker>>),
+        Abort(&'a AbortHandleCell<'a>),
+        AbortSelf(&'a Cell<bool>, &'a AbortHandleCell<'a>),
+        Spawn(&'a ArgSpawner<ArgInvoke<'a>>, &'a Cell<usize>, usize),
+    }
+
+    async fn arg_task(invoke: ArgInvoke<'_>) {
+        match invoke {
+            ArgInvoke::Hang(dropped) => {
+                let _flag = DropFlag(dropped);
+
+                std::future::pending::<()>().await;
+            }
+            ArgInvoke::Hold(held) => {
+                std::future::poll_fn(|cx| {
+                    // hold on to a clone, as a reactor or channel would
+                    held.set(Some(cx.waker().clone()));
+
+                    Poll::<()>::Pending
+                })
+                .await
+            }
+            ArgInvoke::Abort(handle) => handle.get().unwrap().abort(),
+            ArgInvoke::AbortSelf(dropped, handle) => {
+                let _flag = DropFlag(dropped);
+
+                handle.get().unwrap().abort();
+
+                std::future::pending::<()>().await;
+            }
+            ArgInvoke::Spawn(spawner, done, depth) => {
+                if depth > 0 {
+                    spawner
+                        .spawn(ArgInvoke::Spawn(spawner, done, depth - 1))
+                        .unwrap();
+                }
+
+                // wake using the task's own waker, after storage may have
+                // been added by the spawn
+                YieldNow(false).await;
+
+                done.set(done.get() + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_arg_abort() {
+        let dropped = Cell::new(false);
+        let handle = Cell::new(None);
+        let spawner = ArgSpawner::new();
+        let executor = ArgExecutor::new(2, |invoke, dest| {
+            dest.write(arg_task(invoke));
+        });
+
+        executor.set_spawner(&spawner);
+
+        handle.set(Some(spawner.spawn(ArgInvoke::Hang(&dropped)).unwrap()));
+        spawner.spawn(ArgInvoke::Abort(&handle)).unwrap();
+
+        executor.run(|| Ok(()));
+
+        assert!(dropped.get());
+
+        // aborting a finished task is a no-op
+        handle.get().unwrap().abort();
+    }
+
+    #[test]
+    fn test_arg_abort_held_waker() {
+        let held = Cell::new(None);
+        let handle = Cell::new(None);
+        let spawner = ArgSpawner::new();
+        let executor = ArgExecutor::new(2, |invoke, dest| {
+            dest.write(arg_task(invoke));
+        });
+
+        executor.set_spawner(&spawner);
+
+        handle.set(Some(spawner.spawn(ArgInvoke::Hold(&held)).unwrap()));
+        spawner.spawn(ArgInvoke::Abort(&handle)).unwrap();
+
+        executor.run(|| Ok(()));
+
+        let waker = held.take().unwrap();
+
+        // the slot stays reserved while the clone is held
+        spawner.spawn(ArgInvoke::Hold(&held)).unwrap();
+        assert!(matches!(
+            spawner.spawn(ArgInvoke::Hold(&held)),
+            Err(SpawnError::AtCapacity(_))
+        ));
+
+        // waking the aborted task is a no-op, and frees the slot
+        waker.wake();
+
+        spawner.spawn(ArgInvoke::Abort(&handle)).unwrap();
+    }
+
+    #[test]
+    fn test_arg_abort_self() {
+        let dropped = Cell::new(false);
+        let handle = Cell::new(None);
+        let spawner = ArgSpawner::new();
+        let executor = ArgExecutor::new(1, |invoke, dest| {
+            dest.write(arg_task(invoke));
+        });
+
+        executor.set_spawner(&spawner);
+
+        handle.set(Some(
+            spawner
+                .spawn(ArgInvoke::AbortSelf(&dropped, &handle))
+                .unwrap(),
+        ));
+
+        executor.run(|| Ok(()));
+
+        assert!(dropped.get());
+    }
+
+    #[test]
+    fn test_arg_growable() {
+        let done = Cell::new(0);
+        let spawner = ArgSpawner::new();
+        let executor = ArgExecutor::growable(2, |invoke, dest| {
+            dest.write(arg_task(invoke));
+        });
+
+        executor.set_spawner(&spawner);
+
+        // each task spawns the next while the earlier ones are still alive,
+        // so storage must grow from within a poll
+        spawner.spawn(ArgInvoke::Spawn(&spawner, &done, 9)).unwrap();
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(done.get(), 10);
+    }
+
+    #[test]
+    fn test_arg_spawn_error() {
+        let dropped = Cell::new(false);
+        let spawner = ArgSpawner::new();
+
+        // not attached to an executor yet
+        assert!(matches!(
+            spawner.spawn(ArgInvoke::Hang(&dropped)),
+            Err(SpawnError::ExecutorGone(ArgInvoke::Hang(_)))
+        ));
+
+        {
+            let executor = ArgExecutor::new(1, |invoke, dest| {
+                dest.write(arg_task(invoke));
+            });
+
+            executor.set_spawner(&spawner);
+
+            spawner.spawn(ArgInvoke::Hang(&dropped)).unwrap();
+
+            // the argument is handed back
+            match spawner.spawn(ArgInvoke::Hang(&dropped)) {
+                Err(e @ SpawnError::AtCapacity(_)) => {
+                    assert!(matches!(e.into_inner(), ArgInvoke::Hang(_)))
+                }
+                _ => panic!("expected AtCapacity"),
+            }
+        }
+
+        assert!(matches!(
+            spawner.spawn(ArgInvoke::Hang(&dropped)),
+            Err(SpawnError::ExecutorGone(_))
+        ));
+    }
+
+    #[test]
+    fn test_box_join() {
+        let result = Cell::new(None);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(2);
+
+        executor.set_spawner(&spawner);
+
+        spawner
+            .spawn(async {
+                let handle = spawner.spawn(async { 42 }).unwrap();
+
+                result.set(Some(handle.await.unwrap()));
+            })
+            .unwrap();
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(result.get(), Some(42));
+    }
+
+    #[test]
+    fn test_box_join_error() {
+        let result = Cell::new(None);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(2);
+
+        executor.set_spawner(&spawner);
+
+        spawner
+            .spawn(async {
+                let handle = spawner
+                    .spawn(async { Err::<(), _>(io::Error::from(io::ErrorKind::BrokenPipe)) })
+                    .unwrap();
+
+                // errors from the task can be propagated by the parent
+                let ret: Result<(), io::Error> = async { handle.await? }.await;
+
+                result.set(Some(ret.unwrap_err().kind()));
+            })
+            .unwrap();
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(result.get(), Some(io::ErrorKind::BrokenPipe));
+    }
+
+    #[test]
+    fn test_box_join_panic() {
+        let result = Cell::new(None);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(2);
+
+        executor.set_spawner(&spawner);
+
+        spawner
+            .spawn(async {
+                let handle = spawner.spawn(async { panic!("oops") }).unwrap();
+
+                result.set(Some(matches!(handle.await, Err(JoinError::Panicked(_)))));
+            })
+            .unwrap();
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(result.get(), Some(true));
+    }
+
+    #[test]
+    fn test_box_join_executor_gone() {
+        let spawner = BoxSpawner::new();
+
+        let mut handle = {
+            let executor = BoxExecutor::new(1);
+
+            executor.set_spawner(&spawner);
+
+            spawner.spawn(async {}).unwrap()
+        };
+
+        let mut cx = Context::from_waker(Waker::noop());
+
+        assert!(matches!(
+            Pin::new(&mut handle).poll(&mut cx),
+            Poll::Ready(Err(JoinError::Cancelled))
+        ));
+    }
+
+    #[test]
+    fn test_box_join_detached() {
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(1);
+
+        executor.set_spawner(&spawner);
+
+        // dropping the handle detaches the task, freeing its slot once done
+        drop(spawner.spawn(async {}).unwrap());
+        executor.run(|| Ok(()));
+
+        drop(spawner.spawn(async {}).unwrap());
+        executor.run(|| Ok(()));
+    }
+
+    #[test]
+    fn test_box_join_self_wake() {
+        let result = Cell::new(None);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(2);
+
+        executor.set_spawner(&spawner);
+
+        spawner
+            .spawn(async {
+                // the task wakes itself during the poll that completes it
+                let handle = spawner
+                    .spawn(std::future::poll_fn(|cx| {
+                        cx.waker().wake_by_ref();
+
+                        Poll::Ready(42)
+                    }))
+                    .unwrap();
+
+                result.set(Some(handle.await.unwrap()));
+            })
+            .unwrap();
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(result.get(), Some(42));
+    }
+
+    #[test]
+    fn test_box_abort_held_waker() {
+        let held = Cell::new(None);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(2);
+
+        executor.set_spawner(&spawner);
+
+        spawner
+            .spawn(async {
+                let handle = spawner
+                    .spawn(std::future::poll_fn(|cx| {
+                        // hold on to a clone, as a reactor or channel would
+                        held.set(Some(cx.waker().clone()));
+
+                        Poll::<()>::Pending
+                    }))
+                    .unwrap();
+
+                YieldNow(false).await;
+
+                handle.abort();
+
+                assert!(matches!(handle.await, Err(JoinError::Cancelled)));
+            })
+            .unwrap();
+
+        executor.run(|| Ok(()));
+
+        let waker = held.take().unwrap();
+
+        // the slot stays reserved while the clone is held
+        drop(spawner.spawn(async {}).unwrap());
+        assert!(matches!(
+            spawner.spawn(async {}),
+            Err(SpawnError::AtCapacity(_))
+        ));
+
+        // waking the aborted task is a no-op, and frees the slot
+        waker.wake();
+
+        drop(spawner.spawn(async {}).unwrap());
+        executor.run(|| Ok(()));
+    }
+
+    #[test]
+    fn test_box_spawn_error() {
+        let result = Cell::new(None);
+        let spawner = BoxSpawner::new();
+
+        // the rejected future is handed back unpolled, and can be run later
+        let fut = match spawner.spawn(async { result.set(Some(42)) }) {
+            Err(SpawnError::ExecutorGone(fut)) => fut,
+            _ => panic!("expected ExecutorGone"),
+        };
+
+        let executor = BoxExecutor::new(1);
+
+        executor.set_spawner(&spawner);
+
+        spawner.spawn(fut).unwrap();
+
+        assert!(matches!(
+            spawner.spawn_boxed(Box::pin(async {})),
+            Err(SpawnError::AtCapacity(_))
+        ));
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(result.get(), Some(42));
+    }
+
+    // wakes itself and returns pending once, so the task is requeued
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+
+            self.0 = true;
+            cx.waker().wake_by_ref();
+
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_box_growable() {
+        let done = Cell::new(0);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::growable(2);
+
+        executor.set_spawner(&spawner);
+
+        spawner
+            .spawn(async {
+                let handles: Vec<_> = (0..10)
+                    .map(|i| {
+                        spawner
+                            .spawn(async move {
+                                YieldNow(false).await;
+
+                                i
+                            })
+                            .unwrap()
+                    })
+                    .collect();
+
+                for (i, handle) in handles.into_iter().enumerate() {
+                    assert_eq!(handle.await.unwrap(), i);
+
+                    done.set(done.get() + 1);
+                }
+            })
+            .unwrap();
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(done.get(), 10);
+    }
+
+    #[test]
+    fn test_boxrc_growable() {
+        let done = Rc::new(Cell::new(0));
+        let executor = BoxRcExecutor::growable(2, ArcWakerFactory::default());
+
+        for _ in 0..10 {
+            let done = done.clone();
+
+            executor
+                .spawn(async move {
+                    YieldNow(false).await;
+
+                    done.set(done.get() + 1);
+                })
+                .unwrap();
+        }
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(done.get(), 10);
+
+        // a fixed executor is still limited to its capacity
+        let executor = BoxRcExecutor::new(2, ArcWakerFactory::default());
+
+        executor.spawn(async {}).unwrap();
+        executor.spawn(async {}).unwrap();
+        assert!(matches!(
+            executor.spawn(async {}),
+            Err(SpawnError::AtCapacity(_))
+        ));
+    }
+
+    #[test]
+    fn test_boxrc_abort_held_waker() {
+        let held = Rc::new(Cell::new(None));
+        let executor = BoxRcExecutor::new(2, ArcWakerFactory::default());
+
+        let handle = {
+            let held = held.clone();
+
+            executor
+                .spawn(std::future::poll_fn(move |cx| {
+                    // hold on to a clone, as a reactor or channel would
+                    held.set(Some(cx.waker().clone()));
+
+                    Poll::<()>::Pending
+                }))
+                .unwrap()
+        };
+
+        executor.spawn(async move { handle.abort() }).unwrap();
+
+        executor.run(|| Ok(()));
+
+        // waking the aborted task is a no-op
+        held.take().unwrap().wake();
+    }
+
+    #[test]
+    fn test_mt() {
+        let executor = MtExecutor::new(16, 4);
+        let done = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..16 {
+            let done = done.clone();
+
+            executor
+                .spawn(async move {
+                    for _ in 0..100 {
+                        YieldNow(false).await;
+                    }
+
+                    done.fetch_add(1, Ordering::Relaxed);
+                })
+                .unwrap();
+        }
+
+        // full
+        assert!(matches!(
+            executor.spawn(async {}),
+            Err(SpawnError::AtCapacity(_))
+        ));
+
+        executor.run(|| Ok(()));
+
+        assert_eq!(done.load(Ordering::Relaxed), 16);
+
+        // slots are freed once tasks complete
+        executor.spawn(async {}).unwrap();
+        executor.run(|| Ok(()));
+    }
+
+    // pending until park wakes it, like waiting for I/O
+    struct WaitPark<'a> {
+        wakers: &'a Mutex<Vec<Waker>>,
+        waited: bool,
+    }
+
+    impl Future for WaitPark<'_> {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.waited {
+                return Poll::Ready(());
+            }
+
+            self.waited = true;
+            self.wakers.lock().unwrap().push(cx.waker().clone());
+
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_mt_park() {
+        let executor = MtExecutor::new(8, 4);
+        let wakers = Arc::new(Mutex::new(Vec::new()));
+        let done = Arc::new(AtomicUsize::new(0));
+        let parking = AtomicBool::new(false);
+
+        for _ in 0..8 {
+            let wakers = wakers.clone();
+            let done = done.clone();
+
+            executor
+                .spawn(async move {
+                    for _ in 0..10 {
+                        WaitPark {
+                            wakers: &wakers,
+                            waited: false,
+                        }
+                        .await;
+                    }
+
+                    done.fetch_add(1, Ordering::Relaxed);
+                })
+                .unwrap();
+        }
+
+        executor.run(|| {
+            // only one worker parks at a time
+            assert!(!parking.swap(true, Ordering::SeqCst));
+
+            let woken = mem::take(&mut *wakers.lock().unwrap());
+
+            for waker in woken {
+                waker.wake();
+            }
+
+            parking.store(false, Ordering::SeqCst);
+
+            Ok(())
+        });
+
+        assert_eq!(done.load(Ordering::Relaxed), 8);
+    }
+}
@@ -9,6 +9,7 @@ pub enum StatsType {
     Accept,
     Read,
     Write,
+    Timer,
 }
 
 pub trait Stats {
@@ -20,64 +21,192 @@ pub trait Evented {
     fn get_poll_index(&self) -> Option<usize>;
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Step {
+    // the next n calls return WouldBlock. n must be at least 1
+    WouldBlock(usize),
+
+    // the next call transfers at most n bytes. n must be at least 1
+    Ready(usize),
+
+    // this and all further calls fail with ConnectionReset
+    Reset,
+
+    // this and all further reads return 0, and writes fail with BrokenPipe
+    Eof,
+}
+
+// scripts the behavior of each stream accepted from a listener. the script
+// repeats once the end is reached. reads return successive bytes of the
+// request, also repeating.
+// reads and writes share a single cursor into the steps: each call takes
+// the next step, whichever direction it is in. with the request/response
+// connections of the benchmarks, the reads of a request take some steps and
+// the writes of its response take the ones after
+#[derive(Debug, Clone, Copy)]
+pub struct Scenario {
+    request: &'static [u8],
+    steps: &'static [Step],
+}
+
+impl Scenario {
+    // panics if the request or steps are empty, or if a WouldBlock or Ready
+    // step has a count of 0. such steps would never move the script along,
+    // or would transfer nothing
+    pub const fn new(request: &'static [u8], steps: &'static [Step]) -> Self {
+        assert!(!request.is_empty(), "scenario request must not be empty");
+        assert!(!steps.is_empty(), "scenario steps must not be empty");
+
+        let mut i = 0;
+
+        while i < steps.len() {
+            if let Step::WouldBlock(n) | Step::Ready(n) = steps[i] {
+                assert!(n >= 1, "scenario step count must be at least 1");
+            }
+
+            i += 1;
+        }
+
+        Self { request, steps }
+    }
+
+    // the same request with different steps
+    pub const fn with_steps(self, steps: &'static [Step]) -> Self {
+        Self::new(self.request, steps)
+    }
+
+    // every call would block once before succeeding
+    pub const DEFAULT: Self = Self::new(
+        b"hello world\n",
+        &[Step::WouldBlock(1), Step::Ready(usize::MAX)],
+    );
+
+    // requests arrive and responses are sent a few bytes at a time
+    pub const PARTIAL: Self = Self::new(b"hello world\n", &[Step::WouldBlock(1), Step::Ready(5)]);
+
+    // calls would block several times in a row before succeeding
+    pub const SLOW: Self = Self::new(
+        b"hello world\n",
+        &[Step::WouldBlock(4), Step::Ready(usize::MAX)],
+    );
+
+    // the peer resets the connection partway through the request
+    pub const RESET: Self = Self::new(
+        b"hello world\n",
+        &[Step::WouldBlock(1), Step::Ready(5), Step::Reset],
+    );
+
+    // the peer closes the connection partway through the request
+    pub const EOF: Self = Self::new(
+        b"hello world\n",
+        &[Step::WouldBlock(1), Step::Ready(5), Step::Eof],
+    );
+
+    // like DEFAULT, but with an HTTP/1.1 request that has a body
+    pub const HTTP: Self = Self::new(
+        b"POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Length: 11\r\n\r\nhello world",
+        &[Step::WouldBlock(1), Step::Ready(usize::MAX)],
+    );
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 pub struct FakeStream<T>
 where
     T: Stats,
 {
     poll_index: Cell<Option<usize>>,
     stats: T,
-    calls: usize,
+    scenario: Scenario,
+    step: Cell<usize>,
+    step_calls: Cell<usize>,
+    read_pos: Cell<usize>,
 }
 
 impl<T> FakeStream<T>
 where
     T: Stats,
 {
-    fn new(stats: T) -> Self {
+    fn new(stats: T, scenario: Scenario) -> Self {
         Self {
             poll_index: Cell::new(None),
             stats,
-            calls: 0,
+            scenario,
+            step: Cell::new(0),
+            step_calls: Cell::new(0),
+            read_pos: Cell::new(0),
+        }
+    }
+
+    // returns the step for the current call and moves the script along
+    fn next_step(&self) -> Step {
+        let step = self.scenario.steps[self.step.get()];
+
+        let done = match step {
+            Step::WouldBlock(n) => {
+                self.step_calls.set(self.step_calls.get() + 1);
+
+                self.step_calls.get() >= n
+            }
+            Step::Ready(_) => true,
+            Step::Reset | Step::Eof => false,
+        };
+
+        if done {
+            self.step_calls.set(0);
+            self.step
+                .set((self.step.get() + 1) % self.scenario.steps.len());
         }
+
+        step
     }
 }
 
-impl<T> io::Read for FakeStream<T>
+// like TcpStream, reads and writes can be done through a shared reference,
+// so a stream can be read and written from different places at once
+impl<T> io::Read for &FakeStream<T>
 where
     T: Stats,
 {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
         self.stats.inc(StatsType::Read);
 
-        self.calls += 1;
+        match self.next_step() {
+            Step::WouldBlock(_) => Err(io::Error::from(io::ErrorKind::WouldBlock)),
+            Step::Ready(n) => {
+                let request = self.scenario.request;
+                let pos = self.read_pos.get();
 
-        if self.calls % 2 == 1 {
-            Err(io::Error::from(io::ErrorKind::WouldBlock))
-        } else {
-            let data = &b"hello world\n"[..];
+                let size = n.min(buf.len()).min(request.len() - pos);
 
-            assert!(buf.len() >= data.len());
+                buf[..size].copy_from_slice(&request[pos..(pos + size)]);
 
-            buf[..data.len()].copy_from_slice(&data);
+                self.read_pos.set((pos + size) % request.len());
 
-            Ok(data.len())
+                Ok(size)
+            }
+            Step::Reset => Err(io::Error::from(io::ErrorKind::ConnectionReset)),
+            Step::Eof => Ok(0),
         }
     }
 }
 
-impl<T> io::Write for FakeStream<T>
+impl<T> io::Write for &FakeStream<T>
 where
     T: Stats,
 {
     fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
         self.stats.inc(StatsType::Write);
 
-        self.calls += 1;
-
-        if self.calls % 2 == 1 {
-            Err(io::Error::from(io::ErrorKind::WouldBlock))
-        } else {
-            Ok(buf.len())
+        match self.next_step() {
+            Step::WouldBlock(_) => Err(io::Error::from(io::ErrorKind::WouldBlock)),
+            Step::Ready(n) => Ok(n.min(buf.len())),
+            Step::Reset => Err(io::Error::from(io::ErrorKind::ConnectionReset)),
+            Step::Eof => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
         }
     }
 
@@ -86,6 +215,28 @@ where
     }
 }
 
+impl<T> io::Read for FakeStream<T>
+where
+    T: Stats,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        io::Read::read(&mut &*self, buf)
+    }
+}
+
+impl<T> io::Write for FakeStream<T>
+where
+    T: Stats,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        io::Write::write(&mut &*self, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        io::Write::flush(&mut &*self)
+    }
+}
+
 impl<T> Evented for FakeStream<T>
 where
     T: Stats,
@@ -102,6 +253,7 @@ where
 pub struct FakeListener<T> {
     poll_index: Cell<Option<usize>>,
     stats: T,
+    scenario: Scenario,
     calls: RefCell<usize>,
 }
 
@@ -109,10 +261,11 @@ impl<T> FakeListener<T>
 where
     T: Stats + Clone,
 {
-    pub fn new(stats: T) -> Self {
+    pub fn new(stats: T, scenario: Scenario) -> Self {
         Self {
             poll_index: Cell::new(None),
             stats,
+            scenario,
             calls: RefCell::new(0),
         }
     }
@@ -125,7 +278,7 @@ where
         if *self.calls.borrow() % 2 == 1 {
             Err(io::Error::from(io::ErrorKind::WouldBlock))
         } else {
-            Ok(FakeStream::new(self.stats.clone()))
+            Ok(FakeStream::new(self.stats.clone(), self.scenario))
         }
     }
 }
@@ -194,3 +347,51 @@ impl<T> Drop for Poll<T> {
         assert!(self.items.borrow().is_empty());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    struct NoStats;
+
+    impl Stats for NoStats {
+        fn inc(&self, _t: StatsType) {}
+    }
+
+    #[test]
+    #[should_panic(expected = "scenario step count must be at least 1")]
+    fn test_scenario_would_block_zero() {
+        Scenario::new(b"hello\n", &[Step::WouldBlock(0), Step::Ready(1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "scenario step count must be at least 1")]
+    fn test_scenario_ready_zero() {
+        Scenario::DEFAULT.with_steps(&[Step::Ready(0)]);
+    }
+
+    #[test]
+    fn test_scenario_shared_steps() {
+        let scenario = Scenario::new(
+            b"hello\n",
+            &[Step::WouldBlock(1), Step::Ready(2), Step::Ready(3)],
+        );
+
+        let mut s = FakeStream::new(NoStats, scenario);
+        let mut buf = [0; 16];
+
+        // a write takes the step after a read, and vice versa
+        assert_eq!(
+            s.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+        assert_eq!(s.write(b"hello\n").unwrap(), 2);
+        assert_eq!(s.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"hel");
+        assert_eq!(
+            s.write(b"hello\n").unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+    }
+}
@@ -1,156 +1,164 @@
+use crate::asyncio::{AsyncRead, AsyncWrite};
 use crate::fakeio;
-use crate::fakeio::{Evented, FakeListener, FakeStream, Stats, READABLE, WRITABLE};
+use crate::fakeio::{
+    Evented, FakeListener, FakeStream, Scenario, Stats, StatsType, READABLE, WRITABLE,
+};
+use crate::registration::{EventRegistration, Reactor, ReactorRef, RegistrationHandle};
+use crate::timer::{Clock, RealClock, TimerWheel};
 use slab::Slab;
 use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
 use std::future::Future;
 use std::io;
 use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+// the reason a reactor couldn't register an I/O object. shared by all of
+// the reactors
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RegisterError {
+    // the reactor has no room for more registrations
+    Full,
+}
 
-pub trait FakeReactorRef<T>: Clone
-where
-    T: Stats,
-{
-    fn get(&self) -> &FakeReactor<T>;
-
-    fn register<'a, E: Evented>(
-        &'a self,
-        handle: &E,
-        interest: u8,
-    ) -> Result<RegistrationHandle<T, Self>, io::Error> {
-        let r = self.get();
-
-        let data = &mut *r.data.borrow_mut();
-
-        if data.registrations.len() == data.registrations.capacity() {
-            return Err(io::Error::from(io::ErrorKind::WriteZero));
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "reactor registrations are full"),
         }
-
-        let key = data.registrations.insert(EventRegistration {
-            ready: false,
-            waker: None,
-        });
-
-        r.poll.register(handle, key, interest);
-
-        Ok(RegistrationHandle {
-            reactor: self.clone(),
-            key,
-            _marker: PhantomData,
-        })
     }
 }
 
-pub struct RegistrationHandle<T, R>
-where
-    T: Stats,
-    R: FakeReactorRef<T>,
-{
-    reactor: R,
-    key: usize,
-    _marker: PhantomData<T>,
+impl Error for RegisterError {}
+
+impl From<RegisterError> for io::Error {
+    fn from(e: RegisterError) -> Self {
+        io::Error::other(e)
+    }
 }
 
-impl<T, R> RegistrationHandle<T, R>
+// the fake reactor's own operations, for anything that refers to one
+pub trait FakeReactorRef<T>: ReactorRef<Reactor = FakeReactor<T>>
 where
     T: Stats,
-    R: FakeReactorRef<T>,
 {
-    fn is_ready(&self) -> bool {
-        let data = &*self.reactor.get().data.borrow();
-
-        let event_reg = &data.registrations[self.key];
-
-        event_reg.ready
-    }
-
-    fn set_ready(&self, ready: bool) {
-        let data = &mut *self.reactor.get().data.borrow_mut();
+    fn register<E: Evented>(
+        &self,
+        handle: &E,
+        interest: u8,
+    ) -> Result<RegistrationHandle<Self>, RegisterError> {
+        let reg = RegistrationHandle::new(self.clone())?;
 
-        let event_reg = &mut data.registrations[self.key];
+        self.get().poll.register(handle, reg.key(), interest);
 
-        event_reg.ready = ready;
+        Ok(reg)
     }
 
-    fn bind_waker(&self, waker: &Waker) {
-        let data = &mut *self.reactor.get().data.borrow_mut();
-
-        let event_reg = &mut data.registrations[self.key];
+    // if the reactor has no room for the timer, then the returned future
+    // resolves to RegisterError::Full
+    fn sleep(&self, d: Duration) -> Sleep<T, Self> {
+        let key = self.get().add_timer(d);
 
-        if let Some(current_waker) = &event_reg.waker {
-            if current_waker.will_wake(waker) {
-                // keep the current waker
-                return;
-            }
+        Sleep {
+            reactor: self.clone(),
+            key,
+            _marker: PhantomData,
         }
-
-        event_reg.waker = Some(waker.clone());
     }
 
-    fn unbind_waker(&self) {
-        let data = &mut *self.reactor.get().data.borrow_mut();
-
-        let event_reg = &mut data.registrations[self.key];
-
-        event_reg.waker = None;
+    fn timeout<F: Future>(&self, fut: F, d: Duration) -> Timeout<F, T, Self> {
+        Timeout {
+            fut,
+            sleep: self.sleep(d),
+        }
     }
 }
 
-impl<T, R> Drop for RegistrationHandle<T, R>
+impl<T, R> FakeReactorRef<T> for R
 where
     T: Stats,
-    R: FakeReactorRef<T>,
+    R: ReactorRef<Reactor = FakeReactor<T>>,
 {
-    fn drop(&mut self) {
-        let data = &mut *self.reactor.get().data.borrow_mut();
-
-        data.registrations.remove(self.key);
-    }
 }
 
-struct EventRegistration {
-    ready: bool,
+struct TimerRegistration {
+    fired: bool,
     waker: Option<Waker>,
 }
 
 struct FakeReactorData {
     registrations: Slab<EventRegistration>,
     events: Slab<(usize, u8)>,
+    timers: TimerWheel<TimerRegistration>,
 }
 
 pub struct FakeReactor<T> {
     data: RefCell<FakeReactorData>,
     poll: fakeio::Poll<T>,
+    stats: T,
+    clock: Box<dyn Clock>,
+    start: Instant,
 }
 
 impl<T> FakeReactor<T>
 where
-    T: Stats,
+    T: Stats + Clone,
 {
     pub fn new(registrations_max: usize, stats: T) -> Self {
+        Self::with_clock(registrations_max, stats, RealClock::default())
+    }
+
+    pub fn with_clock<C>(registrations_max: usize, stats: T, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
         let data = FakeReactorData {
             registrations: Slab::with_capacity(registrations_max),
             events: Slab::with_capacity(128),
+            timers: TimerWheel::new(registrations_max),
         };
 
+        let start = clock.now();
+
         Self {
             data: RefCell::new(data),
-            poll: fakeio::Poll::new(128, stats),
+            poll: fakeio::Poll::new(128, stats.clone()),
+            stats,
+            clock: Box::new(clock),
+            start,
         }
     }
+}
 
+impl<T> FakeReactor<T>
+where
+    T: Stats,
+{
     pub fn poll(&self) -> Result<(), io::Error> {
         let data = &mut *self.data.borrow_mut();
 
         self.poll.poll(&mut data.events);
 
-        for (_, (key, _)) in data.events.iter() {
-            if let Some(event_reg) = data.registrations.get_mut(*key) {
-                event_reg.ready = true;
+        for (_, &(key, readiness)) in data.events.iter() {
+            if let Some(event_reg) = data.registrations.get_mut(key) {
+                event_reg.set_readiness(readiness, Waker::wake);
+            }
+        }
+
+        // only check the clock if there is something to expire
+        if !data.timers.is_empty() {
+            data.timers.advance(self.ticks(self.clock.now()));
+
+            while let Some(key) = data.timers.take_expired() {
+                let timer = data.timers.get_mut(key).unwrap();
+
+                timer.fired = true;
 
-                if let Some(waker) = event_reg.waker.take() {
+                if let Some(waker) = timer.waker.take() {
                     waker.wake();
                 }
             }
@@ -162,6 +170,70 @@ where
     fn unregister<E: Evented>(&self, handle: &E) {
         self.poll.unregister(handle);
     }
+
+    // timer ticks are milliseconds since the reactor was created, rounded up
+    // so that timers never fire early
+    fn ticks(&self, t: Instant) -> u64 {
+        let d = t.saturating_duration_since(self.start);
+
+        d.as_nanos().div_ceil(1_000_000) as u64
+    }
+
+    fn add_timer(&self, d: Duration) -> Result<usize, RegisterError> {
+        let data = &mut *self.data.borrow_mut();
+
+        let expires = self.ticks(self.clock.now() + d);
+
+        let timer = TimerRegistration {
+            fired: false,
+            waker: None,
+        };
+
+        let key = match data.timers.add(expires, timer) {
+            Ok(key) => key,
+            Err(_) => return Err(RegisterError::Full),
+        };
+
+        self.stats.inc(StatsType::Timer);
+
+        Ok(key)
+    }
+
+    fn poll_timer(&self, key: usize, waker: &Waker) -> Poll<()> {
+        let data = &mut *self.data.borrow_mut();
+
+        let timer = data.timers.get_mut(key).unwrap();
+
+        if timer.fired {
+            return Poll::Ready(());
+        }
+
+        if let Some(current_waker) = &timer.waker {
+            if current_waker.will_wake(waker) {
+                // keep the current waker
+                return Poll::Pending;
+            }
+        }
+
+        timer.waker = Some(waker.clone());
+
+        Poll::Pending
+    }
+
+    fn remove_timer(&self, key: usize) {
+        let data = &mut *self.data.borrow_mut();
+
+        data.timers.remove(key);
+    }
+}
+
+impl<T> Reactor for FakeReactor<T> {
+    fn with_registrations<F, V>(&self, f: F) -> V
+    where
+        F: FnOnce(&mut Slab<EventRegistration>) -> V,
+    {
+        f(&mut self.data.borrow_mut().registrations)
+    }
 }
 
 pub struct AsyncFakeStream<T, R>
@@ -170,7 +242,7 @@ where
     R: FakeReactorRef<T>,
 {
     inner: FakeStream<T>,
-    handle: RegistrationHandle<T, R>,
+    handle: RegistrationHandle<R>,
 }
 
 impl<T, R> AsyncFakeStream<T, R>
@@ -178,12 +250,12 @@ where
     T: Stats + Clone,
     R: FakeReactorRef<T>,
 {
-    pub fn new(s: FakeStream<T>, reactor: R) -> Self {
-        let handle = reactor.register(&s, READABLE | WRITABLE).unwrap();
+    pub fn new(s: FakeStream<T>, reactor: R) -> Result<Self, RegisterError> {
+        let handle = reactor.register(&s, READABLE | WRITABLE)?;
 
-        handle.set_ready(true);
+        handle.set_ready(READABLE | WRITABLE, true);
 
-        Self { inner: s, handle }
+        Ok(Self { inner: s, handle })
     }
 
     pub fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> ReadFuture<'a, T, R> {
@@ -193,6 +265,49 @@ where
     pub fn write<'a>(&'a mut self, buf: &'a [u8]) -> WriteFuture<'a, T, R> {
         WriteFuture { s: self, buf }
     }
+
+    // splits the stream into halves that can be read and written
+    // independently, for example from two different tasks
+    pub fn split(&mut self) -> (ReadHalf<'_, T, R>, WriteHalf<'_, T, R>) {
+        (ReadHalf { s: self }, WriteHalf { s: self })
+    }
+}
+
+impl<T, R> AsyncFakeStream<T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    // only needs a shared reference, so that split halves can use it
+    fn poll_read_shared(&self, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, io::Error>> {
+        self.handle
+            .poll_io(READABLE, cx, || (&self.inner).read(buf))
+    }
+
+    fn poll_write_shared(&self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        self.handle
+            .poll_io(WRITABLE, cx, || (&self.inner).write(buf))
+    }
+}
+
+impl<T, R> AsyncRead for AsyncFakeStream<T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, io::Error>> {
+        self.poll_read_shared(cx, buf)
+    }
+}
+
+impl<T, R> AsyncWrite for AsyncFakeStream<T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        self.poll_write_shared(cx, buf)
+    }
 }
 
 impl<T, R> Drop for AsyncFakeStream<T, R>
@@ -201,7 +316,63 @@ where
     R: FakeReactorRef<T>,
 {
     fn drop(&mut self) {
-        self.handle.reactor.get().unregister(&self.inner);
+        self.handle.reactor().get().unregister(&self.inner);
+    }
+}
+
+pub struct ReadHalf<'a, T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    s: &'a AsyncFakeStream<T, R>,
+}
+
+impl<T, R> ReadHalf<'_, T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    pub fn read<'b>(&'b mut self, buf: &'b mut [u8]) -> ReadFuture<'b, T, R> {
+        ReadFuture { s: self.s, buf }
+    }
+}
+
+impl<T, R> AsyncRead for ReadHalf<'_, T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, io::Error>> {
+        self.s.poll_read_shared(cx, buf)
+    }
+}
+
+pub struct WriteHalf<'a, T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    s: &'a AsyncFakeStream<T, R>,
+}
+
+impl<T, R> WriteHalf<'_, T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    pub fn write<'b>(&'b mut self, buf: &'b [u8]) -> WriteFuture<'b, T, R> {
+        WriteFuture { s: self.s, buf }
+    }
+}
+
+impl<T, R> AsyncWrite for WriteHalf<'_, T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        self.s.poll_write_shared(cx, buf)
     }
 }
 
@@ -211,7 +382,7 @@ where
     R: FakeReactorRef<T>,
 {
     inner: FakeListener<T>,
-    handle: RegistrationHandle<T, R>,
+    handle: RegistrationHandle<R>,
 }
 
 impl<T, R> AsyncFakeListener<T, R>
@@ -220,11 +391,15 @@ where
     R: FakeReactorRef<T>,
 {
     pub fn new(reactor: R, stats: T) -> Self {
-        let l = FakeListener::new(stats);
+        Self::with_scenario(reactor, stats, Scenario::DEFAULT)
+    }
+
+    pub fn with_scenario(reactor: R, stats: T, scenario: Scenario) -> Self {
+        let l = FakeListener::new(stats, scenario);
 
         let handle = reactor.register(&l, READABLE).unwrap();
 
-        handle.set_ready(true);
+        handle.set_ready(READABLE, true);
 
         Self { inner: l, handle }
     }
@@ -240,7 +415,7 @@ where
     R: FakeReactorRef<T>,
 {
     fn drop(&mut self) {
-        self.handle.reactor.get().unregister(&self.inner);
+        self.handle.reactor().get().unregister(&self.inner);
     }
 }
 
@@ -249,7 +424,7 @@ where
     T: Stats,
     R: FakeReactorRef<T>,
 {
-    s: &'a mut AsyncFakeStream<T, R>,
+    s: &'a AsyncFakeStream<T, R>,
     buf: &'a mut [u8],
 }
 
@@ -263,21 +438,7 @@ where
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let f = &mut *self;
 
-        f.s.handle.bind_waker(cx.waker());
-
-        if !f.s.handle.is_ready() {
-            return Poll::Pending;
-        }
-
-        match f.s.inner.read(f.buf) {
-            Ok(size) => Poll::Ready(Ok(size)),
-            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                f.s.handle.set_ready(false);
-
-                Poll::Pending
-            }
-            Err(e) => Poll::Ready(Err(e)),
-        }
+        f.s.poll_read_shared(cx, f.buf)
     }
 }
 
@@ -287,7 +448,7 @@ where
     R: FakeReactorRef<T>,
 {
     fn drop(&mut self) {
-        self.s.handle.unbind_waker();
+        self.s.handle.unbind_waker(READABLE);
     }
 }
 
@@ -296,7 +457,7 @@ where
     T: Stats,
     R: FakeReactorRef<T>,
 {
-    s: &'a mut AsyncFakeStream<T, R>,
+    s: &'a AsyncFakeStream<T, R>,
     buf: &'a [u8],
 }
 
@@ -310,21 +471,7 @@ where
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let f = &mut *self;
 
-        f.s.handle.bind_waker(cx.waker());
-
-        if !f.s.handle.is_ready() {
-            return Poll::Pending;
-        }
-
-        match f.s.inner.write(f.buf) {
-            Ok(size) => Poll::Ready(Ok(size)),
-            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                f.s.handle.set_ready(false);
-
-                Poll::Pending
-            }
-            Err(e) => Poll::Ready(Err(e)),
-        }
+        f.s.poll_write_shared(cx, f.buf)
     }
 }
 
@@ -334,7 +481,7 @@ where
     R: FakeReactorRef<T>,
 {
     fn drop(&mut self) {
-        self.s.handle.unbind_waker();
+        self.s.handle.unbind_waker(WRITABLE);
     }
 }
 
@@ -356,30 +503,103 @@ where
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let f = &mut *self;
 
-        f.l.handle.bind_waker(cx.waker());
+        let reactor = f.l.handle.reactor();
 
-        if !f.l.handle.is_ready() {
-            return Poll::Pending;
-        }
+        f.l.handle.poll_io(READABLE, cx, || {
+            let stream = f.l.inner.accept()?;
 
-        match f.l.inner.accept() {
-            Ok(stream) => Poll::Ready(Ok(AsyncFakeStream::new(stream, f.l.handle.reactor.clone()))),
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
-                f.l.handle.set_ready(false);
+            Ok(AsyncFakeStream::new(stream, reactor.clone())?)
+        })
+    }
+}
 
-                Poll::Pending
-            }
+impl<T, R> Drop for AcceptFuture<'_, T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    fn drop(&mut self) {
+        self.l.handle.unbind_waker(READABLE);
+    }
+}
+
+pub struct Sleep<T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    reactor: R,
+    key: Result<usize, RegisterError>,
+    _marker: PhantomData<T>,
+}
+
+impl<T, R> Sleep<T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    fn poll_timer(&self, waker: &Waker) -> Poll<Result<(), RegisterError>> {
+        match self.key {
+            Ok(key) => self.reactor.get().poll_timer(key, waker).map(Ok),
             Err(e) => Poll::Ready(Err(e)),
         }
     }
 }
 
-impl<T, R> Drop for AcceptFuture<'_, T, R>
+impl<T, R> Future for Sleep<T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    type Output = Result<(), RegisterError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.poll_timer(cx.waker())
+    }
+}
+
+impl<T, R> Drop for Sleep<T, R>
 where
     T: Stats,
     R: FakeReactorRef<T>,
 {
     fn drop(&mut self) {
-        self.l.handle.unbind_waker();
+        if let Ok(key) = self.key {
+            self.reactor.get().remove_timer(key);
+        }
+    }
+}
+
+pub struct Timeout<F, T, R>
+where
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    fut: F,
+    sleep: Sleep<T, R>,
+}
+
+impl<F, T, R> Future for Timeout<F, T, R>
+where
+    F: Future,
+    T: Stats,
+    R: FakeReactorRef<T>,
+{
+    type Output = Result<F::Output, io::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        // SAFETY: fut is structurally pinned and never moved out of self
+        let f = unsafe { self.get_unchecked_mut() };
+        let fut = unsafe { Pin::new_unchecked(&mut f.fut) };
+
+        if let Poll::Ready(v) = fut.poll(cx) {
+            return Poll::Ready(Ok(v));
+        }
+
+        match f.sleep.poll_timer(cx.waker()) {
+            Poll::Ready(Ok(())) => Poll::Ready(Err(io::Error::from(io::ErrorKind::TimedOut))),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e.into())),
+            Poll::Pending => Poll::Pending,
+        }
     }
 }
@@ -0,0 +1,156 @@
+// a minimal HTTP/1.1 server side, enough to parse the scripted requests of
+// the http workload and to answer them. requests and responses are handled
+// in the caller's buffer, without allocating. only the request line and the
+// Content-Length header are interpreted
+
+use std::io;
+use std::io::Write;
+use std::str;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Request {
+    // size of the request line and headers, including the blank line
+    pub header_len: usize,
+    pub body_len: usize,
+}
+
+impl Request {
+    pub fn len(&self) -> usize {
+        self.header_len + self.body_len
+    }
+}
+
+fn invalid() -> io::Error {
+    io::Error::from(io::ErrorKind::InvalidData)
+}
+
+fn find(buf: &[u8], s: &[u8]) -> Option<usize> {
+    buf.windows(s.len()).position(|w| w == s)
+}
+
+// returns the request at the start of buf, or none if more of it must be
+// read first
+pub fn parse_request(buf: &[u8]) -> Result<Option<Request>, io::Error> {
+    let header_len = match find(buf, b"\r\n\r\n") {
+        Some(pos) => pos + 4,
+        None => return Ok(None),
+    };
+
+    let head = str::from_utf8(&buf[..(header_len - 4)]).map_err(|_| invalid())?;
+
+    let mut lines = head.split("\r\n");
+
+    // split always returns at least one item
+    let mut parts = lines.next().unwrap().split(' ');
+
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(path), Some("HTTP/1.1"), None)
+            if !method.is_empty() && path.starts_with('/') => {}
+        _ => return Err(invalid()),
+    }
+
+    let mut body_len = 0;
+
+    for line in lines {
+        let (name, value) = line.split_once(':').ok_or_else(invalid)?;
+
+        if name.eq_ignore_ascii_case("Content-Length") {
+            body_len = value.trim().parse().map_err(|_| invalid())?;
+        }
+    }
+
+    let req = Request {
+        header_len,
+        body_len,
+    };
+
+    if buf.len() < req.len() {
+        return Ok(None);
+    }
+
+    Ok(Some(req))
+}
+
+// replaces the request in buf with a response echoing its body, and returns
+// the size of the response
+pub fn write_response(buf: &mut [u8], req: &Request) -> Result<usize, io::Error> {
+    let mut head = [0; 64];
+
+    let head_len = {
+        let mut w = io::Cursor::new(&mut head[..]);
+
+        write!(
+            w,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+            req.body_len
+        )?;
+
+        w.position() as usize
+    };
+
+    let len = head_len + req.body_len;
+
+    if len > buf.len() {
+        return Err(io::Error::from(io::ErrorKind::WriteZero));
+    }
+
+    buf.copy_within(req.header_len..req.len(), head_len);
+    buf[..head_len].copy_from_slice(&head[..head_len]);
+
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POST: &[u8] = b"POST /echo HTTP/1.1\r\nHost: localhost\r\ncontent-length: 5\r\n\r\nhello";
+
+    #[test]
+    fn test_parse_request() {
+        let req = parse_request(POST).unwrap().unwrap();
+        assert_eq!(req.header_len, POST.len() - 5);
+        assert_eq!(req.body_len, 5);
+
+        // the body is optional
+        let req = parse_request(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(req.len(), 35);
+        assert_eq!(req.body_len, 0);
+
+        // incomplete headers or body
+        assert_eq!(parse_request(&POST[..20]).unwrap(), None);
+        assert_eq!(parse_request(&POST[..(POST.len() - 1)]).unwrap(), None);
+
+        for bad in [
+            &b"GET /\r\n\r\n"[..],
+            b"GET / HTTP/1.0\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost\r\n\r\n",
+            b"GET / HTTP/1.1\r\nContent-Length: x\r\n\r\n",
+        ] {
+            let e = parse_request(bad).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut buf = [0; 128];
+        buf[..POST.len()].copy_from_slice(POST);
+
+        let req = parse_request(&buf[..POST.len()]).unwrap().unwrap();
+        let size = write_response(&mut buf, &req).unwrap();
+        assert_eq!(
+            &buf[..size],
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello"
+        );
+
+        // too large for the buffer
+        let mut buf = [0; 64];
+        buf[..POST.len()].copy_from_slice(POST);
+
+        let e = write_response(&mut buf[..40], &req).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+    }
+}
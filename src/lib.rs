@@ -1,12 +1,23 @@
+pub mod asyncio;
+pub mod channel;
+pub mod future;
 pub mod list;
+pub mod report;
+pub mod results;
 pub mod run;
+pub mod semaphore;
+pub mod timer;
 
+mod alloc;
+mod arena;
+mod epoll;
 mod executor;
 mod fakeio;
-mod future;
+mod http;
+mod registration;
+mod syncfuture;
 mod waker;
 
-pub fn run() {
-    println!("manual: {}", crate::run::run_manual(true, |r| r()));
-    println!("async: {}", crate::run::run_nonbox(true, |r| r()));
-}
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static ALLOCATOR: alloc::CountingAllocator = alloc::CountingAllocator;
@@ -27,7 +27,7 @@ impl<T> Node<T> {
         Self {
             prev: None,
             next: None,
-            value: value,
+            value,
         }
     }
 }
@@ -40,7 +40,7 @@ pub struct List {
 
 impl List {
     pub fn is_empty(&self) -> bool {
-        !self.head.is_some()
+        self.head.is_none()
     }
 
     pub fn insert<T, S>(&mut self, nodes: &mut S, after: Option<usize>, key: usize)
@@ -182,20 +182,20 @@ mod tests {
         assert_eq!(nodes[n3].next, None);
 
         let mut l = List::default();
-        assert_eq!(l.is_empty(), true);
+        assert!(l.is_empty());
         assert_eq!(l.head, None);
         assert_eq!(l.tail, None);
         assert_eq!(l.pop_front(&mut nodes), None);
 
         l.push_back(&mut nodes, n1);
-        assert_eq!(l.is_empty(), false);
+        assert!(!l.is_empty());
         assert_eq!(l.head, Some(n1));
         assert_eq!(l.tail, Some(n1));
         assert_eq!(nodes[n1].prev, None);
         assert_eq!(nodes[n1].next, None);
 
         l.push_back(&mut nodes, n2);
-        assert_eq!(l.is_empty(), false);
+        assert!(!l.is_empty());
         assert_eq!(l.head, Some(n1));
         assert_eq!(l.tail, Some(n2));
         assert_eq!(nodes[n1].prev, None);
@@ -204,7 +204,7 @@ mod tests {
         assert_eq!(nodes[n2].next, None);
 
         l.push_back(&mut nodes, n3);
-        assert_eq!(l.is_empty(), false);
+        assert!(!l.is_empty());
         assert_eq!(l.head, Some(n1));
         assert_eq!(l.tail, Some(n3));
         assert_eq!(nodes[n1].prev, None);
@@ -216,7 +216,7 @@ mod tests {
 
         let key = l.pop_front(&mut nodes);
         assert_eq!(key, Some(n1));
-        assert_eq!(l.is_empty(), false);
+        assert!(!l.is_empty());
         assert_eq!(l.head, Some(n2));
         assert_eq!(l.tail, Some(n3));
         assert_eq!(nodes[n2].prev, None);
@@ -226,7 +226,7 @@ mod tests {
 
         let key = l.pop_front(&mut nodes);
         assert_eq!(key, Some(n2));
-        assert_eq!(l.is_empty(), false);
+        assert!(!l.is_empty());
         assert_eq!(l.head, Some(n3));
         assert_eq!(l.tail, Some(n3));
         assert_eq!(nodes[n3].prev, None);
@@ -234,7 +234,7 @@ mod tests {
 
         let key = l.pop_front(&mut nodes);
         assert_eq!(key, Some(n3));
-        assert_eq!(l.is_empty(), true);
+        assert!(l.is_empty());
         assert_eq!(l.head, None);
         assert_eq!(l.tail, None);
 
@@ -250,19 +250,19 @@ mod tests {
         assert_eq!(nodes[n1].next, None);
 
         let mut l = List::default();
-        assert_eq!(l.is_empty(), true);
+        assert!(l.is_empty());
         assert_eq!(l.head, None);
         assert_eq!(l.tail, None);
 
         l.push_back(&mut nodes, n1);
-        assert_eq!(l.is_empty(), false);
+        assert!(!l.is_empty());
         assert_eq!(l.head, Some(n1));
         assert_eq!(l.tail, Some(n1));
         assert_eq!(nodes[n1].prev, None);
         assert_eq!(nodes[n1].next, None);
 
         l.remove(&mut nodes, n1);
-        assert_eq!(l.is_empty(), true);
+        assert!(l.is_empty());
         assert_eq!(l.head, None);
         assert_eq!(l.tail, None);
         assert_eq!(nodes[n1].prev, None);
@@ -270,7 +270,7 @@ mod tests {
 
         // already removed
         l.remove(&mut nodes, n1);
-        assert_eq!(l.is_empty(), true);
+        assert!(l.is_empty());
         assert_eq!(l.head, None);
         assert_eq!(l.tail, None);
         assert_eq!(nodes[n1].prev, None);
@@ -287,10 +287,10 @@ mod tests {
         let mut b = List::default();
 
         a.concat(&mut nodes, &mut b);
-        assert_eq!(a.is_empty(), true);
+        assert!(a.is_empty());
         assert_eq!(a.head, None);
         assert_eq!(a.tail, None);
-        assert_eq!(b.is_empty(), true);
+        assert!(b.is_empty());
         assert_eq!(b.head, None);
         assert_eq!(b.tail, None);
 
@@ -298,10 +298,10 @@ mod tests {
         b.push_back(&mut nodes, n2);
 
         a.concat(&mut nodes, &mut b);
-        assert_eq!(a.is_empty(), false);
+        assert!(!a.is_empty());
         assert_eq!(a.head, Some(n1));
         assert_eq!(a.tail, Some(n2));
-        assert_eq!(b.is_empty(), true);
+        assert!(b.is_empty());
         assert_eq!(b.head, None);
         assert_eq!(b.tail, None);
         assert_eq!(nodes[n1].prev, None);
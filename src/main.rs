@@ -1,3 +1,696 @@
+use rust_async_bench::report;
+use rust_async_bench::results::{self, Config, HostInfo, Record};
+use rust_async_bench::run::{self, BenchConfig, Protocol, Variant, VARIANTS};
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: rust-async-bench [options] [variant...]
+       rust-async-bench report [options] file...
+       rust-async-bench check [options] baseline current
+
+Runs the selected variants (all of them by default), printing the I/O counts
+and the time taken per iteration. Each iteration serves the given number of
+requests on each connection, one after another. The variants that use real
+sockets, such as manual+epoll, don't support --syscalls or --protocol, and are
+skipped by default when either is given.
+
+options:
+  --list            list the available variants and exit
+  --syscalls        make a syscall for each I/O operation
+  --conns N         number of connections per iteration (default: 256)
+  --conns-max N     most connections open at once (default: same as --conns)
+  --slab-capacity N initial capacity of the manual variants' connection
+                    slabs (default: same as --conns-max)
+  --requests N      requests per connection (default: 1)
+  --protocol PROTO  request protocol: line or http (default: line)
+  --work NS         do about NS nanoseconds of simulated work per request.
+                    the work loop is calibrated on startup (default: 0)
+  --iterations N    number of iterations (default: 1)
+  --format FORMAT   output format: text, json, or csv (default: text)
+  -h, --help        show this help
+
+Use \"report --help\" and \"check --help\" for the options of the other
+commands.";
+
+const REPORT_USAGE: &str = "usage: rust-async-bench report [options] file...
+
+Summarizes JSON result files written with --format json, as markdown. The
+best (lowest mean) run of each variant is used, and pairs of variants are
+compared. All runs of a variant must have the same config, apart from the
+number of iterations.
+
+options:
+  --compare A/B     compare variant A to variant B. may be repeated. by
+                    default, a few interesting pairs are compared
+  --work NS         also compare the syscalls variants as if each request
+                    did NS nanoseconds of work, as variants named +work
+  -h, --help        show this help";
+
+const CHECK_USAGE: &str = "usage: rust-async-bench check [options] baseline current
+
+Compares a JSON result file against a baseline result file. Fails if the best
+time of any variant got slower by more than the threshold, if any of its
+counts changed, or if it is missing from the current results or was run with
+a different config. All runs of a variant within a file must have the same
+config, apart from the number of iterations.
+
+options:
+  --threshold PCT       allowed slowdown in percent (default: 5)
+  --threshold NAME=PCT  allowed slowdown for one variant. may be repeated
+  -h, --help            show this help";
+
+struct ReportArgs {
+    pairs: Vec<(String, String)>,
+    work_ns: Option<u64>,
+    files: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+struct Args {
+    list: bool,
+    syscalls: bool,
+    config: BenchConfig,
+    work_ns: u64,
+    iterations: usize,
+    format: Format,
+    variants: Vec<&'static Variant>,
+}
+
+fn parse_count(name: &str, value: Option<String>) -> Result<usize, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", name))?;
+
+    match value.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("invalid value for {}: {}", name, value)),
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Args>, String> {
+    let mut conns = BenchConfig::DEFAULT.conns;
+    let mut conns_max = None;
+    let mut slab_capacity = None;
+    let mut requests = BenchConfig::DEFAULT.requests;
+    let mut protocol = BenchConfig::DEFAULT.protocol;
+
+    let mut out = Args {
+        list: false,
+        syscalls: false,
+        config: BenchConfig::DEFAULT,
+        work_ns: 0,
+        iterations: 1,
+        format: Format::Text,
+        variants: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--list" => out.list = true,
+            "--syscalls" => out.syscalls = true,
+            "--conns" => conns = parse_count(&arg, args.next())?,
+            "--conns-max" => conns_max = Some(parse_count(&arg, args.next())?),
+            "--slab-capacity" => slab_capacity = Some(parse_count(&arg, args.next())?),
+            "--requests" => requests = parse_count(&arg, args.next())?,
+            "--work" => out.work_ns = parse_count(&arg, args.next())? as u64,
+            "--protocol" => {
+                protocol = match args.next().as_deref() {
+                    Some("line") => Protocol::Line,
+                    Some("http") => Protocol::Http,
+                    Some(p) => return Err(format!("unknown protocol: {}", p)),
+                    None => return Err(format!("{} requires a value", arg)),
+                }
+            }
+            "--iterations" => out.iterations = parse_count(&arg, args.next())?,
+            "--format" => {
+                out.format = match args.next().as_deref() {
+                    Some("text") => Format::Text,
+                    Some("json") => Format::Json,
+                    Some("csv") => Format::Csv,
+                    Some(f) => return Err(format!("unknown format: {}", f)),
+                    None => return Err(format!("{} requires a value", arg)),
+                }
+            }
+            name if name.starts_with('-') => return Err(format!("unknown option: {}", name)),
+            name => match run::find_variant(name) {
+                Some(v) => out.variants.push(v),
+                None => return Err(format!("unknown variant: {} (see --list)", name)),
+            },
+        }
+    }
+
+    let conns_max = conns_max.unwrap_or(conns);
+
+    out.config = BenchConfig {
+        conns,
+        conns_max,
+        slab_capacity: slab_capacity.unwrap_or(conns_max),
+        requests,
+        protocol,
+        work: 0,
+    };
+
+    // the real i/o variants can't honor these options. they are left out
+    // of the default selection, but naming one is an error
+    let fake_only = if out.syscalls {
+        Some("--syscalls")
+    } else if protocol != Protocol::Line {
+        Some("--protocol")
+    } else {
+        None
+    };
+
+    if out.variants.is_empty() {
+        out.variants = VARIANTS
+            .iter()
+            .filter(|v| fake_only.is_none() || !v.real_io)
+            .collect();
+    } else if let Some(option) = fake_only {
+        if let Some(v) = out.variants.iter().find(|v| v.real_io) {
+            return Err(format!(
+                "{} uses real sockets and doesn't support {}",
+                v.name, option
+            ));
+        }
+    }
+
+    Ok(Some(out))
+}
+
+fn parse_report_args<I: Iterator<Item = String>>(
+    mut args: I,
+) -> Result<Option<ReportArgs>, String> {
+    let mut out = ReportArgs {
+        pairs: Vec::new(),
+        work_ns: None,
+        files: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--compare" => {
+                let value = args.next().ok_or("--compare requires a value")?;
+
+                match value.split_once('/') {
+                    Some((a, b)) if !a.is_empty() && !b.is_empty() => {
+                        out.pairs.push((a.to_string(), b.to_string()))
+                    }
+                    _ => return Err(format!("invalid value for --compare: {}", value)),
+                }
+            }
+            "--work" => out.work_ns = Some(parse_count(&arg, args.next())? as u64),
+            name if name.starts_with('-') => return Err(format!("unknown option: {}", name)),
+            path => out.files.push(PathBuf::from(path)),
+        }
+    }
+
+    if out.files.is_empty() {
+        return Err("no result files specified".to_string());
+    }
+
+    Ok(Some(out))
+}
+
+struct CheckArgs {
+    thresholds: report::Thresholds,
+    baseline: PathBuf,
+    current: PathBuf,
+}
+
+fn parse_percent(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(n) if n >= 0.0 => Ok(n),
+        _ => Err(format!("invalid value for --threshold: {}", value)),
+    }
+}
+
+fn parse_check_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<CheckArgs>, String> {
+    let mut thresholds = report::Thresholds {
+        default: 5.0,
+        per_variant: Vec::new(),
+    };
+
+    let mut files = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--threshold" => {
+                let value = args.next().ok_or("--threshold requires a value")?;
+
+                match value.split_once('=') {
+                    Some((name, pct)) => thresholds
+                        .per_variant
+                        .push((name.to_string(), parse_percent(pct)?)),
+                    None => thresholds.default = parse_percent(&value)?,
+                }
+            }
+            name if name.starts_with('-') => return Err(format!("unknown option: {}", name)),
+            path => files.push(PathBuf::from(path)),
+        }
+    }
+
+    let [baseline, current]: [PathBuf; 2] = files
+        .try_into()
+        .map_err(|_| "expected a baseline file and a current file".to_string())?;
+
+    Ok(Some(CheckArgs {
+        thresholds,
+        baseline,
+        current,
+    }))
+}
+
+fn run_variant(
+    v: &Variant,
+    bench: BenchConfig,
+    work_ns: u64,
+    syscalls: bool,
+    iterations: usize,
+    host: &HostInfo,
+) -> Record {
+    let mut times = Vec::with_capacity(iterations);
+
+    let (stats, latency) = (v.run)(bench, syscalls, &mut |r| {
+        for _ in 0..iterations {
+            let start = Instant::now();
+
+            r();
+
+            times.push(start.elapsed());
+        }
+    });
+
+    let config = Config {
+        syscalls,
+        bufsize: v.bufsize,
+        conns: bench.conns,
+        conns_max: bench.conns_max,
+        slab_capacity: bench.slab_capacity,
+        requests: bench.requests,
+        protocol: bench.protocol,
+        work: bench.work,
+        work_ns,
+        iterations,
+    };
+
+    Record::new(
+        v.name,
+        config,
+        host.clone(),
+        stats,
+        latency.as_ref(),
+        &times,
+    )
+}
+
+fn print_text(r: &Record) {
+    let ns = Duration::from_nanos;
+    let per_request = r.mean_ns() / r.config.total_requests();
+
+    // counts are totals across all iterations
+    println!("{}: {}", r.name(), r.stats);
+    println!(
+        "  time: iterations={} min={:?} mean={:?} max={:?} per-request={:?}",
+        r.config.iterations,
+        ns(r.min_ns()),
+        ns(r.mean_ns()),
+        ns(r.max_ns()),
+        ns(per_request)
+    );
+
+    if let Some(l) = &r.latency {
+        println!(
+            "  latency: transactions={} min={:?} mean={:?} max={:?}",
+            l.transactions,
+            ns(l.min_ns),
+            ns(l.mean_ns),
+            ns(l.max_ns)
+        );
+    }
+}
+
+fn read_records(path: &Path) -> Vec<Record> {
+    match report::read_records(path) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("error: {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+}
+
+fn report_main<I: Iterator<Item = String>>(args: I) {
+    let args = match parse_report_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", REPORT_USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, REPORT_USAGE);
+            process::exit(1);
+        }
+    };
+
+    let mut records = Vec::new();
+
+    for path in &args.files {
+        records.extend(read_records(path));
+    }
+
+    let mut best = match report::best_of(&records) {
+        Ok(best) => best,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if let Some(work_ns) = args.work_ns {
+        report::add_work(&mut best, work_ns);
+    }
+
+    let mut comparisons = Vec::new();
+
+    if args.pairs.is_empty() {
+        for (a, b) in report::default_pairs() {
+            comparisons.extend(report::compare(&best, &a, &b));
+        }
+    } else {
+        for (a, b) in &args.pairs {
+            match report::compare(&best, a, b) {
+                Some(c) => comparisons.push(c),
+                None => {
+                    eprintln!("error: no results for {} or {}", a, b);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    print!("{}", report::render(&best, &comparisons));
+}
+
+fn run_main<I: Iterator<Item = String>>(args: I) {
+    let args = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            process::exit(1);
+        }
+    };
+
+    if args.list {
+        for v in VARIANTS {
+            println!("{}", v.name);
+        }
+
+        return;
+    }
+
+    let mut config = args.config;
+
+    if args.work_ns > 0 {
+        config.work = run::calibrate_work(Duration::from_nanos(args.work_ns));
+    }
+
+    let host = HostInfo::current();
+    let mut records = Vec::new();
+
+    for v in args.variants {
+        let r = run_variant(
+            v,
+            config,
+            args.work_ns,
+            args.syscalls,
+            args.iterations,
+            &host,
+        );
+
+        // print as we go, so progress is visible
+        if let Format::Text = args.format {
+            print_text(&r);
+        }
+
+        records.push(r);
+    }
+
+    let ret = match args.format {
+        Format::Text => Ok(()),
+        Format::Json => results::write_json(io::stdout(), &records),
+        Format::Csv => results::write_csv(io::stdout(), &records),
+    };
+
+    if let Err(e) = ret {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn check_main<I: Iterator<Item = String>>(args: I) {
+    let args = match parse_check_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", CHECK_USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, CHECK_USAGE);
+            process::exit(1);
+        }
+    };
+
+    let baseline = read_records(&args.baseline);
+    let current = read_records(&args.current);
+
+    let diffs = match report::check_baseline(&baseline, &current, &args.thresholds) {
+        Ok(diffs) => diffs,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    print!("{}", report::render_baseline(&diffs));
+
+    let failed = diffs.iter().filter(|d| d.failed()).count();
+
+    if failed > 0 {
+        eprintln!(
+            "error: {} of {} variants failed the check",
+            failed,
+            diffs.len()
+        );
+        process::exit(1);
+    }
+}
+
 fn main() {
-    rust_async_bench::run();
+    let mut args = env::args().skip(1).peekable();
+
+    match args.peek().map(|s| s.as_str()) {
+        Some("report") => {
+            args.next();
+
+            report_main(args);
+        }
+        Some("check") => {
+            args.next();
+
+            check_main(args);
+        }
+        _ => run_main(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> impl Iterator<Item = String> + '_ {
+        s.split_whitespace().map(String::from)
+    }
+
+    fn parse(s: &str) -> Args {
+        match parse_args(args(s)) {
+            Ok(Some(a)) => a,
+            Ok(None) => panic!("expected args"),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    fn parse_err<T>(r: Result<Option<T>, String>) -> String {
+        match r {
+            Err(e) => e,
+            Ok(_) => panic!("expected error"),
+        }
+    }
+
+    fn names(a: &Args) -> Vec<&'static str> {
+        a.variants.iter().map(|v| v.name).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let a = parse("");
+        assert!(!a.syscalls);
+        assert_eq!(a.config, BenchConfig::DEFAULT);
+        assert_eq!(a.work_ns, 0);
+        assert_eq!(a.variants.len(), VARIANTS.len());
+
+        let a = parse("--conns 8 --requests 2 --work 500 --protocol http");
+        assert_eq!(a.config.conns, 8);
+        assert_eq!(a.config.conns_max, 8);
+        assert_eq!(a.config.slab_capacity, 8);
+        assert_eq!(a.config.requests, 2);
+        assert_eq!(a.config.protocol, Protocol::Http);
+        assert_eq!(a.work_ns, 500);
+
+        let a = parse("--conns 8 --conns-max 4");
+        assert_eq!(a.config.conns_max, 4);
+        assert_eq!(a.config.slab_capacity, 4);
+
+        assert!(parse_args(args("--conns 8 --help")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert_eq!(
+            parse_err(parse_args(args("--bogus"))),
+            "unknown option: --bogus"
+        );
+        assert_eq!(
+            parse_err(parse_args(args("--conns"))),
+            "--conns requires a value"
+        );
+        assert_eq!(
+            parse_err(parse_args(args("--protocol"))),
+            "--protocol requires a value"
+        );
+        assert_eq!(
+            parse_err(parse_args(args("--work 0"))),
+            "invalid value for --work: 0"
+        );
+        assert_eq!(
+            parse_err(parse_args(args("--protocol smtp"))),
+            "unknown protocol: smtp"
+        );
+        assert_eq!(
+            parse_err(parse_args(args("nope"))),
+            "unknown variant: nope (see --list)"
+        );
+    }
+
+    #[test]
+    fn test_parse_args_variants() {
+        let a = parse("box nonbox");
+        assert_eq!(names(&a), ["box", "nonbox"]);
+
+        // naming a real i/o variant works without the fake-only options
+        let a = parse("manual+epoll --work 100");
+        assert_eq!(names(&a), ["manual+epoll"]);
+
+        // the real i/o variants are skipped by default with either option
+        for opts in [
+            "--syscalls",
+            "--protocol http",
+            "--work 100 --protocol http",
+        ] {
+            let a = parse(opts);
+            assert!(!a.variants.is_empty());
+            assert!(a.variants.iter().all(|v| !v.real_io));
+        }
+
+        let a = parse("--work 100 --protocol line");
+        assert_eq!(a.variants.len(), VARIANTS.len());
+
+        // but naming one is an error
+        assert_eq!(
+            parse_err(parse_args(args("--syscalls nonbox manual+epoll"))),
+            "manual+epoll uses real sockets and doesn't support --syscalls"
+        );
+        assert_eq!(
+            parse_err(parse_args(args("blocking --protocol http"))),
+            "blocking uses real sockets and doesn't support --protocol"
+        );
+    }
+
+    #[test]
+    fn test_parse_report_args() {
+        let a = match parse_report_args(args("--compare a/b --work 100 x.json y.json")) {
+            Ok(Some(a)) => a,
+            _ => panic!("expected args"),
+        };
+        assert_eq!(a.pairs, [("a".to_string(), "b".to_string())]);
+        assert_eq!(a.work_ns, Some(100));
+        assert_eq!(a.files, [PathBuf::from("x.json"), PathBuf::from("y.json")]);
+
+        assert!(parse_report_args(args("--help")).unwrap().is_none());
+
+        assert_eq!(
+            parse_err(parse_report_args(args("--bogus x.json"))),
+            "unknown option: --bogus"
+        );
+        assert_eq!(
+            parse_err(parse_report_args(args("x.json --compare"))),
+            "--compare requires a value"
+        );
+        assert_eq!(
+            parse_err(parse_report_args(args("x.json --work"))),
+            "--work requires a value"
+        );
+        assert_eq!(
+            parse_err(parse_report_args(args("--compare a/ x.json"))),
+            "invalid value for --compare: a/"
+        );
+        assert_eq!(
+            parse_err(parse_report_args(args("--work 100"))),
+            "no result files specified"
+        );
+    }
+
+    #[test]
+    fn test_parse_check_args() {
+        let a = match parse_check_args(args("--threshold 10 --threshold box=20 a.json b.json")) {
+            Ok(Some(a)) => a,
+            _ => panic!("expected args"),
+        };
+        assert_eq!(a.thresholds.default, 10.0);
+        assert_eq!(a.thresholds.per_variant, [("box".to_string(), 20.0)]);
+        assert_eq!(a.baseline, PathBuf::from("a.json"));
+        assert_eq!(a.current, PathBuf::from("b.json"));
+
+        assert!(parse_check_args(args("--help")).unwrap().is_none());
+
+        assert_eq!(
+            parse_err(parse_check_args(args("--bogus a.json b.json"))),
+            "unknown option: --bogus"
+        );
+        assert_eq!(
+            parse_err(parse_check_args(args("a.json b.json --threshold"))),
+            "--threshold requires a value"
+        );
+        assert_eq!(
+            parse_err(parse_check_args(args("--threshold box=-1 a.json b.json"))),
+            "invalid value for --threshold: -1"
+        );
+        assert_eq!(
+            parse_err(parse_check_args(args("a.json"))),
+            "expected a baseline file and a current file"
+        );
+    }
 }
@@ -0,0 +1,199 @@
+// the state each reactor keeps for a registered I/O object. readiness and
+// wakers are tracked per direction, so that a stream can be read by one task
+// while it is written by another. the reactors differ only in how they
+// protect this state, and in how they watch the objects themselves, so the
+// handles and readiness polling are shared here over the Reactor trait
+
+use crate::fakeio::{READABLE, WRITABLE};
+use crate::future::RegisterError;
+use slab::Slab;
+use std::io;
+use std::task::{Context, Poll, Waker};
+
+pub trait Reactor {
+    // calls f with the reactor's registrations, under whatever protection
+    // the reactor uses. f must not call back into the reactor
+    fn with_registrations<F, V>(&self, f: F) -> V
+    where
+        F: FnOnce(&mut Slab<EventRegistration>) -> V;
+}
+
+// something that refers to a reactor, such as a reference or an Rc
+pub trait ReactorRef: Clone {
+    type Reactor: Reactor;
+
+    fn get(&self) -> &Self::Reactor;
+}
+
+pub struct RegistrationHandle<R>
+where
+    R: ReactorRef,
+{
+    reactor: R,
+    key: usize,
+}
+
+impl<R> RegistrationHandle<R>
+where
+    R: ReactorRef,
+{
+    // reserves a registration. the caller then has the reactor watch the
+    // I/O object under the handle's key
+    pub fn new(reactor: R) -> Result<Self, RegisterError> {
+        let key = reactor.get().with_registrations(|registrations| {
+            if registrations.len() == registrations.capacity() {
+                return Err(RegisterError::Full);
+            }
+
+            Ok(registrations.insert(EventRegistration::new()))
+        })?;
+
+        Ok(Self { reactor, key })
+    }
+
+    pub fn reactor(&self) -> &R {
+        &self.reactor
+    }
+
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    // interest is READABLE and/or WRITABLE
+    pub fn is_ready(&self, interest: u8) -> bool {
+        self.with_registration(|r| r.is_ready(interest))
+    }
+
+    pub fn set_ready(&self, interest: u8, ready: bool) {
+        self.with_registration(|r| r.set_ready(interest, ready));
+    }
+
+    // interest is either READABLE or WRITABLE
+    pub fn bind_waker(&self, interest: u8, waker: &Waker) {
+        self.with_registration(|r| r.bind_waker(interest, waker));
+    }
+
+    pub fn unbind_waker(&self, interest: u8) {
+        self.with_registration(|r| r.unbind_waker(interest));
+    }
+
+    // attempts an I/O operation in one direction, if the object may be ready
+    // for it. a WouldBlock result clears the readiness, and the task is woken
+    // when the reactor sees the object become ready again
+    pub fn poll_io<F, V>(&self, interest: u8, cx: &mut Context, f: F) -> Poll<Result<V, io::Error>>
+    where
+        F: FnOnce() -> Result<V, io::Error>,
+    {
+        self.bind_waker(interest, cx.waker());
+
+        if !self.is_ready(interest) {
+            return Poll::Pending;
+        }
+
+        match f() {
+            Ok(v) => Poll::Ready(Ok(v)),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.set_ready(interest, false);
+
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    fn with_registration<F, V>(&self, f: F) -> V
+    where
+        F: FnOnce(&mut EventRegistration) -> V,
+    {
+        self.reactor
+            .get()
+            .with_registrations(|registrations| f(&mut registrations[self.key]))
+    }
+}
+
+impl<R> Drop for RegistrationHandle<R>
+where
+    R: ReactorRef,
+{
+    fn drop(&mut self) {
+        self.reactor.get().with_registrations(|registrations| {
+            registrations.remove(self.key);
+        });
+    }
+}
+
+pub struct EventRegistration {
+    ready: u8,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl EventRegistration {
+    pub fn new() -> Self {
+        Self {
+            ready: 0,
+            read_waker: None,
+            write_waker: None,
+        }
+    }
+
+    // interest is READABLE and/or WRITABLE
+    pub fn is_ready(&self, interest: u8) -> bool {
+        self.ready & interest != 0
+    }
+
+    pub fn set_ready(&mut self, interest: u8, ready: bool) {
+        if ready {
+            self.ready |= interest;
+        } else {
+            self.ready &= !interest;
+        }
+    }
+
+    // interest is either READABLE or WRITABLE
+    pub fn bind_waker(&mut self, interest: u8, waker: &Waker) {
+        let slot = self.waker_mut(interest);
+
+        if let Some(current_waker) = slot {
+            if current_waker.will_wake(waker) {
+                // keep the current waker
+                return;
+            }
+        }
+
+        *slot = Some(waker.clone());
+    }
+
+    pub fn unbind_waker(&mut self, interest: u8) {
+        *self.waker_mut(interest) = None;
+    }
+
+    // marks the object ready in the given directions, and passes along the
+    // wakers that were waiting on them. the caller decides when to call them
+    pub fn set_readiness<F>(&mut self, readiness: u8, mut f: F)
+    where
+        F: FnMut(Waker),
+    {
+        self.ready |= readiness;
+
+        if readiness & READABLE != 0 {
+            if let Some(waker) = self.read_waker.take() {
+                f(waker);
+            }
+        }
+
+        if readiness & WRITABLE != 0 {
+            if let Some(waker) = self.write_waker.take() {
+                f(waker);
+            }
+        }
+    }
+
+    fn waker_mut(&mut self, interest: u8) -> &mut Option<Waker> {
+        if interest == READABLE {
+            &mut self.read_waker
+        } else {
+            &mut self.write_waker
+        }
+    }
+}
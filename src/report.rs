@@ -0,0 +1,631 @@
+// summarizes result files written by the runner: the best time of each
+// variant across all runs, and comparisons between pairs of variants,
+// rendered as markdown. result sets can also be checked against a baseline,
+// to catch regressions
+
+use crate::results::{self, Record};
+use crate::run::{self, Protocol};
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+// a comparison between two variants run with the same options
+struct DefaultPair {
+    a: &'static str,
+    b: &'static str,
+    work: bool,
+    syscalls: bool,
+}
+
+// comparisons made when none are specified
+const DEFAULT_PAIRS: &[DefaultPair] = &[
+    DefaultPair {
+        a: "manual",
+        b: "nonbox",
+        work: false,
+        syscalls: false,
+    },
+    DefaultPair {
+        a: "manual",
+        b: "nonbox",
+        work: false,
+        syscalls: true,
+    },
+    DefaultPair {
+        a: "nonbox",
+        b: "box",
+        work: false,
+        syscalls: true,
+    },
+    DefaultPair {
+        a: "box+rc",
+        b: "box+arc",
+        work: false,
+        syscalls: true,
+    },
+    DefaultPair {
+        a: "manual",
+        b: "nonbox",
+        work: true,
+        syscalls: true,
+    },
+];
+
+// the names of the variants compared when none are specified. pairs with
+// missing variants should be skipped
+pub fn default_pairs() -> Vec<(String, String)> {
+    DEFAULT_PAIRS
+        .iter()
+        .map(|p| {
+            let name = |v| results::variant_name(v, Protocol::Line, p.work, p.syscalls);
+
+            (name(p.a), name(p.b))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Best {
+    pub name: String,
+    // mean time of an iteration, in the best run
+    pub ns: u64,
+    pub runs: usize,
+    pub record: Record,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub a: String,
+    pub b: String,
+    pub ratio: f64,
+    pub percent: f64,
+    // difference in time per request, a minus b
+    pub per_request_ns: i64,
+}
+
+pub fn read_records(path: &Path) -> Result<Vec<Record>, io::Error> {
+    let f = File::open(path)?;
+
+    Ok(serde_json::from_reader(io::BufReader::new(f))?)
+}
+
+// runs of the same variant were made with different configs, so they can't
+// be combined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigMismatch {
+    pub name: String,
+}
+
+impl fmt::Display for ConfigMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "runs of {} have different configs", self.name)
+    }
+}
+
+impl Error for ConfigMismatch {}
+
+// the best run of each variant, in order of first appearance. all runs of a
+// variant must have comparable configs
+pub fn best_of(records: &[Record]) -> Result<Vec<Best>, ConfigMismatch> {
+    let mut out: Vec<Best> = Vec::new();
+
+    for r in records {
+        let name = r.name();
+        let ns = r.mean_ns();
+
+        match out.iter_mut().find(|b| b.name == name) {
+            Some(b) => {
+                if !b.record.config.is_comparable(&r.config) {
+                    return Err(ConfigMismatch { name });
+                }
+
+                if ns < b.ns {
+                    b.ns = ns;
+                    b.record = r.clone();
+                }
+
+                b.runs += 1;
+            }
+            None => out.push(Best {
+                name,
+                ns,
+                runs: 1,
+                record: r.clone(),
+            }),
+        }
+    }
+
+    Ok(out)
+}
+
+// adds a "+work" entry for each syscalls variant, as if every request also
+// did work_ns of application work. this estimates the relative cost of async
+// in a more realistic setting. runs made with --work measure it instead, and
+// have the same names, so a measured entry is kept over an estimate
+pub fn add_work(best: &mut Vec<Best>, work_ns: u64) {
+    let work: Vec<Best> = best
+        .iter()
+        .filter(|b| b.record.config.syscalls && b.record.config.work_ns == 0)
+        .map(|b| Best {
+            name: results::variant_name(&b.record.variant, b.record.config.protocol, true, true),
+            ns: b.ns + work_ns * b.record.config.total_requests(),
+            runs: b.runs,
+            record: b.record.clone(),
+        })
+        .filter(|w| !best.iter().any(|b| b.name == w.name))
+        .collect();
+
+    best.extend(work);
+}
+
+pub fn compare(best: &[Best], a: &str, b: &str) -> Option<Comparison> {
+    let ta = best.iter().find(|x| x.name == a)?;
+    let tb = best.iter().find(|x| x.name == b)?;
+
+    let ratio = (ta.ns as f64) / (tb.ns as f64);
+    let diff = (ta.ns as i64) - (tb.ns as i64);
+
+    Some(Comparison {
+        a: a.to_string(),
+        b: b.to_string(),
+        ratio,
+        percent: (ratio - 1.0) * 100.0,
+        per_request_ns: diff / (ta.record.config.total_requests().max(1) as i64),
+    })
+}
+
+fn format_ns(ns: i64) -> String {
+    let d = Duration::from_nanos(ns.unsigned_abs());
+
+    if ns < 0 {
+        format!("-{:?}", d)
+    } else {
+        format!("{:?}", d)
+    }
+}
+
+pub fn render(best: &[Best], comparisons: &[Comparison]) -> String {
+    let mut out = String::new();
+
+    out.push_str("| variant | time | runs |\n");
+    out.push_str("|---|---|---|\n");
+
+    for b in best {
+        writeln!(
+            out,
+            "| {} | {} | {} |",
+            b.name,
+            format_ns(b.ns as i64),
+            b.runs
+        )
+        .unwrap();
+    }
+
+    if !comparisons.is_empty() {
+        out.push('\n');
+        out.push_str("| comparison | ratio | difference | per request |\n");
+        out.push_str("|---|---|---|---|\n");
+
+        for c in comparisons {
+            writeln!(
+                out,
+                "| {} / {} | {:.4} | {:+.2}% | {} |",
+                c.a,
+                c.b,
+                c.ratio,
+                c.percent,
+                format_ns(c.per_request_ns)
+            )
+            .unwrap();
+        }
+    }
+
+    out
+}
+
+// allowed slowdown per variant, in percent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thresholds {
+    pub default: f64,
+    pub per_variant: Vec<(String, f64)>,
+}
+
+impl Thresholds {
+    pub fn get(&self, name: &str) -> f64 {
+        self.per_variant
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map_or(self.default, |(_, t)| *t)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaselineDiff {
+    pub name: String,
+    pub baseline_ns: u64,
+    // none if the variant is missing from the current results
+    pub current_ns: Option<u64>,
+    pub percent: f64,
+    pub threshold: f64,
+    // the current run can't be compared to the baseline run
+    pub config_changed: bool,
+    // name, baseline value, and current value, per iteration
+    pub changed_counts: Vec<(&'static str, u64, u64)>,
+}
+
+impl BaselineDiff {
+    pub fn regressed(&self) -> bool {
+        self.current_ns.is_some() && self.percent > self.threshold
+    }
+
+    pub fn failed(&self) -> bool {
+        self.current_ns.is_none()
+            || self.config_changed
+            || self.regressed()
+            || !self.changed_counts.is_empty()
+    }
+}
+
+// counts are compared per iteration, so the result sets may use different
+// iteration counts. counts that a variant declares as varying are skipped
+fn changed_counts(baseline: &Record, current: &Record) -> Vec<(&'static str, u64, u64)> {
+    let varying = run::find_variant(&baseline.variant).map_or(&[][..], |v| v.varying_counts);
+
+    let bi = baseline.config.iterations.max(1) as u64;
+    let ci = current.config.iterations.max(1) as u64;
+
+    baseline
+        .stats
+        .fields()
+        .iter()
+        .zip(current.stats.fields().iter())
+        .filter(|((name, b), (_, c))| !varying.contains(name) && b * ci != c * bi)
+        .map(|((name, b), (_, c))| (*name, b / bi, c / ci))
+        .collect()
+}
+
+// compares the best run of each baseline variant to the best current run.
+// a variant that is missing from the current runs, or whose config changed,
+// fails the check
+pub fn check_baseline(
+    baseline: &[Record],
+    current: &[Record],
+    thresholds: &Thresholds,
+) -> Result<Vec<BaselineDiff>, ConfigMismatch> {
+    let current = best_of(current)?;
+
+    let diffs = best_of(baseline)?
+        .into_iter()
+        .map(|b| {
+            let threshold = thresholds.get(&b.name);
+
+            match current.iter().find(|c| c.name == b.name) {
+                Some(c) if !b.record.config.is_comparable(&c.record.config) => BaselineDiff {
+                    name: b.name,
+                    baseline_ns: b.ns,
+                    current_ns: Some(c.ns),
+                    percent: 0.0,
+                    threshold,
+                    config_changed: true,
+                    changed_counts: Vec::new(),
+                },
+                Some(c) => BaselineDiff {
+                    percent: ((c.ns as f64) / (b.ns as f64) - 1.0) * 100.0,
+                    changed_counts: changed_counts(&b.record, &c.record),
+                    name: b.name,
+                    baseline_ns: b.ns,
+                    current_ns: Some(c.ns),
+                    threshold,
+                    config_changed: false,
+                },
+                None => BaselineDiff {
+                    name: b.name,
+                    baseline_ns: b.ns,
+                    current_ns: None,
+                    percent: 0.0,
+                    threshold,
+                    config_changed: false,
+                    changed_counts: Vec::new(),
+                },
+            }
+        })
+        .collect();
+
+    Ok(diffs)
+}
+
+pub fn render_baseline(diffs: &[BaselineDiff]) -> String {
+    let mut out = String::new();
+
+    out.push_str("| variant | baseline | current | difference | threshold | status |\n");
+    out.push_str("|---|---|---|---|---|---|\n");
+
+    for d in diffs {
+        let current = match d.current_ns {
+            Some(ns) => format_ns(ns as i64),
+            None => "-".to_string(),
+        };
+
+        let mut status = Vec::new();
+
+        if d.current_ns.is_none() {
+            status.push("missing".to_string());
+        }
+
+        if d.config_changed {
+            status.push("config changed".to_string());
+        }
+
+        if d.regressed() {
+            status.push("regressed".to_string());
+        }
+
+        if !d.changed_counts.is_empty() {
+            let counts: Vec<String> = d
+                .changed_counts
+                .iter()
+                .map(|(name, b, c)| format!("{} {} -> {}", name, b, c))
+                .collect();
+
+            status.push(format!("counts changed: {}", counts.join(", ")));
+        }
+
+        if status.is_empty() {
+            status.push("ok".to_string());
+        }
+
+        writeln!(
+            out,
+            "| {} | {} | {} | {:+.2}% | {:.2}% | {} |",
+            d.name,
+            format_ns(d.baseline_ns as i64),
+            current,
+            d.percent,
+            d.threshold,
+            status.join("; ")
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{Config, HostInfo};
+    use crate::run::StatsMetrics;
+
+    fn record(variant: &str, syscalls: bool, samples_us: &[u64]) -> Record {
+        let config = Config {
+            syscalls,
+            bufsize: 128,
+            conns: 256,
+            conns_max: 256,
+            slab_capacity: 256,
+            requests: 1,
+            protocol: crate::run::Protocol::Line,
+            work: 0,
+            work_ns: 0,
+            iterations: samples_us.len(),
+        };
+
+        let host = HostInfo {
+            hostname: String::new(),
+            os: String::new(),
+            arch: String::new(),
+            cpus: 1,
+        };
+
+        let samples: Vec<Duration> = samples_us
+            .iter()
+            .map(|&us| Duration::from_micros(us))
+            .collect();
+
+        Record::new(
+            variant,
+            config,
+            host,
+            StatsMetrics::default(),
+            None,
+            &samples,
+        )
+    }
+
+    #[test]
+    fn test_best_of() {
+        let records = [
+            record("manual", false, &[30, 32]),
+            record("nonbox", false, &[90]),
+            record("manual", false, &[26, 28]),
+            record("manual", true, &[1000]),
+        ];
+
+        let best = best_of(&records).unwrap();
+
+        assert_eq!(
+            best.iter()
+                .map(|b| (b.name.as_str(), b.ns, b.runs))
+                .collect::<Vec<_>>(),
+            [
+                ("manual", 27_000, 2),
+                ("nonbox", 90_000, 1),
+                ("manual+syscalls", 1_000_000, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let records = [
+            record("manual", true, &[1024]),
+            record("nonbox", true, &[1280]),
+        ];
+
+        let mut best = best_of(&records).unwrap();
+
+        let c = compare(&best, "manual+syscalls", "nonbox+syscalls").unwrap();
+        assert_eq!(c.ratio, 0.8);
+        assert!((c.percent - -20.0).abs() < 1e-9);
+        assert_eq!(c.per_request_ns, -1000);
+
+        assert!(compare(&best, "manual+syscalls", "box+syscalls").is_none());
+
+        // 1ms of work per request
+        add_work(&mut best, 1_000_000);
+
+        let c = compare(&best, "manual+work+syscalls", "nonbox+work+syscalls").unwrap();
+        assert!(c.ratio > 0.99 && c.ratio < 1.0);
+        assert_eq!(c.per_request_ns, -1000);
+    }
+
+    #[test]
+    fn test_add_work_measured() {
+        let mut measured = record("manual", true, &[2000]);
+        measured.config.work_ns = 1000;
+
+        let records = [record("manual", true, &[1024]), measured];
+
+        let mut best = best_of(&records).unwrap();
+
+        add_work(&mut best, 1_000_000);
+
+        // the measured run is kept, and is not estimated again
+        assert_eq!(
+            best.iter()
+                .map(|b| (b.name.as_str(), b.ns))
+                .collect::<Vec<_>>(),
+            [
+                ("manual+syscalls", 1_024_000),
+                ("manual+work+syscalls", 2_000_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_pairs() {
+        let pairs = default_pairs();
+
+        for (i, (a, b)) in pairs.iter().enumerate() {
+            assert_ne!(a, b);
+
+            // no duplicates, in either direction
+            for (c, d) in &pairs[(i + 1)..] {
+                assert!((a, b) != (c, d) && (a, b) != (d, c), "{}/{}", a, b);
+            }
+        }
+
+        assert!(pairs.contains(&(
+            "manual+work+syscalls".to_string(),
+            "nonbox+work+syscalls".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_render() {
+        let records = [
+            record("manual", false, &[26]),
+            record("nonbox", false, &[52]),
+        ];
+        let best = best_of(&records).unwrap();
+        let comparisons = [compare(&best, "nonbox", "manual").unwrap()];
+
+        assert_eq!(
+            render(&best, &comparisons),
+            "| variant | time | runs |\n\
+             |---|---|---|\n\
+             | manual | 26µs | 1 |\n\
+             | nonbox | 52µs | 1 |\n\
+             \n\
+             | comparison | ratio | difference | per request |\n\
+             |---|---|---|---|\n\
+             | nonbox / manual | 2.0000 | +100.00% | 101ns |\n"
+        );
+    }
+
+    #[test]
+    fn test_check_baseline() {
+        let baseline = [
+            record("manual", false, &[100]),
+            record("nonbox", false, &[100]),
+            record("box", false, &[100]),
+        ];
+
+        let mut changed = record("box", false, &[101]);
+        changed.stats = crate::run::run_manual(
+            crate::run::BenchConfig::DEFAULT,
+            false,
+            crate::run::Scenario::DEFAULT,
+            |r| r(),
+        );
+
+        let current = [
+            record("manual", false, &[104]),
+            record("nonbox", false, &[104]),
+            changed,
+        ];
+
+        let thresholds = Thresholds {
+            default: 5.0,
+            per_variant: vec![("nonbox".to_string(), 2.0)],
+        };
+
+        let diffs = check_baseline(&baseline, &current, &thresholds).unwrap();
+
+        assert_eq!(diffs.len(), 3);
+
+        assert_eq!(diffs[0].name, "manual");
+        assert!(!diffs[0].failed());
+
+        assert_eq!(diffs[1].name, "nonbox");
+        assert!(diffs[1].regressed());
+
+        assert_eq!(diffs[2].name, "box");
+        assert!(!diffs[2].regressed());
+        assert!(diffs[2].failed());
+        assert_eq!(diffs[2].changed_counts[0], ("register", 0, 257));
+
+        // variants missing from the current runs fail
+        let diffs = check_baseline(&baseline, &current[..1], &thresholds).unwrap();
+        assert_eq!(diffs[1].current_ns, None);
+        assert!(diffs[1].failed());
+
+        let out = render_baseline(&diffs);
+        assert!(out.contains("| manual | 100µs | 104µs | +4.00% | 5.00% | ok |"));
+        assert!(out.contains("| nonbox | 100µs | - | +0.00% | 2.00% | missing |"));
+
+        // so do variants run with a different config
+        let mut other = record("manual", false, &[50]);
+        other.config.conns_max = 4;
+
+        let diffs = check_baseline(&baseline[..1], &[other], &thresholds).unwrap();
+        assert!(diffs[0].config_changed);
+        assert!(!diffs[0].regressed());
+        assert!(diffs[0].failed());
+        assert!(render_baseline(&diffs).contains("| config changed |"));
+    }
+
+    #[test]
+    fn test_config_mismatch() {
+        let mut other = record("manual", false, &[26]);
+        other.config.requests = 8;
+
+        // more iterations are still the same benchmark
+        assert!(best_of(&[
+            record("manual", false, &[30]),
+            record("manual", false, &[26, 28])
+        ])
+        .is_ok());
+
+        assert_eq!(
+            best_of(&[record("manual", false, &[30]), other]),
+            Err(ConfigMismatch {
+                name: "manual".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,379 @@
+// machine-readable benchmark results. each record describes one run of a
+// variant: its configuration, the host it ran on, its counts, and the time
+// taken by each iteration
+
+use crate::run::{LatencyMetrics, Protocol, StatsMetrics};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    pub syscalls: bool,
+    pub bufsize: usize,
+    pub conns: usize,
+    pub conns_max: usize,
+    pub slab_capacity: usize,
+    pub requests: usize,
+    pub protocol: Protocol,
+    // iterations of the work loop per request, calibrated to take about
+    // work_ns
+    pub work: u32,
+    pub work_ns: u64,
+    pub iterations: usize,
+}
+
+impl Config {
+    // requests served per iteration
+    pub fn total_requests(&self) -> u64 {
+        (self.conns as u64) * (self.requests as u64)
+    }
+
+    // whether runs with these configs measure the same thing. the number of
+    // iterations doesn't matter, and neither does the calibrated work loop
+    // count, which varies between calibrations of the same work_ns
+    pub fn is_comparable(&self, other: &Config) -> bool {
+        let key = |c: &Config| Config {
+            work: 0,
+            iterations: 0,
+            ..c.clone()
+        };
+
+        key(self) == key(other)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostInfo {
+    pub hostname: String,
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+}
+
+impl HostInfo {
+    pub fn current() -> Self {
+        Self {
+            hostname: hostname().unwrap_or_default(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+fn hostname() -> Option<String> {
+    let mut buf: [u8; 256] = [0; 256];
+
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if ret != 0 {
+        return None;
+    }
+
+    let len = buf.iter().position(|&b| b == 0)?;
+
+    String::from_utf8(buf[..len].to_vec()).ok()
+}
+
+// the name of a variant run with the given options. the suffixes always
+// come in this order, so that names can be compared
+pub fn variant_name(variant: &str, protocol: Protocol, work: bool, syscalls: bool) -> String {
+    let mut name = variant.to_string();
+
+    if protocol == Protocol::Http {
+        name.push_str("+http");
+    }
+
+    if work {
+        name.push_str("+work");
+    }
+
+    if syscalls {
+        name.push_str("+syscalls");
+    }
+
+    name
+}
+
+// the wall-clock latency of each transaction, as measured by the client of
+// the variants that have one. totals across all iterations
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Latency {
+    pub transactions: u32,
+    pub min_ns: u64,
+    pub mean_ns: u64,
+    pub max_ns: u64,
+}
+
+impl From<&LatencyMetrics> for Latency {
+    fn from(m: &LatencyMetrics) -> Self {
+        Self {
+            transactions: m.count(),
+            min_ns: m.min().as_nanos() as u64,
+            mean_ns: m.mean().as_nanos() as u64,
+            max_ns: m.max().as_nanos() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub variant: String,
+    // seconds since the unix epoch
+    pub timestamp: u64,
+    pub config: Config,
+    pub host: HostInfo,
+    pub stats: StatsMetrics,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<Latency>,
+    pub samples_ns: Vec<u64>,
+}
+
+impl Record {
+    pub fn new(
+        variant: &str,
+        config: Config,
+        host: HostInfo,
+        stats: StatsMetrics,
+        latency: Option<&LatencyMetrics>,
+        samples: &[Duration],
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        Self {
+            variant: variant.to_string(),
+            timestamp,
+            config,
+            host,
+            stats,
+            latency: latency.map(Latency::from),
+            samples_ns: samples.iter().map(|d| d.as_nanos() as u64).collect(),
+        }
+    }
+
+    // the variant name, as used by the benchmarks
+    pub fn name(&self) -> String {
+        variant_name(
+            &self.variant,
+            self.config.protocol,
+            self.config.work_ns > 0,
+            self.config.syscalls,
+        )
+    }
+
+    pub fn min_ns(&self) -> u64 {
+        self.samples_ns.iter().copied().min().unwrap_or(0)
+    }
+
+    pub fn max_ns(&self) -> u64 {
+        self.samples_ns.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn mean_ns(&self) -> u64 {
+        if self.samples_ns.is_empty() {
+            return 0;
+        }
+
+        self.samples_ns.iter().sum::<u64>() / (self.samples_ns.len() as u64)
+    }
+}
+
+pub fn write_json<W: io::Write>(mut w: W, records: &[Record]) -> Result<(), io::Error> {
+    serde_json::to_writer_pretty(&mut w, records)?;
+    writeln!(w)?;
+
+    Ok(())
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// one row per record. timing samples are summarized rather than listed
+pub fn write_csv<W: io::Write>(mut w: W, records: &[Record]) -> Result<(), io::Error> {
+    let mut header = vec![
+        "variant",
+        "timestamp",
+        "syscalls",
+        "bufsize",
+        "conns",
+        "conns_max",
+        "slab_capacity",
+        "requests",
+        "protocol",
+        "work",
+        "work_ns",
+        "iterations",
+        "hostname",
+        "os",
+        "arch",
+        "cpus",
+    ];
+
+    header.extend(
+        StatsMetrics::default()
+            .fields()
+            .iter()
+            .map(|(name, _)| *name),
+    );
+    header.extend(["min_ns", "mean_ns", "max_ns"]);
+    header.extend(["latency_min_ns", "latency_mean_ns", "latency_max_ns"]);
+
+    writeln!(w, "{}", header.join(","))?;
+
+    for r in records {
+        let mut row = vec![
+            csv_field(&r.variant),
+            r.timestamp.to_string(),
+            r.config.syscalls.to_string(),
+            r.config.bufsize.to_string(),
+            r.config.conns.to_string(),
+            r.config.conns_max.to_string(),
+            r.config.slab_capacity.to_string(),
+            r.config.requests.to_string(),
+            format!("{:?}", r.config.protocol).to_lowercase(),
+            r.config.work.to_string(),
+            r.config.work_ns.to_string(),
+            r.config.iterations.to_string(),
+            csv_field(&r.host.hostname),
+            csv_field(&r.host.os),
+            csv_field(&r.host.arch),
+            r.host.cpus.to_string(),
+        ];
+
+        row.extend(r.stats.fields().iter().map(|(_, v)| v.to_string()));
+        row.extend([r.min_ns(), r.mean_ns(), r.max_ns()].map(|v| v.to_string()));
+
+        // empty for the variants that don't measure latency
+        match &r.latency {
+            Some(l) => row.extend([l.min_ns, l.mean_ns, l.max_ns].map(|v| v.to_string())),
+            None => row.extend([String::new(), String::new(), String::new()]),
+        }
+
+        writeln!(w, "{}", row.join(","))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> Record {
+        let config = Config {
+            syscalls: true,
+            bufsize: 128,
+            conns: 256,
+            conns_max: 256,
+            slab_capacity: 256,
+            requests: 1,
+            protocol: Protocol::Line,
+            work: 0,
+            work_ns: 0,
+            iterations: 3,
+        };
+
+        let host = HostInfo {
+            hostname: "bench,1".to_string(),
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            cpus: 4,
+        };
+
+        let samples = [
+            Duration::from_micros(3),
+            Duration::from_micros(1),
+            Duration::from_micros(2),
+        ];
+
+        Record::new(
+            "nonbox",
+            config,
+            host,
+            StatsMetrics::default(),
+            None,
+            &samples,
+        )
+    }
+
+    #[test]
+    fn test_record() {
+        let r = record();
+
+        assert_eq!(r.name(), "nonbox+syscalls");
+
+        let mut r = r;
+        r.config.protocol = Protocol::Http;
+        assert_eq!(r.name(), "nonbox+http+syscalls");
+
+        r.config.work_ns = 10_000;
+        assert_eq!(r.name(), "nonbox+http+work+syscalls");
+        assert_eq!(r.min_ns(), 1000);
+        assert_eq!(r.mean_ns(), 2000);
+        assert_eq!(r.max_ns(), 3000);
+    }
+
+    #[test]
+    fn test_comparable() {
+        let a = record().config;
+
+        let mut b = a.clone();
+        b.iterations = 1000;
+        b.work = 7;
+        assert!(a.is_comparable(&b));
+
+        b.conns_max = 4;
+        assert!(!a.is_comparable(&b));
+    }
+
+    #[test]
+    fn test_json() {
+        let mut with_latency = record();
+        with_latency.latency = Some(Latency {
+            transactions: 256,
+            min_ns: 10,
+            mean_ns: 20,
+            max_ns: 30,
+        });
+
+        let records = vec![record(), with_latency];
+
+        let mut out = Vec::new();
+        write_json(&mut out, &records).unwrap();
+
+        let parsed: Vec<Record> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(parsed, records);
+    }
+
+    #[test]
+    fn test_csv() {
+        let records = vec![record()];
+
+        let mut out = Vec::new();
+        write_csv(&mut out, &records).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("variant,timestamp,syscalls,bufsize,"));
+        assert!(lines[0].ends_with(
+            ",alloc_bytes,min_ns,mean_ns,max_ns,latency_min_ns,latency_mean_ns,latency_max_ns"
+        ));
+        assert_eq!(
+            lines[1],
+            format!(
+                "nonbox,{},true,128,256,256,256,1,line,0,0,3,\"bench,1\",linux,x86_64,4,0,0,0,0,0,0,0,0,0,0,1000,2000,3000,,,",
+                records[0].timestamp
+            )
+        );
+    }
+}
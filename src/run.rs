@@ -1,21 +1,152 @@
-use crate::executor::{ArgExecutor, ArgSpawner, BoxExecutor, BoxRcExecutor, BoxSpawner};
+use crate::alloc;
+use crate::alloc::AllocMetrics;
+use crate::asyncio::{read, read_until, write_all, AsyncRead, AsyncWrite};
+use crate::channel;
+use crate::epoll::{
+    AsyncFdListener, AsyncFdStream, BlockingClient, Driver, Epoll, EpollReactor, FdListener,
+    FdStream, TcpClient, UnixClient,
+};
+use crate::executor::{
+    ArgExecutor, ArgSpawner, BoxExecutor, BoxRcExecutor, BoxSpawner, MtExecutor,
+};
 use crate::fakeio;
 use crate::fakeio::{FakeListener, FakeStream, Poll, READABLE, WRITABLE};
 use crate::future::{AsyncFakeListener, AsyncFakeStream, FakeReactor, FakeReactorRef};
+use crate::http;
 use crate::list;
+use crate::registration::ReactorRef;
+use crate::semaphore::{Permit, Semaphore, SyncSemaphore};
+use crate::syncfuture::{SyncFakeListener, SyncFakeReactor};
 use crate::waker::{ArcWakerFactory, CheckedRcWakerFactory, RcWakerFactory};
+use serde::{Deserialize, Serialize};
 use slab::Slab;
 use std::cell::RefCell;
 use std::fmt;
+use std::future::Future;
+use std::hint;
 use std::io;
 use std::io::{Read, Write};
+use std::pin::Pin;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::task;
+use std::task::Context;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub use crate::epoll::LatencyMetrics;
+pub use crate::fakeio::{Scenario, Step};
 
-pub const CONNS_MAX: usize = 256;
 pub const SMALL_BUFSIZE: usize = 128;
 pub const LARGE_BUFSIZE: usize = 16_384;
+pub const CONN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// chunk size of the executors with growable task storage. small enough that
+// the default number of connections spans many chunks
+pub const TASKS_CHUNK_SIZE: usize = 16;
+
+// the protocol spoken by the server. the fake i/o variants should be run
+// with the matching scenario
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    // each request is a line, which is echoed back
+    Line,
+
+    // each request is an HTTP/1.1 request, and its body is echoed back in
+    // the response
+    Http,
+}
+
+impl Protocol {
+    pub fn scenario(self) -> Scenario {
+        match self {
+            Self::Line => Scenario::DEFAULT,
+            Self::Http => Scenario::HTTP,
+        }
+    }
+
+    // if the first len bytes of buf are a complete request, replaces it with
+    // the response and returns the size of the response
+    fn respond(self, buf: &mut [u8], len: usize) -> Result<Option<usize>, io::Error> {
+        match self {
+            Self::Line => Ok(buf[..len].contains(&b'\n').then_some(len)),
+            Self::Http => match http::parse_request(&buf[..len])? {
+                Some(req) => Ok(Some(http::write_response(buf, &req)?)),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+// simulated application work, done once per request: iterations rounds of
+// an FNV-1a hash. the result is passed through black_box so the loop can't
+// be optimized away
+pub fn work(iterations: u32) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+
+    for i in 0..hint::black_box(iterations) {
+        h ^= u64::from(i);
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    hint::black_box(h)
+}
+
+// returns the number of work iterations that take about target on this
+// machine
+pub fn calibrate_work(target: Duration) -> u32 {
+    const SAMPLE: u32 = 1_000_000;
 
+    // warm up
+    work(SAMPLE);
+
+    let start = Instant::now();
+    work(SAMPLE);
+    let elapsed = start.elapsed().as_nanos().max(1);
+
+    let iterations = target.as_nanos() * u128::from(SAMPLE) / elapsed;
+
+    iterations.min(u128::from(u32::MAX)) as u32
+}
+
+// the size of a run. conns connections are accepted and served per
+// iteration, with at most conns_max of them open at a time. each connection
+// serves up to requests requests, one after another, before it is closed,
+// doing work iterations of simulated work for each.
+// executors and reactors are created with room for conns_max connections,
+// and the slabs of the manual event loops start out with slab_capacity
+// entries and grow as needed
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BenchConfig {
+    pub conns: usize,
+    pub conns_max: usize,
+    pub slab_capacity: usize,
+    pub requests: usize,
+    pub protocol: Protocol,
+    pub work: u32,
+}
+
+impl BenchConfig {
+    pub const DEFAULT: Self = Self {
+        conns: 256,
+        conns_max: 256,
+        slab_capacity: 256,
+        requests: 1,
+        protocol: Protocol::Line,
+        work: 0,
+    };
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub struct StatsMetrics {
     register: u32,
     unregister: u32,
@@ -23,39 +154,106 @@ pub struct StatsMetrics {
     accept: u32,
     read: u32,
     write: u32,
+    timer: u32,
+    alloc: u32,
+    dealloc: u32,
+    alloc_bytes: u64,
+}
+
+impl StatsMetrics {
+    // names and values of all the counts, in display order
+    pub fn fields(&self) -> [(&'static str, u64); 10] {
+        [
+            ("register", self.register.into()),
+            ("unregister", self.unregister.into()),
+            ("poll", self.poll.into()),
+            ("accept", self.accept.into()),
+            ("read", self.read.into()),
+            ("write", self.write.into()),
+            ("timer", self.timer.into()),
+            ("alloc", self.alloc.into()),
+            ("dealloc", self.dealloc.into()),
+            ("alloc_bytes", self.alloc_bytes),
+        ]
+    }
+
+    fn add_allocs(&mut self, m: AllocMetrics) {
+        self.alloc += m.alloc;
+        self.dealloc += m.dealloc;
+        self.alloc_bytes += m.bytes;
+    }
 }
 
 impl fmt::Display for StatsMetrics {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "register={} unregister={} poll={} accept={} read={} write={}",
-            self.register, self.unregister, self.poll, self.accept, self.read, self.write
-        )
+            "register={} unregister={} poll={} accept={} read={} write={} timer={}",
+            self.register,
+            self.unregister,
+            self.poll,
+            self.accept,
+            self.read,
+            self.write,
+            self.timer
+        )?;
+
+        // allocations are only counted with the alloc-stats feature
+        if cfg!(feature = "alloc-stats") {
+            write!(
+                f,
+                " alloc={} dealloc={} alloc_bytes={}",
+                self.alloc, self.dealloc, self.alloc_bytes
+            )?;
+        }
+
+        Ok(())
     }
 }
 
-struct StatsData {
-    metrics: StatsMetrics,
-    pipe_fds: Option<[libc::c_int; 2]>,
+// a nonblocking pipe that is read from to simulate the cost of a syscall
+struct SyscallPipe {
+    fds: [libc::c_int; 2],
 }
 
-impl StatsData {
-    fn new(syscalls: bool) -> Self {
-        let pipe_fds = if syscalls {
-            let mut pipe_fds: [libc::c_int; 2] = [0; 2];
+impl SyscallPipe {
+    fn new() -> Self {
+        let mut fds: [libc::c_int; 2] = [0; 2];
 
-            let ret = unsafe { libc::pipe(pipe_fds.as_mut_ptr()) };
-            assert_eq!(ret, 0);
+        let ret = unsafe { libc::pipe(fds.as_mut_ptr()) };
+        assert_eq!(ret, 0);
 
-            let ret = unsafe { libc::fcntl(pipe_fds[0], libc::F_SETFL, libc::O_NONBLOCK) };
-            assert_eq!(ret, 0);
+        let ret = unsafe { libc::fcntl(fds[0], libc::F_SETFL, libc::O_NONBLOCK) };
+        assert_eq!(ret, 0);
 
-            Some(pipe_fds)
-        } else {
-            None
-        };
+        Self { fds }
+    }
+
+    fn call(&self) {
+        let mut dest: [u8; 1] = [0; 1];
+
+        let ret = unsafe { libc::read(self.fds[0], dest.as_mut_ptr() as *mut libc::c_void, 1) };
+        assert_eq!(ret, -1);
+    }
+}
+
+impl Drop for SyscallPipe {
+    fn drop(&mut self) {
+        let ret = unsafe { libc::close(self.fds[0]) };
+        assert_eq!(ret, 0);
 
+        let ret = unsafe { libc::close(self.fds[1]) };
+        assert_eq!(ret, 0);
+    }
+}
+
+struct StatsData {
+    metrics: StatsMetrics,
+    pipe: Option<SyscallPipe>,
+}
+
+impl StatsData {
+    fn new(syscalls: bool) -> Self {
         Self {
             metrics: StatsMetrics {
                 register: 0,
@@ -64,29 +262,18 @@ impl StatsData {
                 accept: 0,
                 read: 0,
                 write: 0,
+                timer: 0,
+                alloc: 0,
+                dealloc: 0,
+                alloc_bytes: 0,
             },
-            pipe_fds,
+            pipe: syscalls.then(SyscallPipe::new),
         }
     }
 
     fn do_call(&mut self) {
-        if let Some(fds) = &self.pipe_fds {
-            let mut dest: [u8; 1] = [0; 1];
-
-            let ret = unsafe { libc::read(fds[0], dest.as_mut_ptr() as *mut libc::c_void, 1) };
-            assert_eq!(ret, -1);
-        }
-    }
-}
-
-impl Drop for StatsData {
-    fn drop(&mut self) {
-        if let Some(fds) = &self.pipe_fds {
-            let ret = unsafe { libc::close(fds[0]) };
-            assert_eq!(ret, 0);
-
-            let ret = unsafe { libc::close(fds[1]) };
-            assert_eq!(ret, 0);
+        if let Some(pipe) = &self.pipe {
+            pipe.call();
         }
     }
 }
@@ -105,13 +292,23 @@ impl Stats {
     fn get(&self) -> StatsMetrics {
         self.data.borrow().metrics
     }
+
+    // runs f, adding the heap allocations it made to the metrics
+    fn count_allocs<F: FnOnce()>(&self, f: F) {
+        let m = alloc::count(f);
+
+        self.data.borrow_mut().metrics.add_allocs(m);
+    }
 }
 
 impl fakeio::Stats for Stats {
     fn inc(&self, t: fakeio::StatsType) {
         let data = &mut *self.data.borrow_mut();
 
-        data.do_call();
+        // timers are managed in userspace, so they don't cost a syscall
+        if !matches!(t, fakeio::StatsType::Timer) {
+            data.do_call();
+        }
 
         match t {
             fakeio::StatsType::Register => data.metrics.register += 1,
@@ -120,6 +317,7 @@ impl fakeio::Stats for Stats {
             fakeio::StatsType::Accept => data.metrics.accept += 1,
             fakeio::StatsType::Read => data.metrics.read += 1,
             fakeio::StatsType::Write => data.metrics.write += 1,
+            fakeio::StatsType::Timer => data.metrics.timer += 1,
         }
     }
 }
@@ -136,40 +334,185 @@ impl fakeio::Stats for Rc<Stats> {
     }
 }
 
-impl<'s> FakeReactorRef<&'s Stats> for &FakeReactor<&'s Stats> {
+// thread-safe stats, for the multi-threaded variant
+pub struct SyncStats {
+    register: AtomicU32,
+    unregister: AtomicU32,
+    poll: AtomicU32,
+    accept: AtomicU32,
+    read: AtomicU32,
+    write: AtomicU32,
+    timer: AtomicU32,
+    alloc: AtomicU32,
+    dealloc: AtomicU32,
+    alloc_bytes: AtomicU64,
+    pipe: Option<SyscallPipe>,
+}
+
+impl SyncStats {
+    pub fn new(syscalls: bool) -> Self {
+        Self {
+            register: AtomicU32::new(0),
+            unregister: AtomicU32::new(0),
+            poll: AtomicU32::new(0),
+            accept: AtomicU32::new(0),
+            read: AtomicU32::new(0),
+            write: AtomicU32::new(0),
+            timer: AtomicU32::new(0),
+            alloc: AtomicU32::new(0),
+            dealloc: AtomicU32::new(0),
+            alloc_bytes: AtomicU64::new(0),
+            pipe: syscalls.then(SyscallPipe::new),
+        }
+    }
+
+    fn get(&self) -> StatsMetrics {
+        StatsMetrics {
+            register: self.register.load(Ordering::Relaxed),
+            unregister: self.unregister.load(Ordering::Relaxed),
+            poll: self.poll.load(Ordering::Relaxed),
+            accept: self.accept.load(Ordering::Relaxed),
+            read: self.read.load(Ordering::Relaxed),
+            write: self.write.load(Ordering::Relaxed),
+            timer: self.timer.load(Ordering::Relaxed),
+            alloc: self.alloc.load(Ordering::Relaxed),
+            dealloc: self.dealloc.load(Ordering::Relaxed),
+            alloc_bytes: self.alloc_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    // only allocations made by the calling thread are counted. MtExecutor
+    // runs its other workers on threads of their own, and any task can run
+    // on any worker, so the counts miss an unpredictable share of the
+    // allocations. the mt variant lists them in varying_counts for that
+    // reason
+    fn count_allocs<F: FnOnce()>(&self, f: F) {
+        let m = alloc::count(f);
+
+        self.alloc.fetch_add(m.alloc, Ordering::Relaxed);
+        self.dealloc.fetch_add(m.dealloc, Ordering::Relaxed);
+        self.alloc_bytes.fetch_add(m.bytes, Ordering::Relaxed);
+    }
+}
+
+impl fakeio::Stats for SyncStats {
+    fn inc(&self, t: fakeio::StatsType) {
+        if let Some(pipe) = &self.pipe {
+            if !matches!(t, fakeio::StatsType::Timer) {
+                pipe.call();
+            }
+        }
+
+        let counter = match t {
+            fakeio::StatsType::Register => &self.register,
+            fakeio::StatsType::Unregister => &self.unregister,
+            fakeio::StatsType::Poll => &self.poll,
+            fakeio::StatsType::Accept => &self.accept,
+            fakeio::StatsType::Read => &self.read,
+            fakeio::StatsType::Write => &self.write,
+            fakeio::StatsType::Timer => &self.timer,
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl fakeio::Stats for Arc<SyncStats> {
+    fn inc(&self, t: fakeio::StatsType) {
+        SyncStats::inc(self.as_ref(), t)
+    }
+}
+
+impl<'s> ReactorRef for &FakeReactor<&'s Stats> {
+    type Reactor = FakeReactor<&'s Stats>;
+
+    #[allow(clippy::explicit_auto_deref)]
     fn get<'a>(&'a self) -> &'a FakeReactor<&'s Stats> {
         *self
     }
 }
 
-impl FakeReactorRef<Rc<Stats>> for Rc<FakeReactor<Rc<Stats>>> {
+impl ReactorRef for Rc<FakeReactor<Rc<Stats>>> {
+    type Reactor = FakeReactor<Rc<Stats>>;
+
     fn get(&self) -> &FakeReactor<Rc<Stats>> {
         self.as_ref()
     }
 }
 
+impl ReactorRef for Arc<SyncFakeReactor<Arc<SyncStats>>> {
+    type Reactor = SyncFakeReactor<Arc<SyncStats>>;
+
+    fn get(&self) -> &SyncFakeReactor<Arc<SyncStats>> {
+        self.as_ref()
+    }
+}
+
+impl<'s> ReactorRef for &EpollReactor<&'s Stats> {
+    type Reactor = EpollReactor<&'s Stats>;
+
+    fn get<'a>(&'a self) -> &'a EpollReactor<&'s Stats> {
+        self
+    }
+}
+
+impl ReactorRef for Rc<EpollReactor<Rc<Stats>>> {
+    type Reactor = EpollReactor<Rc<Stats>>;
+
+    fn get(&self) -> &EpollReactor<Rc<Stats>> {
+        self.as_ref()
+    }
+}
+
+// the peer going away ends a connection, but it isn't a server error
+fn is_disconnect(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe
+    )
+}
+
 enum ConnectionState {
     ReceivingRequest,
     SendingResponse,
 }
 
-struct Connection<T>
+struct Connection<S>
 where
-    T: fakeio::Stats,
+    S: Read + Write,
 {
-    stream: FakeStream<T>,
+    stream: S,
     can_read: bool,
     can_write: bool,
     state: ConnectionState,
     buf: [u8; SMALL_BUFSIZE],
     buf_len: usize,
     sent: usize,
+    requests_left: usize,
+    protocol: Protocol,
+    work: u32,
 }
 
-impl<T> Connection<T>
+impl<S> Connection<S>
 where
-    T: fakeio::Stats,
+    S: Read + Write,
 {
+    fn new(stream: S, config: BenchConfig) -> Self {
+        Self {
+            stream,
+            can_read: true,
+            can_write: true,
+            state: ConnectionState::ReceivingRequest,
+            buf: [0; SMALL_BUFSIZE],
+            buf_len: 0,
+            sent: 0,
+            requests_left: config.requests,
+            protocol: config.protocol,
+            work: config.work,
+        }
+    }
+
+    // returns true when the connection is finished
     fn process(&mut self) -> bool {
         loop {
             match self.state {
@@ -180,13 +523,27 @@ where
                             self.can_read = false;
                             return false;
                         }
+                        Err(e) if is_disconnect(&e) => return true,
                         Err(_) => unreachable!(),
                     };
 
+                    // closed before the request was complete
+                    if size == 0 {
+                        return true;
+                    }
+
                     self.buf_len += size;
 
-                    if (&self.buf[..self.buf_len]).contains(&b'\n') {
-                        self.state = ConnectionState::SendingResponse;
+                    match self.protocol.respond(&mut self.buf, self.buf_len) {
+                        Ok(Some(size)) => {
+                            work(self.work);
+
+                            self.buf_len = size;
+                            self.state = ConnectionState::SendingResponse;
+                        }
+                        Ok(None) => {}
+                        // malformed request
+                        Err(_) => return true,
                     }
                 }
                 ConnectionState::SendingResponse => {
@@ -196,13 +553,25 @@ where
                             self.can_write = false;
                             return false;
                         }
+                        Err(e) if is_disconnect(&e) => return true,
                         Err(_) => unreachable!(),
                     };
 
                     self.sent += size;
 
                     if self.sent >= self.buf_len {
-                        return true;
+                        self.requests_left -= 1;
+
+                        if self.requests_left == 0 {
+                            return true;
+                        }
+
+                        // the peer only sends the next request after
+                        // receiving the response, so nothing of it has been
+                        // read yet
+                        self.state = ConnectionState::ReceivingRequest;
+                        self.buf_len = 0;
+                        self.sent = 0;
                     }
                 }
             }
@@ -211,20 +580,24 @@ where
 }
 
 pub struct RunManual<'s> {
+    config: BenchConfig,
     stats: &'s Stats,
+    scenario: Scenario,
     poll: Poll<&'s Stats>,
     events: Slab<(usize, u8)>,
-    conns: Slab<list::Node<Connection<&'s Stats>>>,
+    conns: Slab<list::Node<Connection<FakeStream<&'s Stats>>>>,
 }
 
 impl<'s> RunManual<'s> {
-    pub fn new(stats: &'s Stats) -> Self {
-        let poll = Poll::new(CONNS_MAX + 1, stats);
-        let events = Slab::with_capacity(CONNS_MAX + 1);
-        let conns = Slab::with_capacity(CONNS_MAX);
+    pub fn new(config: BenchConfig, stats: &'s Stats, scenario: Scenario) -> Self {
+        let poll = Poll::new(config.slab_capacity + 1, stats);
+        let events = Slab::with_capacity(config.slab_capacity + 1);
+        let conns = Slab::with_capacity(config.slab_capacity);
 
         Self {
+            config,
             stats,
+            scenario,
             poll,
             events,
             conns,
@@ -232,35 +605,28 @@ impl<'s> RunManual<'s> {
     }
 
     pub fn run(&mut self) {
+        let config = self.config;
         let poll = &mut self.poll;
         let events = &mut self.events;
         let conns = &mut self.conns;
 
         let mut needs_process = list::List::default();
 
-        let listener = FakeListener::new(self.stats);
+        let listener = FakeListener::new(self.stats, self.scenario);
 
         poll.register(&listener, 0, READABLE);
 
         let mut can_accept = true;
 
-        let mut accept_left = CONNS_MAX;
+        let mut accept_left = config.conns;
 
         loop {
-            while accept_left > 0 && can_accept {
+            while accept_left > 0 && can_accept && conns.len() < config.conns_max {
                 match listener.accept() {
                     Ok(stream) => {
                         accept_left -= 1;
 
-                        let key = conns.insert(list::Node::new(Connection {
-                            stream,
-                            can_read: true,
-                            can_write: true,
-                            state: ConnectionState::ReceivingRequest,
-                            buf: [0; 128],
-                            buf_len: 0,
-                            sent: 0,
-                        }));
+                        let key = conns.insert(list::Node::new(Connection::new(stream, config)));
 
                         let c = &mut conns[key].value;
 
@@ -288,7 +654,7 @@ impl<'s> RunManual<'s> {
                 next = needs_process.head;
             }
 
-            if accept_left == 0 && conns.len() == 0 {
+            if accept_left == 0 && conns.is_empty() {
                 break;
             }
 
@@ -326,418 +692,3699 @@ impl<'s> RunManual<'s> {
     }
 }
 
-async fn listen<'r, 's: 'r>(
-    spawner: &'r ArgSpawner<AsyncInvoke<'r, 's>>,
-    reactor: &'r FakeReactor<&'s Stats>,
-    stats: &'s Stats,
-) -> Result<(), io::Error> {
-    let listener = AsyncFakeListener::new(reactor, stats);
-
-    for _ in 0..CONNS_MAX {
-        let stream = listener.accept().await?;
+// an event handler of the callback variant. handlers are registered with the
+// event loop under a token, and are called through a trait object when the
+// token has events
+trait Handler<'s> {
+    // readiness holds the events received since the last call. returns true
+    // when the handler is finished, after which it is removed
+    fn handle(&mut self, cx: &mut HandlerContext<'_, 's>, readiness: u8) -> bool;
+}
 
-        spawner.spawn(AsyncInvoke::Connection(stream)).unwrap();
-    }
+struct HandlerEntry<'s> {
+    // none while the handler is being called
+    handler: Option<Box<dyn Handler<'s> + 's>>,
+    readiness: u8,
+}
 
-    Ok(())
+struct HandlerContext<'a, 's> {
+    config: BenchConfig,
+    poll: &'a Poll<&'s Stats>,
+    handlers: &'a mut Slab<list::Node<HandlerEntry<'s>>>,
+    needs_process: &'a mut list::List,
 }
 
-pub async fn listen_box<const N: usize>(
-    spawner: &BoxSpawner<'_>,
-    reactor: Rc<FakeReactor<Rc<Stats>>>,
-    stats: Rc<Stats>,
-) -> Result<(), io::Error> {
-    let listener = AsyncFakeListener::new(reactor, stats);
+impl<'s> HandlerContext<'_, 's> {
+    // the token of the next handler to be added, for registering its i/o
+    // object before adding it
+    fn next_key(&self) -> usize {
+        self.handlers.vacant_key()
+    }
 
-    for _ in 0..CONNS_MAX {
-        let stream = listener.accept().await?;
+    // adds a handler, to be called with the given readiness without waiting
+    // for events
+    fn add(&mut self, handler: Box<dyn Handler<'s> + 's>, readiness: u8) -> usize {
+        let key = self.handlers.insert(list::Node::new(HandlerEntry {
+            handler: Some(handler),
+            readiness,
+        }));
 
-        spawner
-            .spawn(async { connection_box::<N>(stream).await.unwrap() })
-            .unwrap();
+        self.needs_process.push_back(self.handlers, key);
+
+        key
     }
+}
 
-    Ok(())
+struct ListenerHandler<'s> {
+    listener: FakeListener<&'s Stats>,
+    accept_left: usize,
 }
 
-pub async fn listen_box_callerbox<const N: usize>(
-    spawner: &BoxSpawner<'_>,
-    reactor: Rc<FakeReactor<Rc<Stats>>>,
-    stats: Rc<Stats>,
-) -> Result<(), io::Error> {
-    let listener = AsyncFakeListener::new(reactor, stats);
+impl<'s> Handler<'s> for ListenerHandler<'s> {
+    fn handle(&mut self, cx: &mut HandlerContext<'_, 's>, readiness: u8) -> bool {
+        if readiness & READABLE == 0 {
+            return false;
+        }
 
-    for _ in 0..CONNS_MAX {
-        let stream = listener.accept().await?;
+        // every handler other than this one is a connection
+        while self.accept_left > 0 && cx.handlers.len() <= cx.config.conns_max {
+            match self.listener.accept() {
+                Ok(stream) => {
+                    self.accept_left -= 1;
 
-        spawner
-            .spawn_boxed(Box::pin(async {
-                connection_box::<N>(stream).await.unwrap()
-            }))
-            .unwrap();
-    }
+                    cx.poll
+                        .register(&stream, cx.next_key(), READABLE | WRITABLE);
 
-    Ok(())
-}
+                    let c = Connection::new(stream, cx.config);
 
-pub async fn listen_rc(
-    executor: Rc<BoxRcExecutor>,
-    reactor: Rc<FakeReactor<Rc<Stats>>>,
-    stats: Rc<Stats>,
-) -> Result<(), io::Error> {
-    let listener = AsyncFakeListener::new(reactor, stats);
+                    cx.add(Box::new(ConnectionHandler { c }), READABLE | WRITABLE);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return false,
+                _ => unreachable!(),
+            }
+        }
 
-    for _ in 0..CONNS_MAX {
-        let stream = listener.accept().await?;
+        if self.accept_left == 0 {
+            cx.poll.unregister(&self.listener);
 
-        executor
-            .spawn(async { connection_box::<SMALL_BUFSIZE>(stream).await.unwrap() })
-            .unwrap();
+            return true;
+        }
+
+        false
     }
+}
 
-    Ok(())
+struct ConnectionHandler<'s> {
+    c: Connection<FakeStream<&'s Stats>>,
 }
 
-async fn connection<'r, 's, const N: usize>(
-    mut stream: AsyncFakeStream<&'s Stats, &'r FakeReactor<&'s Stats>>,
-) -> Result<(), io::Error> {
-    let mut buf = [0; N];
-    let mut buf_len = 0;
+impl<'s> Handler<'s> for ConnectionHandler<'s> {
+    fn handle(&mut self, cx: &mut HandlerContext<'_, 's>, readiness: u8) -> bool {
+        if readiness & READABLE != 0 {
+            self.c.can_read = true;
+        }
 
-    while !(&buf[..buf_len]).contains(&b'\n') {
-        let size = stream.read(&mut buf[buf_len..]).await?;
-        buf_len += size;
-    }
+        if readiness & WRITABLE != 0 {
+            self.c.can_write = true;
+        }
+
+        if self.c.process() {
+            cx.poll.unregister(&self.c.stream);
 
-    let mut sent = 0;
+            return true;
+        }
 
-    while sent < buf_len {
-        let size = stream.write(&buf[sent..buf_len]).await?;
-        sent += size;
+        false
     }
+}
 
-    Ok(())
+// like RunManual, but the connections and the listener are handlers called
+// by a generic event loop, rather than being processed inline
+pub struct RunCallback<'s> {
+    config: BenchConfig,
+    stats: &'s Stats,
+    scenario: Scenario,
+    poll: Poll<&'s Stats>,
+    events: Slab<(usize, u8)>,
+    handlers: Slab<list::Node<HandlerEntry<'s>>>,
 }
 
-async fn connection_box<const N: usize>(
-    mut stream: AsyncFakeStream<Rc<Stats>, Rc<FakeReactor<Rc<Stats>>>>,
-) -> Result<(), io::Error> {
-    let mut buf = [0; N];
-    let mut buf_len = 0;
+impl<'s> RunCallback<'s> {
+    pub fn new(config: BenchConfig, stats: &'s Stats, scenario: Scenario) -> Self {
+        let poll = Poll::new(config.slab_capacity + 1, stats);
+        let events = Slab::with_capacity(config.slab_capacity + 1);
+        let handlers = Slab::with_capacity(config.slab_capacity + 1);
 
-    while !(&buf[..buf_len]).contains(&b'\n') {
-        let size = stream.read(&mut buf[buf_len..]).await?;
-        buf_len += size;
+        Self {
+            config,
+            stats,
+            scenario,
+            poll,
+            events,
+            handlers,
+        }
     }
 
-    let mut sent = 0;
+    pub fn run(&mut self) {
+        let poll = &self.poll;
+        let events = &mut self.events;
+
+        let mut needs_process = list::List::default();
 
-    while sent < buf_len {
-        let size = stream.write(&buf[sent..buf_len]).await?;
-        sent += size;
-    }
+        let mut cx = HandlerContext {
+            config: self.config,
+            poll,
+            handlers: &mut self.handlers,
+            needs_process: &mut needs_process,
+        };
 
-    Ok(())
-}
+        let listener = FakeListener::new(self.stats, self.scenario);
 
-pub enum AsyncInvoke<'r, 's> {
-    Listen,
-    Connection(AsyncFakeStream<&'s Stats, &'r FakeReactor<&'s Stats>>),
-}
+        poll.register(&listener, cx.next_key(), READABLE);
 
-pub async fn server_task<'r, 's: 'r, const N: usize>(
-    spawner: &'r ArgSpawner<AsyncInvoke<'r, 's>>,
-    reactor: &'r FakeReactor<&'s Stats>,
-    stats: &'s Stats,
-    invoke: AsyncInvoke<'r, 's>,
-) {
-    match invoke {
-        AsyncInvoke::Listen => listen(spawner, reactor, stats).await.unwrap(),
-        AsyncInvoke::Connection(stream) => connection::<N>(stream).await.unwrap(),
-    }
-}
+        let l = ListenerHandler {
+            listener,
+            accept_left: self.config.conns,
+        };
 
-pub fn run_manual<R>(syscalls: bool, mut run_fn: R) -> StatsMetrics
-where
-    R: FnMut(&mut dyn FnMut()),
-{
-    let stats = Stats::new(syscalls);
-    let mut r = RunManual::new(&stats);
+        cx.add(Box::new(l), READABLE);
 
-    run_fn(&mut || r.run());
+        loop {
+            while let Some(key) = cx.needs_process.pop_front(cx.handlers) {
+                let entry = &mut cx.handlers[key].value;
 
-    stats.get()
-}
+                let mut handler = entry.handler.take().unwrap();
+                let readiness = entry.readiness;
+                entry.readiness = 0;
 
-pub fn run_nonbox<R>(syscalls: bool, mut run_fn: R) -> StatsMetrics
-where
-    R: FnMut(&mut dyn FnMut()),
-{
-    let stats = Stats::new(syscalls);
-    let reactor = FakeReactor::new(CONNS_MAX + 1, &stats);
-    let spawner = ArgSpawner::new();
-    let executor = ArgExecutor::new(CONNS_MAX + 1, |invoke, dest| {
-        dest.write(server_task::<SMALL_BUFSIZE>(
-            &spawner, &reactor, &stats, invoke,
-        ));
-    });
+                if handler.handle(&mut cx, readiness) {
+                    cx.handlers.remove(key);
+                } else {
+                    cx.handlers[key].value.handler = Some(handler);
+                }
+            }
 
-    executor.set_spawner(&spawner);
+            if cx.handlers.is_empty() {
+                break;
+            }
 
-    run_fn(&mut || {
-        spawner.spawn(AsyncInvoke::Listen).unwrap();
-        executor.run(|| reactor.poll());
-    });
+            poll.poll(events);
 
-    stats.get()
+            for (_, &(key, readiness)) in events.iter() {
+                cx.handlers[key].value.readiness |= readiness;
+
+                cx.needs_process.remove(cx.handlers, key);
+                cx.needs_process.push_back(cx.handlers, key);
+            }
+        }
+    }
+}
+
+pub struct RunManualEpoll<'a, 's, D> {
+    config: BenchConfig,
+    poll: Epoll<&'s Stats>,
+    listener: &'a FdListener<&'s Stats>,
+    client: &'a D,
+    events: Slab<(usize, u8)>,
+    conns: Slab<list::Node<Connection<FdStream<&'s Stats>>>>,
 }
 
-pub fn run_callerbox<R>(syscalls: bool, mut run_fn: R) -> StatsMetrics
+impl<'a, 's, D> RunManualEpoll<'a, 's, D>
 where
-    R: FnMut(&mut dyn FnMut()),
+    D: Driver,
 {
-    let stats = Stats::new(syscalls);
-    let reactor = FakeReactor::new(CONNS_MAX + 1, &stats);
-    let spawner = ArgSpawner::new();
-    let executor = ArgExecutor::new(CONNS_MAX + 1, |invoke, dest| {
-        dest.write(Box::pin(server_task::<SMALL_BUFSIZE>(
-            &spawner, &reactor, &stats, invoke,
-        )));
-    });
-
-    executor.set_spawner(&spawner);
+    pub fn new(
+        config: BenchConfig,
+        stats: &'s Stats,
+        listener: &'a FdListener<&'s Stats>,
+        client: &'a D,
+    ) -> Self {
+        let poll = Epoll::new(config.conns_max + 1, stats);
+        let events = Slab::with_capacity(config.slab_capacity + 1);
+        let conns = Slab::with_capacity(config.slab_capacity);
 
-    run_fn(&mut || {
-        spawner.spawn(AsyncInvoke::Listen).unwrap();
-        executor.run(|| reactor.poll());
-    });
+        Self {
+            config,
+            poll,
+            listener,
+            client,
+            events,
+            conns,
+        }
+    }
+
+    pub fn run(&mut self) {
+        let config = self.config;
+        let poll = &mut self.poll;
+        let listener = self.listener;
+        let client = self.client;
+        let events = &mut self.events;
+        let conns = &mut self.conns;
+
+        let mut needs_process = list::List::default();
+
+        client.start(config.conns, config.requests);
+
+        poll.register(listener, 0, READABLE);
+
+        let mut can_accept = true;
+
+        let mut accept_left = config.conns;
+
+        loop {
+            while accept_left > 0 && can_accept && conns.len() < config.conns_max {
+                match listener.accept() {
+                    Ok(stream) => {
+                        accept_left -= 1;
+
+                        let key = conns.insert(list::Node::new(Connection::new(stream, config)));
+
+                        let c = &mut conns[key].value;
+
+                        poll.register(&c.stream, key + 1, READABLE | WRITABLE);
+
+                        needs_process.push_back(conns, key);
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => can_accept = false,
+                    _ => unreachable!(),
+                }
+            }
+
+            let mut next = needs_process.head;
+
+            while let Some(key) = next {
+                needs_process.remove(conns, key);
+
+                let c = &mut conns[key].value;
+                if c.process() {
+                    poll.unregister(&c.stream);
+
+                    conns.remove(key);
+                }
+
+                next = needs_process.head;
+            }
+
+            if accept_left == 0 && conns.is_empty() {
+                break;
+            }
+
+            let block = client.step();
+
+            poll.poll(events, block).unwrap();
+
+            for (_, &(key, state)) in events.iter() {
+                if key == 0 {
+                    can_accept = true;
+                } else {
+                    let key = key - 1;
+
+                    let c = &mut conns[key].value;
+
+                    let mut do_process = false;
+
+                    if state & READABLE != 0 {
+                        c.can_read = true;
+                        do_process = true;
+                    }
+
+                    if state & WRITABLE != 0 {
+                        c.can_write = true;
+                        do_process = true;
+                    }
+
+                    if do_process {
+                        needs_process.remove(conns, key);
+                        needs_process.push_back(conns, key);
+                    }
+                }
+            }
+        }
+
+        poll.unregister(listener);
+
+        client.finish();
+    }
+}
+
+// listeners only need to limit how many connections are open at once if
+// there can be more connections than conns_max. otherwise the limit is never
+// reached, and taking permits would only add overhead
+fn limit_conns(config: BenchConfig) -> bool {
+    config.conns > config.conns_max
+}
+
+async fn take_permit(permits: Option<&Semaphore>) -> Option<Permit<'_>> {
+    match permits {
+        Some(permits) => Some(permits.acquire().await),
+        None => None,
+    }
+}
+
+// if permits is set, a permit is taken before each accept and held by the
+// connection task, so the server never has more connections open than
+// there are permits, regardless of what the client does. if the executor
+// is out of room anyway, such as when permits aren't used, then spawning
+// fails with an error rather than a panic. the other listeners do the same
+async fn listen<'r, 's: 'r>(
+    spawner: &'r ArgSpawner<AsyncInvoke<'r, 's>>,
+    reactor: &'r FakeReactor<&'s Stats>,
+    stats: &'s Stats,
+    config: BenchConfig,
+    scenario: Scenario,
+    permits: Option<&'r Semaphore>,
+) -> Result<(), io::Error> {
+    let listener = AsyncFakeListener::with_scenario(reactor, stats, scenario);
+
+    for _ in 0..config.conns {
+        let permit = take_permit(permits).await;
+
+        let stream = listener.accept().await?;
+
+        spawner.spawn(AsyncInvoke::Connection(stream, permit))?;
+    }
+
+    Ok(())
+}
+
+async fn listen_epoll<'r, 's: 'r>(
+    spawner: &'r ArgSpawner<AsyncInvokeEpoll<'r, 's>>,
+    reactor: &'r EpollReactor<&'s Stats>,
+    listener: &'r FdListener<&'s Stats>,
+    config: BenchConfig,
+    permits: Option<&'r Semaphore>,
+) -> Result<(), io::Error> {
+    let listener = AsyncFdListener::new(reactor, listener.try_clone()?);
+
+    for _ in 0..config.conns {
+        let permit = take_permit(permits).await;
+
+        let stream = listener.accept().await?;
+
+        spawner.spawn(AsyncInvokeEpoll::Connection(stream, permit))?;
+    }
+
+    Ok(())
+}
+
+async fn listen_box_epoll<'a, const N: usize>(
+    spawner: &BoxSpawner<'a>,
+    reactor: Rc<EpollReactor<Rc<Stats>>>,
+    listener: FdListener<Rc<Stats>>,
+    config: BenchConfig,
+    permits: Option<&'a Semaphore>,
+) -> Result<(), io::Error> {
+    let listener = AsyncFdListener::new(reactor, listener);
+
+    for _ in 0..config.conns {
+        let permit = take_permit(permits).await;
+
+        let stream = listener.accept().await?;
+
+        spawner.spawn(async move {
+            let _permit = permit;
+
+            connection::<_, N>(stream, config).await.unwrap()
+        })?;
+    }
+
+    Ok(())
+}
+
+async fn listen_box_callerbox_epoll<'a>(
+    spawner: &BoxSpawner<'a>,
+    reactor: Rc<EpollReactor<Rc<Stats>>>,
+    listener: FdListener<Rc<Stats>>,
+    config: BenchConfig,
+    permits: Option<&'a Semaphore>,
+) -> Result<(), io::Error> {
+    let listener = AsyncFdListener::new(reactor, listener);
+
+    for _ in 0..config.conns {
+        let permit = take_permit(permits).await;
+
+        let stream = listener.accept().await?;
+
+        spawner.spawn_boxed(Box::pin(async move {
+            let _permit = permit;
+
+            connection::<_, SMALL_BUFSIZE>(stream, config)
+                .await
+                .unwrap()
+        }))?;
+    }
+
+    Ok(())
+}
+
+async fn listen_rc_epoll(
+    executor: Rc<BoxRcExecutor>,
+    reactor: Rc<EpollReactor<Rc<Stats>>>,
+    listener: FdListener<Rc<Stats>>,
+    config: BenchConfig,
+    permits: Option<Rc<Semaphore>>,
+) -> Result<(), io::Error> {
+    let listener = AsyncFdListener::new(reactor, listener);
+
+    for _ in 0..config.conns {
+        let permit = match &permits {
+            Some(permits) => Some(permits.clone().acquire_rc().await),
+            None => None,
+        };
+
+        let stream = listener.accept().await?;
+
+        executor.spawn(async move {
+            let _permit = permit;
+
+            connection::<_, SMALL_BUFSIZE>(stream, config)
+                .await
+                .unwrap()
+        })?;
+    }
+
+    Ok(())
+}
+
+pub async fn listen_box<'a, const N: usize>(
+    spawner: &BoxSpawner<'a>,
+    reactor: Rc<FakeReactor<Rc<Stats>>>,
+    stats: Rc<Stats>,
+    config: BenchConfig,
+    scenario: Scenario,
+    permits: Option<&'a Semaphore>,
+) -> Result<(), io::Error> {
+    let listener = AsyncFakeListener::with_scenario(reactor, stats, scenario);
+
+    for _ in 0..config.conns {
+        let permit = take_permit(permits).await;
+
+        let stream = listener.accept().await?;
+
+        spawner.spawn(async move {
+            let _permit = permit;
+
+            connection::<_, N>(stream, config).await.unwrap()
+        })?;
+    }
+
+    Ok(())
+}
+
+pub async fn listen_box_callerbox<'a, const N: usize>(
+    spawner: &BoxSpawner<'a>,
+    reactor: Rc<FakeReactor<Rc<Stats>>>,
+    stats: Rc<Stats>,
+    config: BenchConfig,
+    scenario: Scenario,
+    permits: Option<&'a Semaphore>,
+) -> Result<(), io::Error> {
+    let listener = AsyncFakeListener::with_scenario(reactor, stats, scenario);
+
+    for _ in 0..config.conns {
+        let permit = take_permit(permits).await;
+
+        let stream = listener.accept().await?;
+
+        spawner.spawn_boxed(Box::pin(async move {
+            let _permit = permit;
+
+            connection::<_, N>(stream, config).await.unwrap()
+        }))?;
+    }
+
+    Ok(())
+}
+
+pub async fn listen_rc(
+    executor: Rc<BoxRcExecutor>,
+    reactor: Rc<FakeReactor<Rc<Stats>>>,
+    stats: Rc<Stats>,
+    config: BenchConfig,
+    scenario: Scenario,
+    permits: Option<Rc<Semaphore>>,
+) -> Result<(), io::Error> {
+    let listener = AsyncFakeListener::with_scenario(reactor, stats, scenario);
+
+    for _ in 0..config.conns {
+        let permit = match &permits {
+            Some(permits) => Some(permits.clone().acquire_rc().await),
+            None => None,
+        };
+
+        let stream = listener.accept().await?;
+
+        executor.spawn(async move {
+            let _permit = permit;
+
+            connection::<_, SMALL_BUFSIZE>(stream, config)
+                .await
+                .unwrap()
+        })?;
+    }
+
+    Ok(())
+}
+
+pub async fn listen_mt(
+    executor: Arc<MtExecutor>,
+    reactor: Arc<SyncFakeReactor<Arc<SyncStats>>>,
+    stats: Arc<SyncStats>,
+    config: BenchConfig,
+    scenario: Scenario,
+    permits: Option<Arc<SyncSemaphore>>,
+) -> Result<(), io::Error> {
+    let mut listener = SyncFakeListener::new(reactor, stats, scenario);
+
+    for _ in 0..config.conns {
+        let permit = match &permits {
+            Some(permits) => Some(permits.clone().acquire_arc().await),
+            None => None,
+        };
+
+        let stream = listener.accept().await?;
+
+        executor.spawn(async move {
+            let _permit = permit;
+
+            connection::<_, SMALL_BUFSIZE>(stream, config)
+                .await
+                .unwrap()
+        })?;
+    }
+
+    Ok(())
+}
+
+// reads until buf holds a complete request, and replaces it with the
+// response. resolves to the size of the response, or none if the peer closed
+// the connection first or the request was malformed
+async fn read_request<S>(
+    stream: &mut S,
+    buf: &mut [u8],
+    protocol: Protocol,
+) -> Result<Option<usize>, io::Error>
+where
+    S: AsyncRead,
+{
+    match protocol {
+        // read_until only stops before the end of the request at eof, or
+        // when buf is full
+        Protocol::Line => {
+            let size = read_until(stream, b'\n', buf).await?;
+
+            Ok(buf[..size].contains(&b'\n').then_some(size))
+        }
+        Protocol::Http => {
+            let mut len = 0;
+
+            loop {
+                let size = read(stream, &mut buf[len..]).await?;
+
+                if size == 0 {
+                    return Ok(None);
+                }
+
+                len += size;
+
+                match protocol.respond(buf, len) {
+                    Ok(Some(size)) => return Ok(Some(size)),
+                    Ok(None) => {}
+                    Err(_) => return Ok(None),
+                }
+            }
+        }
+    }
+}
+
+async fn connection<S, const N: usize>(mut stream: S, config: BenchConfig) -> Result<(), io::Error>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let mut buf = [0; N];
+
+    for _ in 0..config.requests {
+        let size = match read_request(&mut stream, &mut buf, config.protocol).await {
+            Ok(Some(size)) => size,
+            Ok(None) => return Ok(()),
+            Err(e) if is_disconnect(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        work(config.work);
+
+        match write_all(&mut stream, &buf[..size]).await {
+            Ok(()) => {}
+            Err(e) if is_disconnect(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+async fn connection_timeout<'s, const N: usize>(
+    reactor: &FakeReactor<&'s Stats>,
+    mut stream: AsyncFakeStream<&'s Stats, &FakeReactor<&'s Stats>>,
+    config: BenchConfig,
+) -> Result<(), io::Error> {
+    let mut buf = [0; N];
+
+    for _ in 0..config.requests {
+        let size = match reactor
+            .timeout(
+                read_request(&mut stream, &mut buf, config.protocol),
+                CONN_TIMEOUT,
+            )
+            .await?
+        {
+            Ok(Some(size)) => size,
+            Ok(None) => return Ok(()),
+            Err(e) if is_disconnect(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        work(config.work);
+
+        match reactor
+            .timeout(write_all(&mut stream, &buf[..size]), CONN_TIMEOUT)
+            .await?
+        {
+            Ok(()) => {}
+            Err(e) if is_disconnect(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+// like connection, but the work for each request is done by the worker
+// task. the request is handed over a channel, and the connection waits for
+// the reply
+async fn connection_pingpong<'r, 's, S, const N: usize>(
+    mut stream: S,
+    config: BenchConfig,
+    requests: WorkSender<'r, 's>,
+    replies: &'s channel::oneshot::Pool<usize>,
+) -> Result<(), io::Error>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let mut buf = [0; N];
+
+    for _ in 0..config.requests {
+        let size = match read_request(&mut stream, &mut buf, config.protocol).await {
+            Ok(Some(size)) => size,
+            Ok(None) => return Ok(()),
+            Err(e) if is_disconnect(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let (reply, response) = replies.channel();
+
+        requests.send(WorkRequest { size, reply }).await?;
+
+        let size = response.await?;
+
+        match write_all(&mut stream, &buf[..size]).await {
+            Ok(()) => {}
+            Err(e) if is_disconnect(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+// does the work of each request it receives, until all senders are gone
+async fn worker(mut requests: channel::mpsc::Receiver<'_, WorkRequest<'_>>, config: BenchConfig) {
+    while let Some(req) = requests.recv().await {
+        work(config.work);
+
+        // the connection may have gone away in the meantime
+        let _ = req.reply.send(req.size);
+    }
+}
+
+// what connection does, written by hand as a state machine rather than
+// generated from an async fn, the same way Connection does it for the
+// manual variant
+struct ConnectionFuture<S, const N: usize> {
+    stream: S,
+    state: ConnectionState,
+    buf: [u8; N],
+    buf_len: usize,
+    sent: usize,
+    requests_left: usize,
+    protocol: Protocol,
+    work: u32,
+}
+
+impl<S, const N: usize> ConnectionFuture<S, N> {
+    fn new(stream: S, config: BenchConfig) -> Self {
+        Self {
+            stream,
+            state: ConnectionState::ReceivingRequest,
+            buf: [0; N],
+            buf_len: 0,
+            sent: 0,
+            requests_left: config.requests,
+            protocol: config.protocol,
+            work: config.work,
+        }
+    }
+}
+
+impl<S, const N: usize> Future for ConnectionFuture<S, N>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = Result<(), io::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> task::Poll<Self::Output> {
+        let f = &mut *self;
+
+        loop {
+            match f.state {
+                ConnectionState::ReceivingRequest => {
+                    // the request doesn't fit. read_until gives up here too
+                    if f.buf_len == N {
+                        return task::Poll::Ready(Ok(()));
+                    }
+
+                    let size = match f.stream.poll_read(cx, &mut f.buf[f.buf_len..]) {
+                        task::Poll::Ready(Ok(size)) => size,
+                        task::Poll::Ready(Err(e)) if is_disconnect(&e) => {
+                            return task::Poll::Ready(Ok(()))
+                        }
+                        task::Poll::Ready(Err(e)) => return task::Poll::Ready(Err(e)),
+                        task::Poll::Pending => return task::Poll::Pending,
+                    };
+
+                    // closed before the request was complete
+                    if size == 0 {
+                        return task::Poll::Ready(Ok(()));
+                    }
+
+                    f.buf_len += size;
+
+                    match f.protocol.respond(&mut f.buf, f.buf_len) {
+                        Ok(Some(size)) => {
+                            work(f.work);
+
+                            f.buf_len = size;
+                            f.state = ConnectionState::SendingResponse;
+                        }
+                        Ok(None) => {}
+                        // malformed request
+                        Err(_) => return task::Poll::Ready(Ok(())),
+                    }
+                }
+                ConnectionState::SendingResponse => {
+                    let size = match f.stream.poll_write(cx, &f.buf[f.sent..f.buf_len]) {
+                        task::Poll::Ready(Ok(size)) => size,
+                        task::Poll::Ready(Err(e)) if is_disconnect(&e) => {
+                            return task::Poll::Ready(Ok(()))
+                        }
+                        task::Poll::Ready(Err(e)) => return task::Poll::Ready(Err(e)),
+                        task::Poll::Pending => return task::Poll::Pending,
+                    };
+
+                    if size == 0 {
+                        return task::Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero)));
+                    }
+
+                    f.sent += size;
+
+                    if f.sent >= f.buf_len {
+                        f.requests_left -= 1;
+
+                        if f.requests_left == 0 {
+                            return task::Poll::Ready(Ok(()));
+                        }
+
+                        f.state = ConnectionState::ReceivingRequest;
+                        f.buf_len = 0;
+                        f.sent = 0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub enum AsyncInvoke<'r, 's> {
+    Listen(Option<&'r Semaphore>),
+    Connection(
+        AsyncFakeStream<&'s Stats, &'r FakeReactor<&'s Stats>>,
+        Option<Permit<'r>>,
+    ),
+}
+
+pub async fn server_task<'r, 's: 'r, const N: usize>(
+    spawner: &'r ArgSpawner<AsyncInvoke<'r, 's>>,
+    reactor: &'r FakeReactor<&'s Stats>,
+    stats: &'s Stats,
+    config: BenchConfig,
+    scenario: Scenario,
+    invoke: AsyncInvoke<'r, 's>,
+) {
+    match invoke {
+        AsyncInvoke::Listen(permits) => listen(spawner, reactor, stats, config, scenario, permits)
+            .await
+            .unwrap(),
+        AsyncInvoke::Connection(stream, _permit) => {
+            connection::<_, N>(stream, config).await.unwrap()
+        }
+    }
+}
+
+pub async fn server_task_timeout<'r, 's: 'r, const N: usize>(
+    spawner: &'r ArgSpawner<AsyncInvoke<'r, 's>>,
+    reactor: &'r FakeReactor<&'s Stats>,
+    stats: &'s Stats,
+    config: BenchConfig,
+    scenario: Scenario,
+    invoke: AsyncInvoke<'r, 's>,
+) {
+    match invoke {
+        AsyncInvoke::Listen(permits) => listen(spawner, reactor, stats, config, scenario, permits)
+            .await
+            .unwrap(),
+        AsyncInvoke::Connection(stream, _permit) => {
+            connection_timeout::<N>(reactor, stream, config)
+                .await
+                .unwrap()
+        }
+    }
+}
+
+// the task future of the handwritten variant. only connections are written
+// by hand. the listen task is the same async fn used by the other variants
+enum HandwrittenTask<'r, L, S, const N: usize> {
+    Listen(L),
+    Connection(ConnectionFuture<S, N>, Option<Permit<'r>>),
+}
+
+impl<L, S, const N: usize> Future for HandwrittenTask<'_, L, S, N>
+where
+    L: Future<Output = Result<(), io::Error>>,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> task::Poll<Self::Output> {
+        // SAFETY: the futures are structurally pinned and never moved out of
+        // self
+        let ret = match unsafe { self.get_unchecked_mut() } {
+            Self::Listen(fut) => unsafe { Pin::new_unchecked(fut) }.poll(cx),
+            Self::Connection(fut, permit) => {
+                let ret = Pin::new(fut).poll(cx);
+
+                // release the permit as soon as the connection is done
+                if ret.is_ready() {
+                    permit.take();
+                }
+
+                ret
+            }
+        };
+
+        ret.map(|r| r.unwrap())
+    }
+}
+
+// a request handed from a connection task to the worker task. the reply is
+// the size of the response to write. replies are allocated from a pool that
+// lives as long as the stats
+struct WorkRequest<'s> {
+    size: usize,
+    reply: channel::oneshot::Sender<'s, usize>,
+}
+
+type WorkSender<'r, 's> = channel::mpsc::Sender<'r, WorkRequest<'s>>;
+
+enum PingPongInvoke<'r, 's> {
+    Listen(WorkSender<'r, 's>, Option<&'r Semaphore>),
+    Connection(
+        AsyncFakeStream<&'s Stats, &'r FakeReactor<&'s Stats>>,
+        WorkSender<'r, 's>,
+        Option<Permit<'r>>,
+    ),
+    Worker(channel::mpsc::Receiver<'r, WorkRequest<'s>>),
+}
+
+async fn listen_pingpong<'r, 's: 'r>(
+    spawner: &'r ArgSpawner<PingPongInvoke<'r, 's>>,
+    reactor: &'r FakeReactor<&'s Stats>,
+    stats: &'s Stats,
+    config: BenchConfig,
+    scenario: Scenario,
+    requests: WorkSender<'r, 's>,
+    permits: Option<&'r Semaphore>,
+) -> Result<(), io::Error> {
+    let listener = AsyncFakeListener::with_scenario(reactor, stats, scenario);
+
+    for _ in 0..config.conns {
+        let permit = take_permit(permits).await;
+
+        let stream = listener.accept().await?;
+
+        spawner.spawn(PingPongInvoke::Connection(stream, requests.clone(), permit))?;
+    }
+
+    Ok(())
+}
+
+async fn server_task_pingpong<'r, 's: 'r, const N: usize>(
+    spawner: &'r ArgSpawner<PingPongInvoke<'r, 's>>,
+    reactor: &'r FakeReactor<&'s Stats>,
+    stats: &'s Stats,
+    replies: &'s channel::oneshot::Pool<usize>,
+    config: BenchConfig,
+    scenario: Scenario,
+    invoke: PingPongInvoke<'r, 's>,
+) {
+    match invoke {
+        PingPongInvoke::Listen(requests, permits) => {
+            listen_pingpong(spawner, reactor, stats, config, scenario, requests, permits)
+                .await
+                .unwrap()
+        }
+        PingPongInvoke::Connection(stream, requests, _permit) => {
+            connection_pingpong::<_, N>(stream, config, requests, replies)
+                .await
+                .unwrap()
+        }
+        PingPongInvoke::Worker(requests) => worker(requests, config).await,
+    }
+}
+
+pub enum AsyncInvokeEpoll<'r, 's> {
+    Listen(Option<&'r Semaphore>),
+    Connection(
+        AsyncFdStream<&'s Stats, &'r EpollReactor<&'s Stats>>,
+        Option<Permit<'r>>,
+    ),
+}
+
+pub async fn server_task_epoll<'r, 's: 'r, const N: usize>(
+    spawner: &'r ArgSpawner<AsyncInvokeEpoll<'r, 's>>,
+    reactor: &'r EpollReactor<&'s Stats>,
+    listener: &'r FdListener<&'s Stats>,
+    config: BenchConfig,
+    invoke: AsyncInvokeEpoll<'r, 's>,
+) {
+    match invoke {
+        AsyncInvokeEpoll::Listen(permits) => {
+            listen_epoll(spawner, reactor, listener, config, permits)
+                .await
+                .unwrap()
+        }
+        AsyncInvokeEpoll::Connection(stream, _permit) => {
+            connection::<_, N>(stream, config).await.unwrap()
+        }
+    }
+}
+
+pub fn run_manual<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let mut r = RunManual::new(config, &stats, scenario);
+
+    run_fn(&mut || stats.count_allocs(|| r.run()));
+
+    stats.get()
+}
+
+pub fn run_callback<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let mut r = RunCallback::new(config, &stats, scenario);
+
+    run_fn(&mut || stats.count_allocs(|| r.run()));
+
+    stats.get()
+}
+
+// the clients of the epoll and tcp variants only send line requests
+fn check_client_protocol(config: BenchConfig) {
+    assert_eq!(
+        config.protocol,
+        Protocol::Line,
+        "only the fake i/o variants support other protocols"
+    );
+}
+
+// epoll variants make real syscalls, so there is no syscalls option
+pub fn run_manual_epoll<R>(config: BenchConfig, mut run_fn: R) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Stats::new(false);
+    let listener = FdListener::bind_unix(&stats).unwrap();
+    let client = UnixClient::new(&listener, config.conns_max);
+    let mut r = RunManualEpoll::new(config, &stats, &listener, &client);
+
+    run_fn(&mut || stats.count_allocs(|| r.run()));
+
+    stats.get()
+}
+
+pub fn run_nonbox<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let reactor = FakeReactor::new(config.conns_max + 1, &stats);
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = ArgSpawner::new();
+    let executor = ArgExecutor::new(config.conns_max + 1, |invoke, dest| {
+        dest.write(server_task::<SMALL_BUFSIZE>(
+            &spawner, &reactor, &stats, config, scenario, invoke,
+        ));
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            spawner
+                .spawn(AsyncInvoke::Listen(permits.as_ref()))
+                .unwrap();
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+pub fn run_nonbox_timeout<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let reactor = FakeReactor::new(config.conns_max + 1, &stats);
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = ArgSpawner::new();
+    let executor = ArgExecutor::new(config.conns_max + 1, |invoke, dest| {
+        dest.write(server_task_timeout::<SMALL_BUFSIZE>(
+            &spawner, &reactor, &stats, config, scenario, invoke,
+        ));
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            spawner
+                .spawn(AsyncInvoke::Listen(permits.as_ref()))
+                .unwrap();
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+// like run_nonbox, but the executor's task storage starts small and grows in
+// chunks. the chunks are kept between runs, so after the first run this
+// measures the cost of the chunked storage itself
+pub fn run_nonbox_growable<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let reactor = FakeReactor::new(config.conns_max + 1, &stats);
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = ArgSpawner::new();
+    let executor = ArgExecutor::growable(TASKS_CHUNK_SIZE, |invoke, dest| {
+        dest.write(server_task::<SMALL_BUFSIZE>(
+            &spawner, &reactor, &stats, config, scenario, invoke,
+        ));
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            spawner
+                .spawn(AsyncInvoke::Listen(permits.as_ref()))
+                .unwrap();
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+// like run_nonbox, but the listener takes a permit from a semaphore before
+// each accept. there is always a permit available by the time the listener
+// wants one, so this measures the cost of acquiring and releasing permits
+pub fn run_nonbox_semaphore<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let reactor = FakeReactor::new(config.conns_max + 1, &stats);
+    let permits = Semaphore::new(config.conns_max, 1);
+    let spawner = ArgSpawner::new();
+    let executor = ArgExecutor::new(config.conns_max + 1, |invoke, dest| {
+        dest.write(server_task::<SMALL_BUFSIZE>(
+            &spawner, &reactor, &stats, config, scenario, invoke,
+        ));
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            spawner.spawn(AsyncInvoke::Listen(Some(&permits))).unwrap();
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+// like run_nonbox, but connection tasks hand each request to a single
+// worker task over a channel and wait for its reply, so every request
+// involves wakeups between tasks
+pub fn run_nonbox_pingpong<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let replies = channel::oneshot::Pool::new(config.conns_max);
+    let reactor = FakeReactor::new(config.conns_max + 1, &stats);
+    let requests = channel::mpsc::Channel::new(config.conns_max, config.conns_max);
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = ArgSpawner::new();
+
+    // plus the listener and the worker
+    let executor = ArgExecutor::new(config.conns_max + 2, |invoke, dest| {
+        dest.write(server_task_pingpong::<SMALL_BUFSIZE>(
+            &spawner, &reactor, &stats, &replies, config, scenario, invoke,
+        ));
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            let (tx, rx) = requests.split();
+
+            spawner.spawn(PingPongInvoke::Worker(rx)).unwrap();
+            spawner
+                .spawn(PingPongInvoke::Listen(tx, permits.as_ref()))
+                .unwrap();
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+pub fn run_nonbox_handwritten<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let reactor = FakeReactor::new(config.conns_max + 1, &stats);
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = ArgSpawner::new();
+    let executor = ArgExecutor::new(config.conns_max + 1, |invoke, dest| {
+        dest.write(match invoke {
+            AsyncInvoke::Listen(permits) => HandwrittenTask::Listen(listen(
+                &spawner, &reactor, &stats, config, scenario, permits,
+            )),
+            AsyncInvoke::Connection(stream, permit) => HandwrittenTask::Connection(
+                ConnectionFuture::<_, SMALL_BUFSIZE>::new(stream, config),
+                permit,
+            ),
+        });
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            spawner
+                .spawn(AsyncInvoke::Listen(permits.as_ref()))
+                .unwrap();
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+fn nonbox_epoll<D, R, const N: usize>(
+    config: BenchConfig,
+    stats: &Stats,
+    listener: &FdListener<&Stats>,
+    client: &D,
+    mut run_fn: R,
+) where
+    D: Driver,
+    R: FnMut(&mut dyn FnMut()),
+{
+    let reactor = EpollReactor::new(config.conns_max + 1, stats);
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = ArgSpawner::new();
+    let executor = ArgExecutor::new(config.conns_max + 1, |invoke, dest| {
+        dest.write(server_task_epoll::<N>(
+            &spawner, &reactor, listener, config, invoke,
+        ));
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            client.start(config.conns, config.requests);
+            spawner
+                .spawn(AsyncInvokeEpoll::Listen(permits.as_ref()))
+                .unwrap();
+            executor.run(|| reactor.poll(client.step()));
+            client.finish();
+        })
+    });
+}
+
+pub fn run_nonbox_epoll<R>(config: BenchConfig, run_fn: R) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Stats::new(false);
+    let listener = FdListener::bind_unix(&stats).unwrap();
+    let client = UnixClient::new(&listener, config.conns_max);
+
+    nonbox_epoll::<_, _, SMALL_BUFSIZE>(config, &stats, &listener, &client, run_fn);
+
+    stats.get()
+}
+
+pub fn run_callerbox<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let reactor = FakeReactor::new(config.conns_max + 1, &stats);
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = ArgSpawner::new();
+    let executor = ArgExecutor::new(config.conns_max + 1, |invoke, dest| {
+        dest.write(Box::pin(server_task::<SMALL_BUFSIZE>(
+            &spawner, &reactor, &stats, config, scenario, invoke,
+        )));
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            spawner
+                .spawn(AsyncInvoke::Listen(permits.as_ref()))
+                .unwrap();
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+pub fn run_large_nonbox<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Stats::new(syscalls);
+    let reactor = FakeReactor::new(config.conns_max + 1, &stats);
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = ArgSpawner::new();
+    let executor = ArgExecutor::new(config.conns_max + 1, |invoke, dest| {
+        dest.write(server_task::<LARGE_BUFSIZE>(
+            &spawner, &reactor, &stats, config, scenario, invoke,
+        ));
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            spawner
+                .spawn(AsyncInvoke::Listen(permits.as_ref()))
+                .unwrap();
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+pub fn run_box<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Rc::new(Stats::new(syscalls));
+    let reactor = Rc::new(FakeReactor::new(config.conns_max + 1, stats.clone()));
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = BoxSpawner::new();
+    let executor = BoxExecutor::new(config.conns_max + 1);
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            {
+                let stats = stats.clone();
+                let reactor = reactor.clone();
+
+                spawner
+                    .spawn(async {
+                        listen_box::<SMALL_BUFSIZE>(
+                            &spawner,
+                            reactor,
+                            stats,
+                            config,
+                            scenario,
+                            permits.as_ref(),
+                        )
+                        .await
+                        .unwrap()
+                    })
+                    .unwrap();
+            }
+
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+// like run_box, with growable task storage as in run_nonbox_growable
+pub fn run_box_growable<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Rc::new(Stats::new(syscalls));
+    let reactor = Rc::new(FakeReactor::new(config.conns_max + 1, stats.clone()));
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = BoxSpawner::new();
+    let executor = BoxExecutor::growable(TASKS_CHUNK_SIZE);
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            {
+                let stats = stats.clone();
+                let reactor = reactor.clone();
+
+                spawner
+                    .spawn(async {
+                        listen_box::<SMALL_BUFSIZE>(
+                            &spawner,
+                            reactor,
+                            stats,
+                            config,
+                            scenario,
+                            permits.as_ref(),
+                        )
+                        .await
+                        .unwrap()
+                    })
+                    .unwrap();
+            }
+
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+pub fn run_box_callerbox<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Rc::new(Stats::new(syscalls));
+    let reactor = Rc::new(FakeReactor::new(config.conns_max + 1, stats.clone()));
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = BoxSpawner::new();
+    let executor = BoxExecutor::new(config.conns_max + 1);
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            {
+                let stats = stats.clone();
+                let reactor = reactor.clone();
+
+                spawner
+                    .spawn_boxed(Box::pin(async {
+                        listen_box_callerbox::<SMALL_BUFSIZE>(
+                            &spawner,
+                            reactor,
+                            stats,
+                            config,
+                            scenario,
+                            permits.as_ref(),
+                        )
+                        .await
+                        .unwrap()
+                    }))
+                    .unwrap();
+            }
+
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+pub fn run_large_box<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Rc::new(Stats::new(syscalls));
+    let reactor = Rc::new(FakeReactor::new(config.conns_max + 1, stats.clone()));
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = BoxSpawner::new();
+    let executor = BoxExecutor::new(config.conns_max + 1);
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            {
+                let stats = stats.clone();
+                let reactor = reactor.clone();
+
+                spawner
+                    .spawn(async {
+                        listen_box::<LARGE_BUFSIZE>(
+                            &spawner,
+                            reactor,
+                            stats,
+                            config,
+                            scenario,
+                            permits.as_ref(),
+                        )
+                        .await
+                        .unwrap()
+                    })
+                    .unwrap();
+            }
+
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+pub enum BoxRcMode {
+    RcWaker,
+    CheckedRcWaker,
+    ArcWaker,
+}
+
+pub fn run_box_rc<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mode: BoxRcMode,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Rc::new(Stats::new(syscalls));
+    let reactor = Rc::new(FakeReactor::new(config.conns_max + 1, stats.clone()));
+
+    let executor = Rc::new(match mode {
+        BoxRcMode::RcWaker => BoxRcExecutor::new(config.conns_max + 1, RcWakerFactory::default()),
+        BoxRcMode::CheckedRcWaker => {
+            BoxRcExecutor::new(config.conns_max + 1, CheckedRcWakerFactory::default())
+        }
+        BoxRcMode::ArcWaker => BoxRcExecutor::new(config.conns_max + 1, ArcWakerFactory::default()),
+    });
+
+    let permits = limit_conns(config).then(|| Rc::new(Semaphore::new(config.conns_max, 1)));
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            {
+                let stats = stats.clone();
+                let reactor = reactor.clone();
+                let executor_copy = executor.clone();
+                let permits = permits.clone();
+
+                executor
+                    .spawn(async move {
+                        listen_rc(executor_copy, reactor, stats, config, scenario, permits)
+                            .await
+                            .unwrap()
+                    })
+                    .unwrap();
+            }
+
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+// use up to 4 threads, but not more than the machine has
+fn mt_workers() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get().min(4))
+}
+
+pub fn run_box_arc_mt<R>(
+    config: BenchConfig,
+    syscalls: bool,
+    scenario: Scenario,
+    mut run_fn: R,
+) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    let stats = Arc::new(SyncStats::new(syscalls));
+    let reactor = Arc::new(SyncFakeReactor::new(config.conns_max + 1, stats.clone()));
+    let workers = mt_workers();
+
+    // a connection task releases its permit during its last poll, before
+    // the executor frees its slot, so each worker may be holding a slot
+    // that the listener has already been given a permit for
+    let executor = Arc::new(MtExecutor::new(config.conns_max + 1 + workers, workers));
+    let permits = limit_conns(config).then(|| Arc::new(SyncSemaphore::new(config.conns_max, 1)));
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            {
+                let stats = stats.clone();
+                let reactor = reactor.clone();
+                let executor_copy = executor.clone();
+                let permits = permits.clone();
+
+                executor
+                    .spawn(async move {
+                        listen_mt(executor_copy, reactor, stats, config, scenario, permits)
+                            .await
+                            .unwrap()
+                    })
+                    .unwrap();
+            }
+
+            executor.run(|| reactor.poll());
+        })
+    });
+
+    stats.get()
+}
+
+// the latency is only measured by the variants with a client thread
+pub type VariantFn = fn(
+    BenchConfig,
+    bool,
+    &mut dyn FnMut(&mut dyn FnMut()),
+) -> (StatsMetrics, Option<LatencyMetrics>);
+
+// a variant that can be selected by name, for the command line runner. the
+// function takes the config, the syscalls option, and a run_fn, and uses the
+// scenario matching the config's protocol.
+// varying_counts names the counts that may differ between runs, for
+// example because they depend on thread scheduling. real_io variants serve
+// real sockets instead of the fake i/o objects, so they ignore the syscalls
+// option and only support the line protocol
+pub struct Variant {
+    pub name: &'static str,
+    pub bufsize: usize,
+    pub varying_counts: &'static [&'static str],
+    pub real_io: bool,
+    pub run: VariantFn,
+}
+
+// the server waits on the client thread, so whether an accept or read would
+// block, and how much is ready per poll, depends on timing
+const TCP_VARYING_COUNTS: &[&str] = &["poll", "accept", "read", "alloc", "dealloc", "alloc_bytes"];
+
+fn tcp_variant(
+    (stats, latency): (StatsMetrics, LatencyMetrics),
+) -> (StatsMetrics, Option<LatencyMetrics>) {
+    (stats, Some(latency))
+}
+
+pub const VARIANTS: &[Variant] = &[
+    Variant {
+        name: "manual",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_manual(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "callback",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_callback(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "nonbox",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_nonbox(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "nonbox+timeout",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_nonbox_timeout(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "nonbox+sem",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_nonbox_semaphore(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "nonbox+pingpong",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_nonbox_pingpong(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "nonbox+grow",
+        bufsize: SMALL_BUFSIZE,
+        // the storage chunks are allocated during the first run
+        varying_counts: &["alloc", "alloc_bytes"],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_nonbox_growable(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "nonbox+handwritten",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_nonbox_handwritten(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "callerbox",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_callerbox(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "large+nonbox",
+        bufsize: LARGE_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_large_nonbox(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "box",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_box(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "box+grow",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &["alloc", "alloc_bytes"],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_box_growable(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "box+callerbox",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_box_callerbox(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "large+box",
+        bufsize: LARGE_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_large_box(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "box+rc",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_box_rc(
+                    config,
+                    syscalls,
+                    config.protocol.scenario(),
+                    BoxRcMode::RcWaker,
+                    run_fn,
+                ),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "box+chkrc",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_box_rc(
+                    config,
+                    syscalls,
+                    config.protocol.scenario(),
+                    BoxRcMode::CheckedRcWaker,
+                    run_fn,
+                ),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "box+arc",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_box_rc(
+                    config,
+                    syscalls,
+                    config.protocol.scenario(),
+                    BoxRcMode::ArcWaker,
+                    run_fn,
+                ),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "box+arc+mt",
+        bufsize: SMALL_BUFSIZE,
+        // the alloc counts only cover the worker on the calling thread. see
+        // SyncStats::count_allocs
+        varying_counts: &["poll", "alloc", "dealloc", "alloc_bytes"],
+        real_io: false,
+        run: |config, syscalls, run_fn| {
+            (
+                run_box_arc_mt(config, syscalls, config.protocol.scenario(), run_fn),
+                None,
+            )
+        },
+    },
+    Variant {
+        name: "manual+epoll",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: true,
+        run: |config, _, run_fn| (run_manual_epoll(config, run_fn), None),
+    },
+    Variant {
+        name: "nonbox+epoll",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &[],
+        real_io: true,
+        run: |config, _, run_fn| (run_nonbox_epoll(config, run_fn), None),
+    },
+    Variant {
+        name: "blocking",
+        bufsize: SMALL_BUFSIZE,
+        // state shared with the server threads may be freed by either side
+        varying_counts: &["dealloc"],
+        real_io: true,
+        run: |config, _, run_fn| (run_blocking(config, run_fn), None),
+    },
+    Variant {
+        name: "blocking+pool",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: &["dealloc"],
+        real_io: true,
+        run: |config, _, run_fn| (run_blocking_pool(config, run_fn), None),
+    },
+    Variant {
+        name: "manual+tcp",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| tcp_variant(run_manual_tcp(config, run_fn)),
+    },
+    Variant {
+        name: "nonbox+tcp",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| tcp_variant(run_nonbox_tcp(config, run_fn)),
+    },
+    Variant {
+        name: "callerbox+tcp",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| tcp_variant(run_callerbox_tcp(config, run_fn)),
+    },
+    Variant {
+        name: "large+nonbox+tcp",
+        bufsize: LARGE_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| tcp_variant(run_large_nonbox_tcp(config, run_fn)),
+    },
+    Variant {
+        name: "box+tcp",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| tcp_variant(run_box_tcp(config, run_fn)),
+    },
+    Variant {
+        name: "box+callerbox+tcp",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| tcp_variant(run_box_callerbox_tcp(config, run_fn)),
+    },
+    Variant {
+        name: "large+box+tcp",
+        bufsize: LARGE_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| tcp_variant(run_large_box_tcp(config, run_fn)),
+    },
+    Variant {
+        name: "box+rc+tcp",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| tcp_variant(run_box_rc_tcp(config, BoxRcMode::RcWaker, run_fn)),
+    },
+    Variant {
+        name: "box+chkrc+tcp",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| {
+            tcp_variant(run_box_rc_tcp(config, BoxRcMode::CheckedRcWaker, run_fn))
+        },
+    },
+    Variant {
+        name: "box+arc+tcp",
+        bufsize: SMALL_BUFSIZE,
+        varying_counts: TCP_VARYING_COUNTS,
+        real_io: true,
+        run: |config, _, run_fn| tcp_variant(run_box_rc_tcp(config, BoxRcMode::ArcWaker, run_fn)),
+    },
+];
+
+pub fn find_variant(name: &str) -> Option<&'static Variant> {
+    VARIANTS.iter().find(|v| v.name == name)
+}
+
+// the tcp variants serve loopback connections made by a client thread, with
+// up to conns_max transactions at a time. as with the epoll variants, there
+// is no syscalls option
+
+pub fn run_manual_tcp<R>(config: BenchConfig, mut run_fn: R) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Stats::new(false);
+    let listener = FdListener::bind_tcp(&stats).unwrap();
+    let client = TcpClient::new(&listener, config.conns_max);
+    let mut r = RunManualEpoll::new(config, &stats, &listener, &client);
+
+    run_fn(&mut || stats.count_allocs(|| r.run()));
+
+    (stats.get(), client.latency())
+}
+
+fn nonbox_tcp<R, const N: usize>(config: BenchConfig, run_fn: R) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Stats::new(false);
+    let listener = FdListener::bind_tcp(&stats).unwrap();
+    let client = TcpClient::new(&listener, config.conns_max);
+
+    nonbox_epoll::<_, _, N>(config, &stats, &listener, &client, run_fn);
+
+    (stats.get(), client.latency())
+}
+
+pub fn run_nonbox_tcp<R>(config: BenchConfig, run_fn: R) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    nonbox_tcp::<_, SMALL_BUFSIZE>(config, run_fn)
+}
+
+pub fn run_callerbox_tcp<R>(config: BenchConfig, mut run_fn: R) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Stats::new(false);
+    let listener = FdListener::bind_tcp(&stats).unwrap();
+    let client = TcpClient::new(&listener, config.conns_max);
+    let reactor = EpollReactor::new(config.conns_max + 1, &stats);
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = ArgSpawner::new();
+    let executor = ArgExecutor::new(config.conns_max + 1, |invoke, dest| {
+        dest.write(Box::pin(server_task_epoll::<SMALL_BUFSIZE>(
+            &spawner, &reactor, &listener, config, invoke,
+        )));
+    });
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            client.start(config.conns, config.requests);
+            spawner
+                .spawn(AsyncInvokeEpoll::Listen(permits.as_ref()))
+                .unwrap();
+            executor.run(|| reactor.poll(client.step()));
+            client.finish();
+        })
+    });
+
+    (stats.get(), client.latency())
+}
+
+pub fn run_large_nonbox_tcp<R>(config: BenchConfig, run_fn: R) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    nonbox_tcp::<_, LARGE_BUFSIZE>(config, run_fn)
+}
+
+fn box_tcp<R, const N: usize>(config: BenchConfig, mut run_fn: R) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Rc::new(Stats::new(false));
+    let listener = FdListener::bind_tcp(stats.clone()).unwrap();
+    let client = TcpClient::new(&listener, config.conns_max);
+    let reactor = Rc::new(EpollReactor::new(config.conns_max + 1, stats.clone()));
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = BoxSpawner::new();
+    let executor = BoxExecutor::new(config.conns_max + 1);
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            client.start(config.conns, config.requests);
+
+            {
+                let reactor = reactor.clone();
+                let listener = listener.try_clone().unwrap();
+
+                spawner
+                    .spawn(async {
+                        listen_box_epoll::<N>(&spawner, reactor, listener, config, permits.as_ref())
+                            .await
+                            .unwrap()
+                    })
+                    .unwrap();
+            }
+
+            executor.run(|| reactor.poll(client.step()));
+            client.finish();
+        })
+    });
+
+    (stats.get(), client.latency())
+}
+
+pub fn run_box_tcp<R>(config: BenchConfig, run_fn: R) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    box_tcp::<_, SMALL_BUFSIZE>(config, run_fn)
+}
+
+pub fn run_box_callerbox_tcp<R>(
+    config: BenchConfig,
+    mut run_fn: R,
+) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Rc::new(Stats::new(false));
+    let listener = FdListener::bind_tcp(stats.clone()).unwrap();
+    let client = TcpClient::new(&listener, config.conns_max);
+    let reactor = Rc::new(EpollReactor::new(config.conns_max + 1, stats.clone()));
+    let permits = limit_conns(config).then(|| Semaphore::new(config.conns_max, 1));
+    let spawner = BoxSpawner::new();
+    let executor = BoxExecutor::new(config.conns_max + 1);
+
+    executor.set_spawner(&spawner);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            client.start(config.conns, config.requests);
+
+            {
+                let reactor = reactor.clone();
+                let listener = listener.try_clone().unwrap();
+
+                spawner
+                    .spawn_boxed(Box::pin(async {
+                        listen_box_callerbox_epoll(
+                            &spawner,
+                            reactor,
+                            listener,
+                            config,
+                            permits.as_ref(),
+                        )
+                        .await
+                        .unwrap()
+                    }))
+                    .unwrap();
+            }
+
+            executor.run(|| reactor.poll(client.step()));
+            client.finish();
+        })
+    });
+
+    (stats.get(), client.latency())
+}
+
+pub fn run_large_box_tcp<R>(config: BenchConfig, run_fn: R) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    box_tcp::<_, LARGE_BUFSIZE>(config, run_fn)
+}
+
+pub fn run_box_rc_tcp<R>(
+    config: BenchConfig,
+    mode: BoxRcMode,
+    mut run_fn: R,
+) -> (StatsMetrics, LatencyMetrics)
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Rc::new(Stats::new(false));
+    let listener = FdListener::bind_tcp(stats.clone()).unwrap();
+    let client = TcpClient::new(&listener, config.conns_max);
+    let reactor = Rc::new(EpollReactor::new(config.conns_max + 1, stats.clone()));
+
+    let executor = Rc::new(match mode {
+        BoxRcMode::RcWaker => BoxRcExecutor::new(config.conns_max + 1, RcWakerFactory::default()),
+        BoxRcMode::CheckedRcWaker => {
+            BoxRcExecutor::new(config.conns_max + 1, CheckedRcWakerFactory::default())
+        }
+        BoxRcMode::ArcWaker => BoxRcExecutor::new(config.conns_max + 1, ArcWakerFactory::default()),
+    });
+
+    let permits = limit_conns(config).then(|| Rc::new(Semaphore::new(config.conns_max, 1)));
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            client.start(config.conns, config.requests);
+
+            {
+                let reactor = reactor.clone();
+                let listener = listener.try_clone().unwrap();
+                let executor_copy = executor.clone();
+                let permits = permits.clone();
+
+                executor
+                    .spawn(async move {
+                        listen_rc_epoll(executor_copy, reactor, listener, config, permits)
+                            .await
+                            .unwrap()
+                    })
+                    .unwrap();
+            }
+
+            executor.run(|| reactor.poll(client.step()));
+            client.finish();
+        })
+    });
+
+    (stats.get(), client.latency())
+}
+
+// the blocking variants serve unix socket connections with blocking i/o,
+// one connection per thread, for comparison with the event loop and async
+// variants. the connections are made by the calling thread, in batches of up
+// to conns_max, and the server threads use the same Connection logic as the
+// manual variants. since the streams never return WouldBlock, a single call
+// to process serves a connection to completion. there is no syscalls option,
+// and allocations made by the server threads are not counted
+
+type BlockingConnection = Connection<FdStream<Arc<SyncStats>>>;
+
+// accepts the next batch of connections, passing each to serve
+fn accept_blocking<F>(
+    config: BenchConfig,
+    listener: &FdListener<Arc<SyncStats>>,
+    client: &mut BlockingClient,
+    count: usize,
+    mut serve: F,
+) where
+    F: FnMut(BlockingConnection),
+{
+    for _ in 0..count {
+        client.connect().unwrap();
+
+        let stream = listener.accept().unwrap();
+        stream.set_nonblocking(false).unwrap();
+
+        serve(Connection::new(stream, config));
+    }
+}
+
+// spawns a thread for each connection
+pub fn run_blocking<R>(config: BenchConfig, mut run_fn: R) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Arc::new(SyncStats::new(false));
+    let listener = FdListener::bind_unix(stats.clone()).unwrap();
+    let mut client = BlockingClient::new(&listener, config.conns_max);
+    let mut threads = Vec::with_capacity(config.conns_max);
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            let mut accept_left = config.conns;
+
+            while accept_left > 0 {
+                let count = accept_left.min(config.conns_max);
+                accept_left -= count;
+
+                accept_blocking(config, &listener, &mut client, count, |mut c| {
+                    threads.push(thread::spawn(move || assert!(c.process())));
+                });
+
+                client.transact(config.requests).unwrap();
+
+                for t in threads.drain(..) {
+                    t.join().unwrap();
+                }
+            }
+        })
+    });
+
+    stats.get()
+}
+
+// passes connections to a pool of conns_max threads, which is started
+// before the run. the pool is large enough to serve a whole batch at once,
+// so the client never waits on a connection that has no thread
+pub fn run_blocking_pool<R>(config: BenchConfig, mut run_fn: R) -> StatsMetrics
+where
+    R: FnMut(&mut dyn FnMut()),
+{
+    check_client_protocol(config);
+
+    let stats = Arc::new(SyncStats::new(false));
+    let listener = FdListener::bind_unix(stats.clone()).unwrap();
+    let mut client = BlockingClient::new(&listener, config.conns_max);
+
+    let (conns_sender, conns_receiver) = mpsc::channel::<BlockingConnection>();
+    let (done_sender, done) = mpsc::channel();
+
+    let conns_receiver = Arc::new(Mutex::new(conns_receiver));
+
+    let workers: Vec<thread::JoinHandle<()>> = (0..config.conns_max)
+        .map(|_| {
+            let conns_receiver = conns_receiver.clone();
+            let done_sender = done_sender.clone();
+
+            thread::spawn(move || loop {
+                // the lock is released before serving the connection
+                let mut c = match conns_receiver.lock().unwrap().recv() {
+                    Ok(c) => c,
+                    // the sender was dropped
+                    Err(_) => break,
+                };
+
+                assert!(c.process());
+
+                // drop the connection before reporting it as done
+                drop(c);
+
+                done_sender.send(()).unwrap();
+            })
+        })
+        .collect();
+
+    run_fn(&mut || {
+        stats.count_allocs(|| {
+            let mut accept_left = config.conns;
+
+            while accept_left > 0 {
+                let count = accept_left.min(config.conns_max);
+                accept_left -= count;
+
+                accept_blocking(config, &listener, &mut client, count, |c| {
+                    conns_sender.send(c).unwrap();
+                });
+
+                client.transact(config.requests).unwrap();
+
+                for _ in 0..count {
+                    done.recv().unwrap();
+                }
+            }
+        })
+    });
+
+    // closing the channel stops the workers
+    drop(conns_sender);
+
+    for w in workers {
+        w.join().unwrap();
+    }
+
+    stats.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::JoinError;
+    use crate::future::RegisterError;
+    use crate::timer::{Clock, FakeClock};
+    use std::cell::Cell;
+
+    const CONFIG: BenchConfig = BenchConfig::DEFAULT;
+
+    const EXPECTED_STATS: StatsMetrics = StatsMetrics {
+        register: 257,
+        unregister: 257,
+        poll: 258,
+        accept: 512,
+        read: 512,
+        write: 512,
+        timer: 0,
+        alloc: 0,
+        dealloc: 0,
+        alloc_bytes: 0,
+    };
+
+    // real sockets don't alternate WouldBlock on every call. the listener
+    // would block once before the client connects, and each stream would
+    // block once before the client sends its request. this assumes the
+    // listen backlog fits all connections at once
+    const EXPECTED_EPOLL_STATS: StatsMetrics = StatsMetrics {
+        register: 257,
+        unregister: 257,
+        poll: 2,
+        accept: 257,
+        read: 512,
+        write: 256,
+        timer: 0,
+        alloc: 0,
+        dealloc: 0,
+        alloc_bytes: 0,
+    };
+
+    // drops the allocation counts, which are only collected with the
+    // alloc-stats feature and are checked separately
+    fn io_counts(m: StatsMetrics) -> StatsMetrics {
+        StatsMetrics {
+            alloc: 0,
+            dealloc: 0,
+            alloc_bytes: 0,
+            ..m
+        }
+    }
+
+    #[test]
+    fn test_manual() {
+        assert_eq!(
+            io_counts(run_manual(CONFIG, false, Scenario::DEFAULT, |r| r())),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_nonbox() {
+        assert_eq!(
+            io_counts(run_nonbox(CONFIG, false, Scenario::DEFAULT, |r| r())),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_nonbox_timeout() {
+        assert_eq!(
+            io_counts(run_nonbox_timeout(CONFIG, false, Scenario::DEFAULT, |r| r())),
+            StatsMetrics {
+                timer: 512,
+                ..EXPECTED_STATS
+            }
+        );
+    }
+
+    #[test]
+    fn test_manual_epoll() {
+        assert_eq!(
+            io_counts(run_manual_epoll(CONFIG, |r| r())),
+            EXPECTED_EPOLL_STATS
+        );
+    }
+
+    #[test]
+    fn test_nonbox_epoll() {
+        assert_eq!(
+            io_counts(run_nonbox_epoll(CONFIG, |r| r())),
+            EXPECTED_EPOLL_STATS
+        );
+    }
+
+    // timing decides how often the server sees WouldBlock, so only check the
+    // counts that don't depend on it
+    fn check_tcp((stats, latency): (StatsMetrics, LatencyMetrics)) {
+        assert_eq!(stats.register, 257);
+        assert_eq!(stats.unregister, 257);
+        assert_eq!(stats.write, 256);
+        assert!(stats.accept >= 256);
+        assert!(stats.read >= 256);
+        assert_eq!(latency.count(), 256);
+        assert!(latency.min() <= latency.mean());
+        assert!(latency.mean() <= latency.max());
+    }
+
+    #[test]
+    fn test_tcp_client_concurrent() {
+        let stats = Stats::new(false);
+        let listener = FdListener::bind_tcp(&stats).unwrap();
+        let client = TcpClient::new(&listener, 4);
+
+        client.start(4, 1);
+
+        // all of the connections are made before any of them is served
+        let mut streams = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(10);
+
+        while streams.len() < 4 {
+            match listener.accept() {
+                Ok(stream) => streams.push(stream),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    assert!(Instant::now() < deadline);
+
+                    thread::sleep(Duration::from_millis(1));
+                }
+                Err(e) => panic!("accept failed: {}", e),
+            }
+        }
+
+        for mut stream in streams {
+            stream.set_nonblocking(false).unwrap();
+
+            let mut buf = [0; 128];
+            let mut len = 0;
+
+            while !buf[..len].contains(&b'\n') {
+                len += stream.read(&mut buf[len..]).unwrap();
+            }
+
+            stream.write_all(&buf[..len]).unwrap();
+        }
+
+        client.finish();
+
+        assert_eq!(client.latency().count(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "client failed")]
+    fn test_tcp_client_failed() {
+        let stats = Stats::new(false);
+        let listener = FdListener::bind_tcp(&stats).unwrap();
+        let client = TcpClient::new(&listener, 4);
+
+        client.start(1, 1);
+
+        // close the connection without responding
+        loop {
+            match listener.accept() {
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(1))
+                }
+                Err(e) => panic!("accept failed: {}", e),
+            }
+        }
+
+        client.finish();
+    }
+
+    #[test]
+    fn test_manual_tcp() {
+        check_tcp(run_manual_tcp(CONFIG, |r| r()));
+    }
+
+    #[test]
+    fn test_nonbox_tcp() {
+        check_tcp(run_nonbox_tcp(CONFIG, |r| r()));
+    }
+
+    #[test]
+    fn test_callerbox_tcp() {
+        check_tcp(run_callerbox_tcp(CONFIG, |r| r()));
+    }
+
+    #[test]
+    fn test_large_nonbox_tcp() {
+        check_tcp(run_large_nonbox_tcp(CONFIG, |r| r()));
+    }
+
+    #[test]
+    fn test_box_tcp() {
+        check_tcp(run_box_tcp(CONFIG, |r| r()));
+    }
+
+    #[test]
+    fn test_box_callerbox_tcp() {
+        check_tcp(run_box_callerbox_tcp(CONFIG, |r| r()));
+    }
+
+    #[test]
+    fn test_large_box_tcp() {
+        check_tcp(run_large_box_tcp(CONFIG, |r| r()));
+    }
+
+    #[test]
+    fn test_box_rc_tcp() {
+        check_tcp(run_box_rc_tcp(CONFIG, BoxRcMode::RcWaker, |r| r()));
+        check_tcp(run_box_rc_tcp(CONFIG, BoxRcMode::CheckedRcWaker, |r| r()));
+        check_tcp(run_box_rc_tcp(CONFIG, BoxRcMode::ArcWaker, |r| r()));
+    }
+
+    // each connection is served by a single read and write per request
+    fn check_blocking<F>(mut run: F)
+    where
+        F: FnMut(BenchConfig) -> StatsMetrics,
+    {
+        let expected = StatsMetrics {
+            accept: 256,
+            read: 256,
+            write: 256,
+            ..StatsMetrics::default()
+        };
+
+        assert_eq!(io_counts(run(CONFIG)), expected);
+
+        // several batches, with keep-alive
+        let config = BenchConfig {
+            conns: 10,
+            conns_max: 4,
+            requests: 3,
+            ..CONFIG
+        };
+
+        let expected = StatsMetrics {
+            accept: 10,
+            read: 30,
+            write: 30,
+            ..StatsMetrics::default()
+        };
+
+        assert_eq!(io_counts(run(config)), expected);
+    }
+
+    #[test]
+    fn test_blocking() {
+        check_blocking(|config| run_blocking(config, |r| r()));
+    }
+
+    #[test]
+    fn test_blocking_pool() {
+        check_blocking(|config| run_blocking_pool(config, |r| r()));
+    }
+
+    #[test]
+    fn test_callerbox() {
+        assert_eq!(
+            io_counts(run_callerbox(CONFIG, false, Scenario::DEFAULT, |r| r())),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_large_nonbox() {
+        assert_eq!(
+            io_counts(run_large_nonbox(CONFIG, false, Scenario::DEFAULT, |r| r())),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_box() {
+        assert_eq!(
+            io_counts(run_box(CONFIG, false, Scenario::DEFAULT, |r| r())),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_box_callerbox() {
+        assert_eq!(
+            io_counts(run_box_callerbox(CONFIG, false, Scenario::DEFAULT, |r| r())),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_large_box() {
+        assert_eq!(
+            io_counts(run_large_box(CONFIG, false, Scenario::DEFAULT, |r| r())),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_box_rc() {
+        assert_eq!(
+            io_counts(run_box_rc(
+                CONFIG,
+                false,
+                Scenario::DEFAULT,
+                BoxRcMode::RcWaker,
+                |r| r()
+            )),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_box_chkrc() {
+        assert_eq!(
+            io_counts(run_box_rc(
+                CONFIG,
+                false,
+                Scenario::DEFAULT,
+                BoxRcMode::CheckedRcWaker,
+                |r| r()
+            )),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_box_arc() {
+        assert_eq!(
+            io_counts(run_box_rc(
+                CONFIG,
+                false,
+                Scenario::DEFAULT,
+                BoxRcMode::ArcWaker,
+                |r| r()
+            )),
+            EXPECTED_STATS
+        );
+    }
+
+    #[test]
+    fn test_box_arc_mt() {
+        let stats = io_counts(run_box_arc_mt(CONFIG, false, Scenario::DEFAULT, |r| r()));
+
+        // idle workers may poll concurrently, so the poll count varies
+        assert_eq!(
+            stats,
+            StatsMetrics {
+                poll: stats.poll,
+                ..EXPECTED_STATS
+            }
+        );
+        assert!(stats.poll >= EXPECTED_STATS.poll);
+    }
+
+    // runs every variant with the given config. timer counts are skipped,
+    // since only some variants use timers
+    fn check_variants(config: BenchConfig, expected: StatsMetrics) {
+        check_variants_except(config, expected, &["timer"]);
+    }
+
+    // like check_variants, but doesn't check the named counts. only the
+    // fake i/o variants are checked
+    fn check_variants_except(config: BenchConfig, expected: StatsMetrics, skip: &[&str]) {
+        for v in VARIANTS.iter().filter(|v| !v.real_io) {
+            let stats = io_counts((v.run)(config, false, &mut |r| r()).0);
+
+            for ((name, value), (_, expected)) in
+                stats.fields().iter().zip(expected.fields().iter())
+            {
+                if v.varying_counts.contains(name) {
+                    assert!(value >= expected);
+                } else if !skip.contains(name) {
+                    assert_eq!(value, expected, "{} {}", v.name, name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_variants() {
+        for v in VARIANTS {
+            assert_eq!(find_variant(v.name).unwrap().name, v.name);
+        }
+
+        assert!(find_variant("unknown").is_none());
+
+        check_variants(CONFIG, EXPECTED_STATS);
+
+        // the real i/o variants write each response with one call. only the
+        // ones with a client thread measure latency
+        for v in VARIANTS.iter().filter(|v| v.real_io) {
+            let (stats, latency) = (v.run)(CONFIG, false, &mut |r| r());
+
+            assert_eq!(stats.write, 256, "{}", v.name);
+            assert_eq!(
+                latency.map(|l| l.count()),
+                v.name.ends_with("+tcp").then_some(256),
+                "{}",
+                v.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_semaphore_limit() {
+        let stats = Stats::new(false);
+        let reactor = FakeReactor::new(CONFIG.conns_max + 1, &stats);
+        let permits = Semaphore::new(2, 1);
+        let spawner = ArgSpawner::new();
+        let executor = ArgExecutor::new(CONFIG.conns_max + 1, |invoke, dest| {
+            dest.write(server_task::<SMALL_BUFSIZE>(
+                &spawner,
+                &reactor,
+                &stats,
+                CONFIG,
+                Scenario::DEFAULT,
+                invoke,
+            ));
+        });
+
+        executor.set_spawner(&spawner);
+
+        // the client would open more connections at once than there are
+        // permits, so the listener has to wait for connections to finish
+        let exhausted = Cell::new(false);
+
+        spawner.spawn(AsyncInvoke::Listen(Some(&permits))).unwrap();
+        executor.run(|| {
+            if permits.available_permits() == 0 {
+                exhausted.set(true);
+            }
+
+            reactor.poll()
+        });
+
+        assert!(exhausted.get());
+        assert_eq!(permits.available_permits(), 2);
+
+        let stats = stats.get();
+        assert_eq!(stats.accept, EXPECTED_STATS.accept);
+        assert_eq!(stats.read, EXPECTED_STATS.read);
+        assert_eq!(stats.write, EXPECTED_STATS.write);
+    }
+
+    #[test]
+    fn test_listen_at_capacity() {
+        let stats = Rc::new(Stats::new(false));
+        let reactor = Rc::new(FakeReactor::new(CONFIG.conns_max + 1, stats.clone()));
+        let result = Cell::new(None);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(2);
+
+        executor.set_spawner(&spawner);
+
+        // without permits, the listener accepts more connections than there
+        // are task slots, and spawning fails
+        spawner
+            .spawn(async {
+                let ret = listen_box::<SMALL_BUFSIZE>(
+                    &spawner,
+                    reactor.clone(),
+                    stats.clone(),
+                    CONFIG,
+                    Scenario::DEFAULT,
+                    None,
+                )
+                .await;
+
+                result.set(Some(ret.is_err()));
+            })
+            .unwrap();
+
+        executor.run(|| reactor.poll());
+
+        assert_eq!(result.get(), Some(true));
+    }
+
+    #[test]
+    fn test_handwritten() {
+        // the hand-written connection future does the same i/o as the async
+        // fn, including when there are several requests per connection
+        for requests in [1, 3] {
+            let config = BenchConfig { requests, ..CONFIG };
+
+            let stats = run_nonbox_handwritten(config, false, Scenario::DEFAULT, |r| r());
+            assert_eq!(
+                io_counts(stats),
+                io_counts(run_nonbox(config, false, Scenario::DEFAULT, |r| r()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_config() {
+        // more connections than fit at once, and slabs that must grow
+        let config = BenchConfig {
+            conns: 1000,
+            conns_max: 4,
+            slab_capacity: 1,
+            ..CONFIG
+        };
+
+        let expected = StatsMetrics {
+            register: 1001,
+            unregister: 1001,
+            poll: 1002,
+            accept: 2000,
+            read: 2000,
+            write: 2000,
+            ..EXPECTED_STATS
+        };
+
+        check_variants(config, expected);
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        for requests in [2, 3, 8] {
+            let config = BenchConfig { requests, ..CONFIG };
+            let k = requests as u32;
+
+            // each request is read and written the same way as a single
+            // request, and takes two more polls
+            let expected = StatsMetrics {
+                poll: 256 + (2 * k),
+                read: 512 * k,
+                write: 512 * k,
+                ..EXPECTED_STATS
+            };
+
+            check_variants(config, expected);
+
+            assert_eq!(
+                io_counts(run_nonbox_timeout(config, false, Scenario::DEFAULT, |r| r())).timer,
+                512 * k
+            );
+
+            // the client sends each request after receiving the previous
+            // response, which takes one more poll
+            let expected = StatsMetrics {
+                poll: 1 + k,
+                read: 512 * k,
+                write: 256 * k,
+                ..EXPECTED_EPOLL_STATS
+            };
+
+            assert_eq!(io_counts(run_manual_epoll(config, |r| r())), expected);
+            assert_eq!(io_counts(run_nonbox_epoll(config, |r| r())), expected);
+
+            let (stats, latency) = run_nonbox_tcp(config, |r| r());
+            assert_eq!(stats.write, 256 * k);
+            assert!(stats.read >= 256 * k);
+            assert_eq!(latency.count(), 256);
+        }
+    }
+
+    #[test]
+    fn test_keep_alive_conns_max() {
+        for requests in [2, 3, 8] {
+            // only a few connections are open at once, so each poll finds
+            // less ready I/O and the requests take more polls
+            let config = BenchConfig {
+                conns_max: 4,
+                slab_capacity: 4,
+                requests,
+                ..CONFIG
+            };
+            let k = requests as u32;
+
+            let expected = StatsMetrics {
+                poll: 67 + (128 * k),
+                read: 512 * k,
+                write: 512 * k,
+                ..EXPECTED_STATS
+            };
+
+            let run = |r: &mut dyn FnMut()| r();
+
+            assert_eq!(
+                io_counts(run_nonbox(config, false, Scenario::DEFAULT, run)),
+                expected
+            );
+            assert_eq!(
+                io_counts(run_box(config, false, Scenario::DEFAULT, run)),
+                expected
+            );
+            assert_eq!(
+                io_counts(run_box_rc(
+                    config,
+                    false,
+                    Scenario::DEFAULT,
+                    BoxRcMode::RcWaker,
+                    run
+                )),
+                expected
+            );
+
+            // the poll count depends on timing, when there are several
+            // workers
+            let stats = run_box_arc_mt(config, false, Scenario::DEFAULT, run);
+            assert_eq!(
+                io_counts(StatsMetrics {
+                    poll: expected.poll,
+                    ..stats
+                }),
+                expected
+            );
+
+            let expected = StatsMetrics {
+                poll: 64 + (64 * k),
+                read: 512 * k,
+                write: 256 * k,
+                ..EXPECTED_EPOLL_STATS
+            };
+
+            assert_eq!(io_counts(run_manual_epoll(config, run)), expected);
+
+            // the listener retries accepting after each wait for a permit
+            assert_eq!(
+                io_counts(run_nonbox_epoll(config, run)),
+                StatsMetrics {
+                    accept: 320,
+                    ..expected
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_conns_max_keep_alive() {
+        // connections that make several requests each, more of them than
+        // fit at once. new connections must wait for a permit while the
+        // open ones are still being served
+        let config = BenchConfig {
+            conns: 100,
+            conns_max: 4,
+            slab_capacity: 4,
+            requests: 8,
+            ..CONFIG
+        };
+
+        // the number of polls depends on how the variant interleaves
+        // accepting with serving
+        let expected = StatsMetrics {
+            register: 101,
+            unregister: 101,
+            accept: 200,
+            read: 1600,
+            write: 1600,
+            ..EXPECTED_STATS
+        };
+
+        check_variants_except(config, expected, &["timer", "poll"]);
+
+        for stats in [
+            run_manual_epoll(config, |r| r()),
+            run_nonbox_epoll(config, |r| r()),
+        ] {
+            assert_eq!(stats.register, 101);
+            assert_eq!(stats.unregister, 101);
+            assert!(stats.accept >= 101);
+            assert_eq!(stats.read, 1600);
+            assert_eq!(stats.write, 800);
+        }
+
+        for (stats, latency) in [
+            run_manual_tcp(config, |r| r()),
+            run_nonbox_tcp(config, |r| r()),
+            run_box_tcp(config, |r| r()),
+            run_box_rc_tcp(config, BoxRcMode::RcWaker, |r| r()),
+        ] {
+            assert_eq!(stats.register, 101);
+            assert_eq!(stats.unregister, 101);
+            assert_eq!(stats.write, 800);
+            assert_eq!(latency.count(), 100);
+        }
+
+        for stats in [
+            run_blocking(config, |r| r()),
+            run_blocking_pool(config, |r| r()),
+        ] {
+            assert_eq!(stats.accept, 100);
+            assert_eq!(stats.read, 800);
+            assert_eq!(stats.write, 800);
+        }
+    }
+
+    #[cfg(feature = "alloc-stats")]
+    #[test]
+    fn test_allocs() {
+        let conns = CONFIG.conns as u32;
+
+        let stats = run_manual(CONFIG, false, Scenario::DEFAULT, |r| r());
+        assert_eq!((stats.alloc, stats.dealloc), (0, 0));
+
+        let stats = run_nonbox(CONFIG, false, Scenario::DEFAULT, |r| r());
+        assert_eq!((stats.alloc, stats.dealloc), (0, 0));
+
+        let stats = run_nonbox_handwritten(CONFIG, false, Scenario::DEFAULT, |r| r());
+        assert_eq!((stats.alloc, stats.dealloc), (0, 0));
+
+        // nor does taking permits
+        let stats = run_nonbox_semaphore(CONFIG, false, Scenario::DEFAULT, |r| r());
+        assert_eq!((stats.alloc, stats.dealloc), (0, 0));
+
+        // nor does passing requests and replies over channels
+        let stats = run_nonbox_pingpong(CONFIG, false, Scenario::DEFAULT, |r| r());
+        assert_eq!((stats.alloc, stats.dealloc), (0, 0));
+
+        // parsing http requests doesn't allocate either
+        let http = BenchConfig {
+            protocol: Protocol::Http,
+            ..CONFIG
+        };
+
+        let stats = run_manual(http, false, Scenario::HTTP, |r| r());
+        assert_eq!((stats.alloc, stats.dealloc), (0, 0));
+
+        let stats = run_nonbox(http, false, Scenario::HTTP, |r| r());
+        assert_eq!((stats.alloc, stats.dealloc), (0, 0));
+
+        // one handler per connection, plus the listener
+        let stats = run_callback(CONFIG, false, Scenario::DEFAULT, |r| r());
+        assert_eq!(stats.alloc, conns + 1);
+        assert_eq!(stats.dealloc, stats.alloc);
+
+        // one per connection, plus the listen task
+        let stats = run_box(CONFIG, false, Scenario::DEFAULT, |r| r());
+        assert_eq!(stats.alloc, conns + 1);
+        assert_eq!(stats.dealloc, stats.alloc);
+
+        // exactly one more for each added connection
+        let more = BenchConfig {
+            conns: CONFIG.conns * 2,
+            ..CONFIG
+        };
+
+        let more_stats = run_box(more, false, Scenario::DEFAULT, |r| r());
+        assert_eq!(more_stats.alloc - stats.alloc, conns);
+        assert_eq!(more_stats.dealloc, more_stats.alloc);
+
+        // the per-connection cost doesn't depend on the number of runs
+        let stats = run_box(CONFIG, false, Scenario::DEFAULT, |r| {
+            r();
+            r();
+        });
+        assert_eq!(stats.alloc, (conns + 1) * 2);
+    }
+
+    // except for poll in the multi-threaded variant
+    fn check_scenario(
+        config: BenchConfig,
+        scenario: Scenario,
+        expected: StatsMetrics,
+        timers: u32,
+    ) {
+        assert_eq!(
+            io_counts(run_manual(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_callback(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_nonbox(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_nonbox_semaphore(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_nonbox_pingpong(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_nonbox_growable(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_nonbox_handwritten(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_callerbox(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_large_nonbox(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_box(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_box_growable(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_box_callerbox(config, false, scenario, |r| r())),
+            expected
+        );
+        assert_eq!(
+            io_counts(run_large_box(config, false, scenario, |r| r())),
+            expected
+        );
 
-    stats.get()
-}
+        for mode in [
+            BoxRcMode::RcWaker,
+            BoxRcMode::CheckedRcWaker,
+            BoxRcMode::ArcWaker,
+        ] {
+            assert_eq!(
+                io_counts(run_box_rc(config, false, scenario, mode, |r| r())),
+                expected
+            );
+        }
 
-pub fn run_large_nonbox<R>(syscalls: bool, mut run_fn: R) -> StatsMetrics
-where
-    R: FnMut(&mut dyn FnMut()),
-{
-    let stats = Stats::new(syscalls);
-    let reactor = FakeReactor::new(CONNS_MAX + 1, &stats);
-    let spawner = ArgSpawner::new();
-    let executor = ArgExecutor::new(CONNS_MAX + 1, |invoke, dest| {
-        dest.write(server_task::<LARGE_BUFSIZE>(
-            &spawner, &reactor, &stats, invoke,
-        ));
-    });
+        assert_eq!(
+            io_counts(run_nonbox_timeout(config, false, scenario, |r| r())),
+            StatsMetrics {
+                timer: timers,
+                ..expected
+            }
+        );
 
-    executor.set_spawner(&spawner);
+        let stats = io_counts(run_box_arc_mt(config, false, scenario, |r| r()));
+        assert_eq!(
+            stats,
+            StatsMetrics {
+                poll: stats.poll,
+                ..expected
+            }
+        );
+    }
 
-    run_fn(&mut || {
-        spawner.spawn(AsyncInvoke::Listen).unwrap();
-        executor.run(|| reactor.poll());
-    });
+    #[test]
+    fn test_scenario_partial() {
+        // requests and responses take 3 calls each, of 5, 5, and 2 bytes
+        let expected = StatsMetrics {
+            poll: 262,
+            read: 1536,
+            write: 1536,
+            ..EXPECTED_STATS
+        };
 
-    stats.get()
-}
+        check_scenario(CONFIG, Scenario::PARTIAL, expected, 512);
+    }
 
-pub fn run_box<R>(syscalls: bool, mut run_fn: R) -> StatsMetrics
-where
-    R: FnMut(&mut dyn FnMut()),
-{
-    let stats = Rc::new(Stats::new(syscalls));
-    let reactor = Rc::new(FakeReactor::new(CONNS_MAX + 1, stats.clone()));
-    let spawner = BoxSpawner::new();
-    let executor = BoxExecutor::new(CONNS_MAX + 1);
+    #[test]
+    fn test_scenario_slow() {
+        let expected = StatsMetrics {
+            poll: 264,
+            read: 1280,
+            write: 1280,
+            ..EXPECTED_STATS
+        };
 
-    executor.set_spawner(&spawner);
+        check_scenario(CONFIG, Scenario::SLOW, expected, 512);
+    }
+
+    #[test]
+    fn test_scenario_reset() {
+        // connections end during the request, so nothing is written
+        let expected = StatsMetrics {
+            poll: 257,
+            read: 768,
+            write: 0,
+            ..EXPECTED_STATS
+        };
+
+        check_scenario(CONFIG, Scenario::RESET, expected, 256);
+    }
+
+    #[test]
+    fn test_scenario_eof() {
+        let expected = StatsMetrics {
+            poll: 257,
+            read: 768,
+            write: 0,
+            ..EXPECTED_STATS
+        };
+
+        check_scenario(CONFIG, Scenario::EOF, expected, 256);
+    }
+
+    #[test]
+    fn test_work() {
+        assert_eq!(work(10), work(10));
+        assert_ne!(work(10), work(11));
+
+        assert_eq!(calibrate_work(Duration::ZERO), 0);
+        assert!(calibrate_work(Duration::from_millis(1)) > 0);
+
+        // work doesn't change the counts
+        check_variants(
+            BenchConfig {
+                work: 100,
+                ..CONFIG
+            },
+            EXPECTED_STATS,
+        );
+    }
+
+    #[test]
+    fn test_http() {
+        let config = BenchConfig {
+            protocol: Protocol::Http,
+            ..CONFIG
+        };
+
+        // the request and the response each take a single call
+        check_variants(config, EXPECTED_STATS);
+
+        check_variants(
+            BenchConfig {
+                requests: 2,
+                ..config
+            },
+            StatsMetrics {
+                poll: 260,
+                read: 1024,
+                write: 1024,
+                ..EXPECTED_STATS
+            },
+        );
+
+        // the 71 byte request and the 50 byte response are transferred 5
+        // bytes at a time, over 15 and 10 calls
+        let expected = StatsMetrics {
+            poll: 281,
+            read: 7680,
+            write: 5120,
+            ..EXPECTED_STATS
+        };
+
+        let scenario = Scenario::HTTP.with_steps(&[Step::WouldBlock(1), Step::Ready(5)]);
+
+        check_scenario(config, scenario, expected, 512);
+    }
+
+    // accepts a connection and then reads from it forever
+    async fn read_forever<T, R>(reactor: R, stats: T) -> Result<(), io::Error>
+    where
+        T: fakeio::Stats + Clone,
+        R: FakeReactorRef<T>,
+    {
+        let listener = AsyncFakeListener::new(reactor, stats);
+        let mut stream = listener.accept().await?;
+        let mut buf = [0; SMALL_BUFSIZE];
+
+        loop {
+            stream.read(&mut buf).await?;
+        }
+    }
+
+    async fn accept_one<T, R>(reactor: R, stats: T) -> Result<(), io::Error>
+    where
+        T: fakeio::Stats + Clone,
+        R: FakeReactorRef<T>,
+    {
+        let listener = AsyncFakeListener::new(reactor, stats);
+        listener.accept().await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_box_abort() {
+        let stats = Rc::new(Stats::new(false));
+        let reactor = Rc::new(FakeReactor::new(4, stats.clone()));
+        let cancelled = Cell::new(false);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(2);
+
+        executor.set_spawner(&spawner);
+
+        let handle = spawner
+            .spawn(read_forever(reactor.clone(), stats.clone()))
+            .unwrap();
 
-    run_fn(&mut || {
         {
-            let stats = stats.clone();
             let reactor = reactor.clone();
+            let stats = stats.clone();
+            let cancelled = &cancelled;
 
             spawner
-                .spawn(async {
-                    listen_box::<SMALL_BUFSIZE>(&spawner, reactor, stats)
-                        .await
-                        .unwrap()
+                .spawn(async move {
+                    accept_one(reactor, stats).await.unwrap();
+
+                    handle.abort();
+
+                    cancelled.set(matches!(handle.await, Err(JoinError::Cancelled)));
                 })
                 .unwrap();
         }
 
         executor.run(|| reactor.poll());
-    });
 
-    stats.get()
-}
+        assert!(cancelled.get());
 
-pub fn run_box_callerbox<R>(syscalls: bool, mut run_fn: R) -> StatsMetrics
-where
-    R: FnMut(&mut dyn FnMut()),
-{
-    let stats = Rc::new(Stats::new(syscalls));
-    let reactor = Rc::new(FakeReactor::new(CONNS_MAX + 1, stats.clone()));
-    let spawner = BoxSpawner::new();
-    let executor = BoxExecutor::new(CONNS_MAX + 1);
+        // the aborted task's registrations were dropped
+        assert_eq!(Rc::strong_count(&reactor), 1);
 
-    executor.set_spawner(&spawner);
+        let m = stats.get();
+        assert_eq!(m.register, 4);
+        assert_eq!(m.unregister, 4);
+    }
+
+    #[test]
+    fn test_box_rc_abort() {
+        let stats = Rc::new(Stats::new(false));
+        let reactor = Rc::new(FakeReactor::new(4, stats.clone()));
+        let executor = BoxRcExecutor::new(2, ArcWakerFactory::default());
+
+        let handle = executor
+            .spawn({
+                let reactor = reactor.clone();
+                let stats = stats.clone();
+
+                async { read_forever(reactor, stats).await.unwrap() }
+            })
+            .unwrap();
+
+        executor
+            .spawn({
+                let reactor = reactor.clone();
+                let stats = stats.clone();
+
+                async move {
+                    accept_one(reactor, stats).await.unwrap();
+
+                    handle.abort();
+                }
+            })
+            .unwrap();
+
+        executor.run(|| reactor.poll());
+
+        // the aborted task's registrations were dropped
+        assert_eq!(Rc::strong_count(&reactor), 1);
+
+        let m = stats.get();
+        assert_eq!(m.register, 4);
+        assert_eq!(m.unregister, 4);
+    }
+
+    #[test]
+    fn test_sleep() {
+        let clock = Rc::new(FakeClock::new());
+        let start = clock.now();
+        let stats = Rc::new(Stats::new(false));
+        let reactor = Rc::new(FakeReactor::with_clock(1, stats.clone(), clock.clone()));
+        let woke_at = Cell::new(None);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(1);
+
+        executor.set_spawner(&spawner);
 
-    run_fn(&mut || {
         {
-            let stats = stats.clone();
             let reactor = reactor.clone();
+            let clock = clock.clone();
+            let woke_at = &woke_at;
 
             spawner
-                .spawn_boxed(Box::pin(async {
-                    listen_box_callerbox::<SMALL_BUFSIZE>(&spawner, reactor, stats)
-                        .await
-                        .unwrap()
-                }))
+                .spawn(async move {
+                    reactor.sleep(Duration::from_millis(100)).await.unwrap();
+
+                    woke_at.set(Some(clock.now() - start));
+                })
                 .unwrap();
         }
 
-        executor.run(|| reactor.poll());
-    });
+        executor.run(|| {
+            clock.advance(Duration::from_millis(10));
+            reactor.poll()
+        });
 
-    stats.get()
-}
+        assert_eq!(woke_at.get(), Some(Duration::from_millis(100)));
+        assert_eq!(stats.get().timer, 1);
+    }
 
-pub fn run_large_box<R>(syscalls: bool, mut run_fn: R) -> StatsMetrics
-where
-    R: FnMut(&mut dyn FnMut()),
-{
-    let stats = Rc::new(Stats::new(syscalls));
-    let reactor = Rc::new(FakeReactor::new(CONNS_MAX + 1, stats.clone()));
-    let spawner = BoxSpawner::new();
-    let executor = BoxExecutor::new(CONNS_MAX + 1);
+    #[test]
+    fn test_timeout() {
+        let clock = Rc::new(FakeClock::new());
+        let start = clock.now();
+        let stats = Rc::new(Stats::new(false));
+        let reactor = Rc::new(FakeReactor::with_clock(2, stats.clone(), clock.clone()));
+        let result = Cell::new(None);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(1);
 
-    executor.set_spawner(&spawner);
+        executor.set_spawner(&spawner);
 
-    run_fn(&mut || {
         {
-            let stats = stats.clone();
             let reactor = reactor.clone();
+            let result = &result;
 
             spawner
-                .spawn(async {
-                    listen_box::<LARGE_BUFSIZE>(&spawner, reactor, stats)
+                .spawn(async move {
+                    let ready = reactor
+                        .timeout(async { 42 }, Duration::from_millis(50))
                         .await
-                        .unwrap()
+                        .unwrap();
+                    assert_eq!(ready, 42);
+
+                    let e = reactor
+                        .timeout(std::future::pending::<()>(), Duration::from_millis(50))
+                        .await
+                        .unwrap_err();
+
+                    result.set(Some(e.kind()));
                 })
                 .unwrap();
         }
 
-        executor.run(|| reactor.poll());
-    });
-
-    stats.get()
-}
+        executor.run(|| {
+            clock.advance(Duration::from_millis(10));
+            reactor.poll()
+        });
 
-pub enum BoxRcMode {
-    RcWaker,
-    CheckedRcWaker,
-    ArcWaker,
-}
+        assert_eq!(result.get(), Some(io::ErrorKind::TimedOut));
+        assert_eq!(clock.now() - start, Duration::from_millis(50));
+        assert_eq!(stats.get().timer, 2);
+    }
 
-pub fn run_box_rc<R>(syscalls: bool, mode: BoxRcMode, mut run_fn: R) -> StatsMetrics
-where
-    R: FnMut(&mut dyn FnMut()),
-{
-    let stats = Rc::new(Stats::new(syscalls));
-    let reactor = Rc::new(FakeReactor::new(CONNS_MAX + 1, stats.clone()));
+    #[test]
+    fn test_timers_full() {
+        let clock = Rc::new(FakeClock::new());
+        let stats = Rc::new(Stats::new(false));
+        let reactor = Rc::new(FakeReactor::with_clock(1, stats.clone(), clock.clone()));
+        let done = Cell::new(false);
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(1);
 
-    let executor = Rc::new(match mode {
-        BoxRcMode::RcWaker => BoxRcExecutor::new(CONNS_MAX + 1, RcWakerFactory::default()),
-        BoxRcMode::CheckedRcWaker => {
-            BoxRcExecutor::new(CONNS_MAX + 1, CheckedRcWakerFactory::default())
-        }
-        BoxRcMode::ArcWaker => BoxRcExecutor::new(CONNS_MAX + 1, ArcWakerFactory::default()),
-    });
+        executor.set_spawner(&spawner);
 
-    run_fn(&mut || {
         {
-            let stats = stats.clone();
             let reactor = reactor.clone();
-            let executor_copy = executor.clone();
+            let done = &done;
 
-            executor
-                .spawn(async { listen_rc(executor_copy, reactor, stats).await.unwrap() })
-                .unwrap();
-        }
+            spawner
+                .spawn(async move {
+                    // takes the only timer slot
+                    let sleep = reactor.sleep(Duration::from_millis(100));
 
-        executor.run(|| reactor.poll());
-    });
+                    assert_eq!(
+                        reactor.sleep(Duration::from_millis(50)).await,
+                        Err(RegisterError::Full)
+                    );
 
-    stats.get()
-}
+                    let e = reactor
+                        .timeout(std::future::pending::<()>(), Duration::from_millis(50))
+                        .await
+                        .unwrap_err();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+                    assert_eq!(
+                        e.get_ref().unwrap().downcast_ref::<RegisterError>(),
+                        Some(&RegisterError::Full)
+                    );
 
-    const EXPECTED_STATS: StatsMetrics = StatsMetrics {
-        register: 257,
-        unregister: 257,
-        poll: 258,
-        accept: 512,
-        read: 512,
-        write: 512,
-    };
+                    // the slot is freed once the first timer is dropped
+                    drop(sleep);
 
-    #[test]
-    fn test_manual() {
-        assert_eq!(run_manual(false, |r| r()), EXPECTED_STATS);
-    }
+                    reactor.sleep(Duration::from_millis(50)).await.unwrap();
 
-    #[test]
-    fn test_nonbox() {
-        assert_eq!(run_nonbox(false, |r| r()), EXPECTED_STATS);
-    }
+                    done.set(true);
+                })
+                .unwrap();
+        }
 
-    #[test]
-    fn test_callerbox() {
-        assert_eq!(run_callerbox(false, |r| r()), EXPECTED_STATS);
-    }
+        executor.run(|| {
+            clock.advance(Duration::from_millis(10));
+            reactor.poll()
+        });
 
-    #[test]
-    fn test_large_nonbox() {
-        assert_eq!(run_large_nonbox(false, |r| r()), EXPECTED_STATS);
+        assert!(done.get());
+        assert_eq!(stats.get().timer, 2);
     }
 
     #[test]
-    fn test_box() {
-        assert_eq!(run_box(false, |r| r()), EXPECTED_STATS);
-    }
+    fn test_split() {
+        let stats = Stats::new(false);
+        let reactor = FakeReactor::new(1, &stats);
+        let received = Cell::new(0);
 
-    #[test]
-    fn test_box_callerbox() {
-        assert_eq!(run_box_callerbox(false, |r| r()), EXPECTED_STATS);
-    }
+        let mut stream = {
+            let listener = FakeListener::new(&stats, Scenario::DEFAULT);
 
-    #[test]
-    fn test_large_box() {
-        assert_eq!(run_large_box(false, |r| r()), EXPECTED_STATS);
-    }
+            // the first accept would block
+            assert!(listener.accept().is_err());
 
-    #[test]
-    fn test_box_rc() {
-        assert_eq!(
-            run_box_rc(false, BoxRcMode::RcWaker, |r| r()),
-            EXPECTED_STATS
-        );
+            AsyncFakeStream::new(listener.accept().unwrap(), &reactor).unwrap()
+        };
+
+        let (mut r, mut w) = stream.split();
+
+        let spawner = BoxSpawner::new();
+        let executor = BoxExecutor::new(2);
+
+        executor.set_spawner(&spawner);
+
+        {
+            let received = &received;
+
+            // the reader waits for readability while the writer is using
+            // the stream, and must still be woken afterwards
+            spawner
+                .spawn(async move {
+                    let mut buf = [0; SMALL_BUFSIZE];
+
+                    received.set(r.read(&mut buf).await.unwrap());
+                })
+                .unwrap();
+
+            spawner
+                .spawn(async move {
+                    w.write(b"hello\n").await.unwrap();
+                })
+                .unwrap();
+        }
+
+        let polls = Cell::new(0);
+
+        executor.run(|| {
+            polls.set(polls.get() + 1);
+            assert!(polls.get() < 10);
+
+            reactor.poll()
+        });
+
+        assert_eq!(received.get(), 12);
+        assert_eq!(stats.get().read, 3);
+        assert_eq!(stats.get().write, 1);
     }
 
     #[test]
-    fn test_box_chkrc() {
+    fn test_register_full() {
+        let stats = Stats::new(false);
+        let reactor = FakeReactor::new(1, &stats);
+
+        // takes the only registration
+        let listener = AsyncFakeListener::new(&reactor, &stats);
+
         assert_eq!(
-            run_box_rc(false, BoxRcMode::CheckedRcWaker, |r| r()),
-            EXPECTED_STATS
+            (&reactor)
+                .register(&FakeListener::new(&stats, Scenario::DEFAULT), READABLE)
+                .err(),
+            Some(RegisterError::Full)
         );
-    }
 
-    #[test]
-    fn test_box_arc() {
+        // an accepted stream that can't be registered is an error, not a
+        // panic
+        let l = FakeListener::new(&stats, Scenario::DEFAULT);
+        assert!(l.accept().is_err());
+
         assert_eq!(
-            run_box_rc(false, BoxRcMode::ArcWaker, |r| r()),
-            EXPECTED_STATS
+            AsyncFakeStream::new(l.accept().unwrap(), &reactor).err(),
+            Some(RegisterError::Full)
         );
+
+        // the registration is freed when the listener is dropped
+        drop(listener);
+        drop(AsyncFakeListener::new(&reactor, &stats));
     }
 }
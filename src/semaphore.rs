@@ -0,0 +1,503 @@
+// async counting semaphores. Semaphore is for single-threaded executors.
+// its waiters are kept in a slab allocated up front, and each keeps a clone
+// of its task's waker, so acquiring and releasing permits doesn't allocate
+// when the wakers don't allocate on clone, as with EmbedWaker. SyncSemaphore
+// is the same, protected by a mutex, for multi-threaded executors
+
+use crate::list;
+use slab::Slab;
+use std::cell::RefCell;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+struct SemaphoreData {
+    permits: usize,
+    waiters: Slab<list::Node<Option<Waker>>>,
+    // waiters in the order they started waiting. permits are handed out in
+    // this order
+    queue: list::List,
+}
+
+impl SemaphoreData {
+    fn new(permits: usize, waiters_max: usize) -> Self {
+        Self {
+            permits,
+            waiters: Slab::with_capacity(waiters_max),
+            queue: list::List::default(),
+        }
+    }
+
+    // the waker of the longest waiting acquire, if there is a permit for it
+    fn first_waker(&self) -> Option<&Waker> {
+        if self.permits == 0 {
+            return None;
+        }
+
+        self.queue
+            .head
+            .and_then(|key| self.waiters[key].value.as_ref())
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        if self.permits == 0 || !self.queue.is_empty() {
+            return false;
+        }
+
+        self.permits -= 1;
+
+        true
+    }
+
+    // key is set while waiting. returns true if a permit was taken, in which
+    // case there may be a permit for the next waiter too
+    fn poll_acquire(&mut self, key: &mut Option<usize>, waker: &Waker) -> bool {
+        let k = match *key {
+            Some(k) => k,
+            None => {
+                if self.try_acquire() {
+                    return true;
+                }
+
+                assert!(
+                    self.waiters.len() < self.waiters.capacity(),
+                    "too many waiters"
+                );
+
+                let k = self.waiters.insert(list::Node::new(None));
+                self.queue.push_back(&mut self.waiters, k);
+
+                *key = Some(k);
+
+                k
+            }
+        };
+
+        if self.permits > 0 && self.queue.head == Some(k) {
+            self.queue.remove(&mut self.waiters, k);
+            self.waiters.remove(k);
+            self.permits -= 1;
+
+            *key = None;
+
+            return true;
+        }
+
+        let current = &mut self.waiters[k].value;
+
+        match current {
+            Some(current) if current.will_wake(waker) => {}
+            _ => *current = Some(waker.clone()),
+        }
+
+        false
+    }
+
+    fn cancel(&mut self, key: usize) {
+        self.queue.remove(&mut self.waiters, key);
+        self.waiters.remove(key);
+    }
+}
+
+pub struct Semaphore {
+    data: RefCell<SemaphoreData>,
+}
+
+impl Semaphore {
+    // waiters_max is the most acquires that may be waiting at once
+    pub fn new(permits: usize, waiters_max: usize) -> Self {
+        Self {
+            data: RefCell::new(SemaphoreData::new(permits, waiters_max)),
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.data.borrow().permits
+    }
+
+    // takes a permit without waiting, unless there are none or others are
+    // already waiting for one
+    pub fn try_acquire(&self) -> Option<Permit<'_>> {
+        if !self.data.borrow_mut().try_acquire() {
+            return None;
+        }
+
+        Some(Permit { semaphore: self })
+    }
+
+    pub fn acquire(&self) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            key: None,
+        }
+    }
+
+    // like acquire, but the permit keeps the semaphore alive, so that it can
+    // be held by a task that must be 'static
+    pub async fn acquire_rc(self: Rc<Self>) -> RcPermit {
+        self.acquire().await.forget();
+
+        RcPermit { semaphore: self }
+    }
+
+    fn release(&self) {
+        let data = &mut *self.data.borrow_mut();
+
+        data.permits += 1;
+
+        if let Some(waker) = data.first_waker() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+// returns its permit to the semaphore when dropped
+pub struct Permit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Permit<'_> {
+    // keeps the permit taken, for a permit that is released some other way
+    fn forget(self) {
+        mem::forget(self);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+pub struct RcPermit {
+    semaphore: Rc<Semaphore>,
+}
+
+impl Drop for RcPermit {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    // set while waiting
+    key: Option<usize>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Permit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let f = &mut *self;
+        let data = &mut *f.semaphore.data.borrow_mut();
+
+        if !data.poll_acquire(&mut f.key, cx.waker()) {
+            return Poll::Pending;
+        }
+
+        if let Some(waker) = data.first_waker() {
+            waker.wake_by_ref();
+        }
+
+        Poll::Ready(Permit {
+            semaphore: f.semaphore,
+        })
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            let data = &mut *self.semaphore.data.borrow_mut();
+
+            data.cancel(key);
+
+            // if this waiter was woken for a permit, pass the wake on
+            if let Some(waker) = data.first_waker() {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+// wakers are called after the lock is released, since they may call back
+// into the semaphore
+pub struct SyncSemaphore {
+    data: Mutex<SemaphoreData>,
+}
+
+impl SyncSemaphore {
+    // waiters_max is the most acquires that may be waiting at once
+    pub fn new(permits: usize, waiters_max: usize) -> Self {
+        Self {
+            data: Mutex::new(SemaphoreData::new(permits, waiters_max)),
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.data.lock().unwrap().permits
+    }
+
+    // the permit keeps the semaphore alive, so that it can be held by a
+    // task that must be 'static
+    pub async fn acquire_arc(self: Arc<Self>) -> ArcPermit {
+        SyncAcquire {
+            semaphore: &self,
+            key: None,
+        }
+        .await;
+
+        ArcPermit { semaphore: self }
+    }
+
+    fn release(&self) {
+        let waker = {
+            let data = &mut *self.data.lock().unwrap();
+
+            data.permits += 1;
+
+            data.first_waker().cloned()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+// returns its permit to the semaphore when dropped
+pub struct ArcPermit {
+    semaphore: Arc<SyncSemaphore>,
+}
+
+impl Drop for ArcPermit {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+// resolves once a permit has been taken. the caller is responsible for
+// releasing it
+struct SyncAcquire<'a> {
+    semaphore: &'a SyncSemaphore,
+    // set while waiting
+    key: Option<usize>,
+}
+
+impl Future for SyncAcquire<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let f = &mut *self;
+
+        let waker = {
+            let data = &mut *f.semaphore.data.lock().unwrap();
+
+            if !data.poll_acquire(&mut f.key, cx.waker()) {
+                return Poll::Pending;
+            }
+
+            data.first_waker().cloned()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+
+        Poll::Ready(())
+    }
+}
+
+impl Drop for SyncAcquire<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            let waker = {
+                let data = &mut *self.semaphore.data.lock().unwrap();
+
+                data.cancel(key);
+
+                // if this waiter was woken for a permit, pass the wake on
+                data.first_waker().cloned()
+            };
+
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct CountWaker(AtomicUsize);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn count_waker() -> (Arc<CountWaker>, Waker) {
+        let w = Arc::new(CountWaker(AtomicUsize::new(0)));
+
+        (w.clone(), Waker::from(w))
+    }
+
+    #[test]
+    fn test_try_acquire() {
+        let s = Semaphore::new(2, 1);
+
+        let p1 = s.try_acquire().unwrap();
+        let p2 = s.try_acquire().unwrap();
+        assert!(s.try_acquire().is_none());
+        assert_eq!(s.available_permits(), 0);
+
+        drop(p1);
+        assert_eq!(s.available_permits(), 1);
+
+        drop(p2);
+        assert_eq!(s.available_permits(), 2);
+    }
+
+    #[test]
+    fn test_acquire() {
+        let s = Semaphore::new(1, 2);
+        let (w1, waker1) = count_waker();
+        let (w2, waker2) = count_waker();
+
+        let p = s.try_acquire().unwrap();
+
+        let mut a1 = s.acquire();
+        let mut a2 = s.acquire();
+
+        assert!(Pin::new(&mut a1)
+            .poll(&mut Context::from_waker(&waker1))
+            .is_pending());
+        assert!(Pin::new(&mut a2)
+            .poll(&mut Context::from_waker(&waker2))
+            .is_pending());
+
+        // waiters are served in order, and can't be cut in front of
+        drop(p);
+        assert!(s.try_acquire().is_none());
+        assert_eq!(w1.0.load(Ordering::Relaxed), 1);
+        assert_eq!(w2.0.load(Ordering::Relaxed), 0);
+
+        assert!(Pin::new(&mut a2)
+            .poll(&mut Context::from_waker(&waker2))
+            .is_pending());
+
+        let p = match Pin::new(&mut a1).poll(&mut Context::from_waker(&waker1)) {
+            Poll::Ready(p) => p,
+            Poll::Pending => panic!("expected permit"),
+        };
+
+        drop(p);
+        assert_eq!(w2.0.load(Ordering::Relaxed), 1);
+
+        assert!(Pin::new(&mut a2)
+            .poll(&mut Context::from_waker(&waker2))
+            .is_ready());
+        assert_eq!(s.available_permits(), 1);
+    }
+
+    #[test]
+    fn test_acquire_drop() {
+        let s = Semaphore::new(1, 2);
+        let (w2, waker2) = count_waker();
+
+        let p = s.try_acquire().unwrap();
+
+        let mut a1 = s.acquire();
+        let mut a2 = s.acquire();
+
+        assert!(Pin::new(&mut a1)
+            .poll(&mut Context::from_waker(Waker::noop()))
+            .is_pending());
+        assert!(Pin::new(&mut a2)
+            .poll(&mut Context::from_waker(&waker2))
+            .is_pending());
+
+        // the first waiter is woken, but goes away without taking the
+        // permit, so the wake is passed on
+        drop(p);
+        drop(a1);
+        assert_eq!(w2.0.load(Ordering::Relaxed), 1);
+
+        assert!(Pin::new(&mut a2)
+            .poll(&mut Context::from_waker(&waker2))
+            .is_ready());
+    }
+
+    #[test]
+    fn test_acquire_rc() {
+        let s = Rc::new(Semaphore::new(1, 1));
+        let (w, waker) = count_waker();
+
+        let p = s.try_acquire().unwrap();
+
+        let mut a = Box::pin(s.clone().acquire_rc());
+
+        assert!(a
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+
+        drop(p);
+        assert_eq!(w.0.load(Ordering::Relaxed), 1);
+
+        let p = match a.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(p) => p,
+            Poll::Pending => panic!("expected permit"),
+        };
+
+        assert_eq!(s.available_permits(), 0);
+
+        drop(p);
+        assert_eq!(s.available_permits(), 1);
+    }
+
+    #[test]
+    fn test_sync_acquire() {
+        let s = Arc::new(SyncSemaphore::new(1, 1));
+        let (w, waker) = count_waker();
+
+        let mut a1 = Box::pin(s.clone().acquire_arc());
+
+        let p = match a1.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(p) => p,
+            Poll::Pending => panic!("expected permit"),
+        };
+
+        let mut a2 = Box::pin(s.clone().acquire_arc());
+
+        assert!(a2
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+        assert_eq!(w.0.load(Ordering::Relaxed), 0);
+
+        // the permit can be released from another thread
+        std::thread::spawn(move || drop(p)).join().unwrap();
+        assert_eq!(w.0.load(Ordering::Relaxed), 1);
+
+        let p = match a2.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(p) => p,
+            Poll::Pending => panic!("expected permit"),
+        };
+
+        assert_eq!(s.available_permits(), 0);
+
+        drop(p);
+        assert_eq!(s.available_permits(), 1);
+    }
+}
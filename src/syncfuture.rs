@@ -0,0 +1,378 @@
+// thread-safe versions of the reactor and async I/O objects in future.rs,
+// for use with multi-threaded executors. reactor state is protected by a
+// mutex, and the I/O objects are Send so tasks can move between threads
+
+use crate::asyncio::{AsyncRead, AsyncWrite};
+use crate::fakeio;
+use crate::fakeio::{Evented, FakeListener, FakeStream, Scenario, Stats, READABLE, WRITABLE};
+use crate::future::RegisterError;
+use crate::registration::{EventRegistration, Reactor, ReactorRef, RegistrationHandle};
+use slab::Slab;
+use std::future::Future;
+use std::io;
+use std::io::{Read, Write};
+use std::mem;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+pub trait SyncFakeReactorRef<T>: ReactorRef<Reactor = SyncFakeReactor<T>>
+where
+    T: Stats,
+{
+    fn register<E: Evented>(
+        &self,
+        handle: &E,
+        interest: u8,
+    ) -> Result<RegistrationHandle<Self>, RegisterError> {
+        let reg = RegistrationHandle::new(self.clone())?;
+
+        let data = &mut *self.get().data.lock().unwrap();
+
+        data.poll.register(handle, reg.key(), interest);
+
+        Ok(reg)
+    }
+}
+
+impl<T, R> SyncFakeReactorRef<T> for R
+where
+    T: Stats,
+    R: ReactorRef<Reactor = SyncFakeReactor<T>>,
+{
+}
+
+struct SyncFakeReactorData<T> {
+    registrations: Slab<EventRegistration>,
+    events: Slab<(usize, u8)>,
+    poll: fakeio::Poll<T>,
+    // reused by poll to collect wakers, so they can be called after the
+    // lock is released
+    wakers: Vec<Waker>,
+}
+
+pub struct SyncFakeReactor<T> {
+    data: Mutex<SyncFakeReactorData<T>>,
+}
+
+impl<T> SyncFakeReactor<T>
+where
+    T: Stats,
+{
+    pub fn new(registrations_max: usize, stats: T) -> Self {
+        let data = SyncFakeReactorData {
+            registrations: Slab::with_capacity(registrations_max),
+            events: Slab::with_capacity(128),
+            poll: fakeio::Poll::new(128, stats),
+            wakers: Vec::with_capacity(128),
+        };
+
+        Self {
+            data: Mutex::new(data),
+        }
+    }
+
+    // may be called from several threads at once. wakers are called after
+    // the reactor is unlocked, so they may call back into the reactor
+    pub fn poll(&self) -> Result<(), io::Error> {
+        let mut wakers = {
+            let data = &mut *self.data.lock().unwrap();
+
+            data.poll.poll(&mut data.events);
+
+            // if another thread is polling, its buffer is away and we get
+            // an empty one
+            let mut wakers = mem::take(&mut data.wakers);
+
+            for (_, &(key, readiness)) in data.events.iter() {
+                if let Some(event_reg) = data.registrations.get_mut(key) {
+                    event_reg.set_readiness(readiness, |waker| wakers.push(waker));
+                }
+            }
+
+            wakers
+        };
+
+        for waker in wakers.drain(..) {
+            waker.wake();
+        }
+
+        // return the buffer for reuse, keeping the larger one
+        let data = &mut *self.data.lock().unwrap();
+
+        if wakers.capacity() > data.wakers.capacity() {
+            data.wakers = wakers;
+        }
+
+        Ok(())
+    }
+
+    fn unregister<E: Evented>(&self, handle: &E) {
+        self.data.lock().unwrap().poll.unregister(handle);
+    }
+}
+
+impl<T> Reactor for SyncFakeReactor<T> {
+    fn with_registrations<F, V>(&self, f: F) -> V
+    where
+        F: FnOnce(&mut Slab<EventRegistration>) -> V,
+    {
+        f(&mut self.data.lock().unwrap().registrations)
+    }
+}
+
+pub struct SyncFakeStream<T, R>
+where
+    T: Stats,
+    R: SyncFakeReactorRef<T>,
+{
+    inner: FakeStream<T>,
+    handle: RegistrationHandle<R>,
+}
+
+impl<T, R> SyncFakeStream<T, R>
+where
+    T: Stats + Clone,
+    R: SyncFakeReactorRef<T>,
+{
+    pub fn new(s: FakeStream<T>, reactor: R) -> Result<Self, RegisterError> {
+        let handle = reactor.register(&s, READABLE | WRITABLE)?;
+
+        handle.set_ready(READABLE | WRITABLE, true);
+
+        Ok(Self { inner: s, handle })
+    }
+}
+
+impl<T, R> Drop for SyncFakeStream<T, R>
+where
+    T: Stats,
+    R: SyncFakeReactorRef<T>,
+{
+    fn drop(&mut self) {
+        self.handle.reactor().get().unregister(&self.inner);
+    }
+}
+
+impl<T, R> AsyncRead for SyncFakeStream<T, R>
+where
+    T: Stats,
+    R: SyncFakeReactorRef<T>,
+{
+    fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, io::Error>> {
+        let inner = &mut self.inner;
+
+        self.handle.poll_io(READABLE, cx, || inner.read(buf))
+    }
+}
+
+impl<T, R> AsyncWrite for SyncFakeStream<T, R>
+where
+    T: Stats,
+    R: SyncFakeReactorRef<T>,
+{
+    fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let inner = &mut self.inner;
+
+        self.handle.poll_io(WRITABLE, cx, || inner.write(buf))
+    }
+}
+
+pub struct SyncFakeListener<T, R>
+where
+    T: Stats,
+    R: SyncFakeReactorRef<T>,
+{
+    inner: FakeListener<T>,
+    handle: RegistrationHandle<R>,
+}
+
+impl<T, R> SyncFakeListener<T, R>
+where
+    T: Stats + Clone,
+    R: SyncFakeReactorRef<T>,
+{
+    pub fn new(reactor: R, stats: T, scenario: Scenario) -> Self {
+        let l = FakeListener::new(stats, scenario);
+
+        let handle = reactor.register(&l, READABLE).unwrap();
+
+        handle.set_ready(READABLE, true);
+
+        Self { inner: l, handle }
+    }
+
+    // takes &mut so that the future is Send. the inner listener is not Sync
+    pub fn accept<'a>(&'a mut self) -> AcceptFuture<'a, T, R> {
+        AcceptFuture { l: self }
+    }
+}
+
+impl<T, R> Drop for SyncFakeListener<T, R>
+where
+    T: Stats,
+    R: SyncFakeReactorRef<T>,
+{
+    fn drop(&mut self) {
+        self.handle.reactor().get().unregister(&self.inner);
+    }
+}
+
+pub struct AcceptFuture<'a, T, R>
+where
+    T: Stats,
+    R: SyncFakeReactorRef<T>,
+{
+    l: &'a mut SyncFakeListener<T, R>,
+}
+
+impl<T, R> Future for AcceptFuture<'_, T, R>
+where
+    T: Stats + Clone,
+    R: SyncFakeReactorRef<T>,
+{
+    type Output = Result<SyncFakeStream<T, R>, io::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let f = &mut *self;
+
+        let inner = &f.l.inner;
+        let reactor = f.l.handle.reactor();
+
+        f.l.handle.poll_io(READABLE, cx, || {
+            let stream = inner.accept()?;
+
+            Ok(SyncFakeStream::new(stream, reactor.clone())?)
+        })
+    }
+}
+
+impl<T, R> Drop for AcceptFuture<'_, T, R>
+where
+    T: Stats,
+    R: SyncFakeReactorRef<T>,
+{
+    fn drop(&mut self) {
+        self.l.handle.unbind_waker(READABLE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    #[derive(Clone)]
+    struct NoStats;
+
+    impl Stats for NoStats {
+        fn inc(&self, _t: fakeio::StatsType) {}
+    }
+
+    type TestReactor = Arc<SyncFakeReactor<NoStats>>;
+
+    impl ReactorRef for TestReactor {
+        type Reactor = SyncFakeReactor<NoStats>;
+
+        fn get(&self) -> &SyncFakeReactor<NoStats> {
+            self.as_ref()
+        }
+    }
+
+    // polls the reactor again when woken
+    struct ReentrantWaker {
+        reactor: TestReactor,
+        woken: AtomicBool,
+    }
+
+    impl Wake for ReentrantWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.woken.store(true, Ordering::Relaxed);
+
+            self.reactor.poll().unwrap();
+        }
+    }
+
+    struct FlagWaker(AtomicBool);
+
+    impl Wake for FlagWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_read_write_wakers() {
+        let reactor = Arc::new(SyncFakeReactor::new(2, NoStats));
+        let mut listener = SyncFakeListener::new(reactor.clone(), NoStats, Scenario::SLOW);
+
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut stream = {
+            let mut fut = listener.accept();
+
+            // the first accept would block
+            assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+
+            reactor.poll().unwrap();
+
+            match Pin::new(&mut fut).poll(&mut cx) {
+                Poll::Ready(Ok(stream)) => stream,
+                _ => panic!("expected stream"),
+            }
+        };
+
+        let read_flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+        let write_flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+
+        let read_waker = Waker::from(read_flag.clone());
+        let write_waker = Waker::from(write_flag.clone());
+
+        let mut buf = [0; 16];
+
+        // both would block, and each direction keeps its own waker
+        assert!(stream
+            .poll_read(&mut Context::from_waker(&read_waker), &mut buf)
+            .is_pending());
+        assert!(stream
+            .poll_write(&mut Context::from_waker(&write_waker), b"hello")
+            .is_pending());
+
+        reactor.poll().unwrap();
+
+        assert!(read_flag.0.load(Ordering::Relaxed));
+        assert!(write_flag.0.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_wake_reentrant() {
+        let reactor = Arc::new(SyncFakeReactor::new(1, NoStats));
+
+        let waker = Arc::new(ReentrantWaker {
+            reactor: reactor.clone(),
+            woken: AtomicBool::new(false),
+        });
+
+        let mut listener = SyncFakeListener::new(reactor.clone(), NoStats, Scenario::DEFAULT);
+
+        {
+            let std_waker = Waker::from(waker.clone());
+            let mut cx = Context::from_waker(&std_waker);
+
+            let mut fut = listener.accept();
+
+            // the first accept would block
+            assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+
+            reactor.poll().unwrap();
+
+            assert!(waker.woken.load(Ordering::Relaxed));
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use crate::list;
+use slab::Slab;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// each level of the wheel has 64 slots, and each slot of a level covers the
+// entire range of the level below it
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 6;
+
+// the furthest into the future a timer can be placed without wrapping around
+const MAX_DURATION: u64 = (1 << (SLOT_BITS as usize * LEVELS)) - 1;
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Default)]
+pub struct RealClock {}
+
+impl Clock for RealClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+pub struct FakeClock {
+    now: Cell<Instant>,
+}
+
+impl FakeClock {
+    pub fn new() -> Self {
+        Self {
+            now: Cell::new(Instant::now()),
+        }
+    }
+
+    pub fn advance(&self, d: Duration) {
+        self.now.set(self.now.get() + d);
+    }
+}
+
+impl Default for FakeClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
+
+impl<C: Clock> Clock for Rc<C> {
+    fn now(&self) -> Instant {
+        self.as_ref().now()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Position {
+    Wheel(usize, usize),
+    Expired,
+    None,
+}
+
+struct Timer<T> {
+    expires: u64,
+    position: Position,
+    value: T,
+}
+
+struct Level {
+    occupied: u64,
+    slots: [list::List; SLOTS],
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            occupied: 0,
+            slots: [list::List::default(); SLOTS],
+        }
+    }
+}
+
+impl Level {
+    fn slot_range(level: usize) -> u64 {
+        1 << (SLOT_BITS as usize * level)
+    }
+
+    fn next_occupied_slot(&self, level: usize, now: u64) -> Option<usize> {
+        if self.occupied == 0 {
+            return None;
+        }
+
+        let now_slot = now / Self::slot_range(level);
+
+        // truncating the shift amount is fine since rotation is mod 64
+        let occupied = self.occupied.rotate_right(now_slot as u32);
+        let zeros = occupied.trailing_zeros() as usize;
+
+        Some((zeros + now_slot as usize) % SLOTS)
+    }
+}
+
+// hierarchical timer wheel, with times measured in ticks. timers are stored
+// in a slab and linked into per-slot lists, so adding and removing timers
+// is O(1) and never allocates
+pub struct TimerWheel<T> {
+    nodes: Slab<list::Node<Timer<T>>>,
+    levels: [Level; LEVELS],
+    expired: list::List,
+    elapsed: u64,
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            nodes: Slab::with_capacity(capacity),
+            levels: Default::default(),
+            expired: list::List::default(),
+            elapsed: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn add(&mut self, expires: u64, value: T) -> Result<usize, T> {
+        if self.nodes.len() == self.nodes.capacity() {
+            return Err(value);
+        }
+
+        let key = self.nodes.insert(list::Node::new(Timer {
+            expires,
+            position: Position::None,
+            value,
+        }));
+
+        self.link(key);
+
+        Ok(key)
+    }
+
+    pub fn remove(&mut self, key: usize) -> Option<T> {
+        self.nodes.get(key)?;
+
+        self.unlink(key);
+
+        Some(self.nodes.remove(key).value.value)
+    }
+
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        self.nodes.get_mut(key).map(|n| &mut n.value.value)
+    }
+
+    // moves all timers due at or before now to the expired list
+    pub fn advance(&mut self, now: u64) {
+        while let Some((level, slot, deadline)) = self.next_expiration() {
+            if deadline > now {
+                break;
+            }
+
+            self.elapsed = deadline;
+
+            let mut l = self.levels[level].slots[slot];
+            self.levels[level].slots[slot] = list::List::default();
+            self.levels[level].occupied &= !(1 << slot);
+
+            // timers either expire or cascade down to a lower level
+            while let Some(key) = l.pop_front(&mut self.nodes) {
+                self.nodes[key].value.position = Position::None;
+
+                self.link(key);
+            }
+        }
+
+        if now > self.elapsed {
+            self.elapsed = now;
+        }
+    }
+
+    // takes the next expired timer. it stays allocated until removed
+    pub fn take_expired(&mut self) -> Option<usize> {
+        let key = self.expired.pop_front(&mut self.nodes)?;
+
+        self.nodes[key].value.position = Position::None;
+
+        Some(key)
+    }
+
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        for level in 0..LEVELS {
+            if let Some(slot) = self.levels[level].next_occupied_slot(level, self.elapsed) {
+                let slot_range = Level::slot_range(level);
+                let level_range = slot_range * SLOTS as u64;
+
+                let level_start = self.elapsed & !(level_range - 1);
+                let mut deadline = level_start + slot as u64 * slot_range;
+
+                // a slot "before" the current time can only happen at the
+                // top level, for timers beyond MAX_DURATION that wrapped
+                if deadline <= self.elapsed {
+                    deadline += level_range;
+                }
+
+                return Some((level, slot, deadline));
+            }
+        }
+
+        None
+    }
+
+    fn link(&mut self, key: usize) {
+        let expires = self.nodes[key].value.expires;
+
+        let position = if expires <= self.elapsed {
+            self.expired.push_back(&mut self.nodes, key);
+
+            Position::Expired
+        } else {
+            let level = level_for(self.elapsed, expires);
+            let slot = ((expires >> (SLOT_BITS as usize * level)) % SLOTS as u64) as usize;
+
+            let l = &mut self.levels[level];
+            l.slots[slot].push_back(&mut self.nodes, key);
+            l.occupied |= 1 << slot;
+
+            Position::Wheel(level, slot)
+        };
+
+        self.nodes[key].value.position = position;
+    }
+
+    fn unlink(&mut self, key: usize) {
+        match self.nodes[key].value.position {
+            Position::Wheel(level, slot) => {
+                let l = &mut self.levels[level];
+                l.slots[slot].remove(&mut self.nodes, key);
+
+                if l.slots[slot].is_empty() {
+                    l.occupied &= !(1 << slot);
+                }
+            }
+            Position::Expired => self.expired.remove(&mut self.nodes, key),
+            Position::None => {}
+        }
+
+        self.nodes[key].value.position = Position::None;
+    }
+}
+
+fn level_for(elapsed: u64, when: u64) -> usize {
+    // mask in the low bits so the result is at least level 0
+    let mut masked = (elapsed ^ when) | (SLOTS as u64 - 1);
+
+    if masked >= MAX_DURATION {
+        masked = MAX_DURATION - 1;
+    }
+
+    let significant = 63 - masked.leading_zeros() as usize;
+
+    significant / SLOT_BITS as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expired(w: &mut TimerWheel<&'static str>) -> Vec<&'static str> {
+        let mut out = Vec::new();
+
+        while let Some(key) = w.take_expired() {
+            out.push(w.remove(key).unwrap());
+        }
+
+        out
+    }
+
+    #[test]
+    fn test_level_for() {
+        assert_eq!(level_for(0, 1), 0);
+        assert_eq!(level_for(0, 63), 0);
+        assert_eq!(level_for(0, 64), 1);
+        assert_eq!(level_for(0, 4095), 1);
+        assert_eq!(level_for(0, 4096), 2);
+        assert_eq!(level_for(100, 120), 0);
+        assert_eq!(level_for(0, u64::MAX), LEVELS - 1);
+    }
+
+    #[test]
+    fn test_wheel_expire() {
+        let mut w = TimerWheel::new(8);
+
+        w.add(5, "a").unwrap();
+        w.add(70, "b").unwrap();
+        w.add(5000, "c").unwrap();
+        w.add(0, "now").unwrap();
+
+        assert_eq!(expired(&mut w), vec!["now"]);
+
+        w.advance(4);
+        assert!(expired(&mut w).is_empty());
+
+        w.advance(5);
+        assert_eq!(expired(&mut w), vec!["a"]);
+
+        w.advance(69);
+        assert!(expired(&mut w).is_empty());
+
+        w.advance(1000);
+        assert_eq!(expired(&mut w), vec!["b"]);
+
+        w.advance(4999);
+        assert!(expired(&mut w).is_empty());
+
+        w.advance(100_000);
+        assert_eq!(expired(&mut w), vec!["c"]);
+
+        assert!(w.is_empty());
+    }
+
+    #[test]
+    fn test_wheel_remove() {
+        let mut w = TimerWheel::new(2);
+
+        let a = w.add(10, "a").unwrap();
+        w.add(20, "b").unwrap();
+
+        // full
+        assert_eq!(w.add(30, "c"), Err("c"));
+
+        assert_eq!(w.remove(a), Some("a"));
+        assert_eq!(w.remove(a), None);
+
+        w.advance(100);
+        assert_eq!(expired(&mut w), vec!["b"]);
+    }
+
+    #[test]
+    fn test_wheel_far_future() {
+        let mut w = TimerWheel::new(1);
+
+        let when = MAX_DURATION * 2;
+
+        w.add(when, "far").unwrap();
+
+        w.advance(when - 1);
+        assert!(expired(&mut w).is_empty());
+
+        w.advance(when);
+        assert_eq!(expired(&mut w), vec!["far"]);
+    }
+
+    #[test]
+    fn test_fake_clock() {
+        let clock = FakeClock::new();
+        let start = clock.now();
+
+        clock.advance(Duration::from_millis(10));
+
+        assert_eq!(clock.now() - start, Duration::from_millis(10));
+    }
+}
@@ -11,10 +11,15 @@ use std::thread::{self, ThreadId};
 
 pub trait EmbedWake {
     fn wake(&self, task_id: usize);
+
+    // called when the last clone of an orphaned waker is dropped, after
+    // which the owner may free the waker
+    fn free(&self, task_id: usize);
 }
 
 pub struct EmbedWaker<'a, W> {
     refs: Cell<usize>,
+    orphaned: Cell<bool>,
     wake: &'a W,
     task_id: usize,
 }
@@ -26,6 +31,7 @@ where
     pub fn new(wake: &'a W, task_id: usize) -> Self {
         Self {
             refs: Cell::new(1),
+            orphaned: Cell::new(false),
             wake,
             task_id,
         }
@@ -35,6 +41,15 @@ where
         self.refs.get()
     }
 
+    // marks the waker as belonging to a task that is gone, while clones of
+    // it are still held elsewhere. the owner must keep the waker in place
+    // until it is told to free it
+    pub fn orphan(&self) {
+        assert!(self.refs.get() > 1);
+
+        self.orphaned.set(true);
+    }
+
     pub fn as_std<'out>(
         self: Pin<&mut Self>,
         output_mem: &'out mut MaybeUninit<Waker>,
@@ -82,6 +97,11 @@ where
         assert!(refs > 1);
 
         s.refs.set(refs - 1);
+
+        // the owner may free the waker, so it must not be used after this
+        if refs == 2 && s.orphaned.get() {
+            s.wake.free(s.task_id);
+        }
     }
 }
 
@@ -96,7 +116,7 @@ pub trait LocalWake {
 #[inline(always)]
 fn is_current_thread(id: ThreadId) -> bool {
     thread_local! {
-        static CURRENT_THREAD: RefCell<Option<ThreadId>> = RefCell::new(None);
+        static CURRENT_THREAD: RefCell<Option<ThreadId>> = const { RefCell::new(None) };
     }
 
     CURRENT_THREAD.with(|v| id == *v.borrow_mut().get_or_insert_with(|| thread::current().id()))
@@ -130,7 +150,7 @@ fn raw_waker<W: LocalWake>(waker: Rc<W>) -> RawWaker {
     unsafe fn clone_waker<W: LocalWake>(waker: *const ()) -> RawWaker {
         Rc::increment_strong_count(waker as *const W);
         RawWaker::new(
-            waker as *const (),
+            waker,
             &RawWakerVTable::new(
                 clone_waker::<W>,
                 wake::<W>,